@@ -0,0 +1,506 @@
+//! Access control of targets by ordered allow and deny rules.
+
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use {ParsedTarget, RequestTarget};
+use glob::Glob;
+use decode::decoded_unit;
+use matcher::TargetMatcher;
+use normalize::{collapse_slashes_in_place, decode_unreserved_into,
+                remove_dot_segments_in_place};
+
+/// Length of the stack buffer that a path is normalized into before it's matched.
+const PATH_LEN: usize = 2048;
+
+/// Length of the stack buffer that a host is decoded into before it's matched.
+const HOST_LEN: usize = 256;
+
+/// Action taken for a target under an access control list.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Action {
+    /// Let the request through.
+    Allow,
+    /// Reject the request.
+    Deny,
+}
+
+/// Rule of an access control list, taking an action for targets that meet all of its
+/// conditions.
+///
+/// A rule with no conditions matches every target, so it can end a list as a catch-all.
+/// Each condition only matches targets that have the component it checks, so a `host`
+/// rule never matches an origin-form target, whose host is only in the `Host` header,
+/// and a `path` rule never matches an authority-form or asterisk-form target.
+///
+/// Paths and hosts are normalized before they're compared, so that the usual alternate
+/// spellings of a target can't slip past a deny rule. A path has its escaped unreserved
+/// characters decoded, its runs of `/` collapsed, and then its dot segments removed, so
+/// `/./admin/x`, `//admin/x`, and `/a/../%61dmin/x` all match `/admin/**`. A host is
+/// percent-decoded and loses one trailing `.`, so `EV%69L.com` and `evil.com.` both
+/// match `evil.com`. A path or host too long to normalize on the stack, or a host that
+/// decodes to a delimiter like `#` or `/`, matches a deny rule but never an allow rule.
+///
+/// **This doesn't cover every equivalence a backend may apply**, such as case-insensitive
+/// paths, backslashes as separators, or numeric spellings of IP addresses. A frontend
+/// enforcing rules for a backend should normalize each target the way the backend
+/// does, such as with `NormalizeOptions`, and forward that normalized target, so that
+/// the target the rules allowed is the one the backend serves.
+///
+/// ```rust
+/// use uhttp_request_target::{Action, Glob, ParsedTarget, RequestTarget, Rule};
+///
+/// let rule = Rule::deny()
+///     .form(RequestTarget::AbsPath)
+///     .path(Glob::parse("/admin/**").unwrap());
+///
+/// assert!(rule.matches(&ParsedTarget::parse("/admin/users").unwrap()));
+/// assert!(!rule.matches(&ParsedTarget::parse("/public").unwrap()));
+/// assert_eq!(rule.action(), Action::Deny);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Rule<'p> {
+    action: Action,
+    form: Option<RequestTarget>,
+    path: Option<Glob<'p>>,
+    host: Option<&'p str>,
+    scheme: Option<&'p str>,
+    matcher: Option<Custom<'p>>,
+}
+
+/// Custom condition of a rule, compared by the address of the matcher.
+#[derive(Copy, Clone)]
+struct Custom<'p>(&'p dyn TargetMatcher);
+
+impl<'p> Custom<'p> {
+    /// Retrieve the address of the matcher, without its vtable.
+    fn addr(&self) -> *const () { self.0 as *const dyn TargetMatcher as *const () }
+}
+
+impl<'p> PartialEq for Custom<'p> {
+    fn eq(&self, other: &Self) -> bool { self.addr() == other.addr() }
+}
+
+impl<'p> Eq for Custom<'p> {}
+
+impl<'p> Hash for Custom<'p> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.addr().hash(state) }
+}
+
+impl<'p> fmt::Debug for Custom<'p> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Custom").field(&self.addr()).finish()
+    }
+}
+
+impl<'p> Rule<'p> {
+    /// Create a rule that takes the given action for every target.
+    pub const fn new(action: Action) -> Self {
+        Rule {
+            action,
+            form: None,
+            path: None,
+            host: None,
+            scheme: None,
+            matcher: None,
+        }
+    }
+
+    /// Create a rule that allows every target.
+    pub const fn allow() -> Self { Rule::new(Action::Allow) }
+
+    /// Create a rule that denies every target.
+    pub const fn deny() -> Self { Rule::new(Action::Deny) }
+
+    /// Only match targets of the given form.
+    pub const fn form(mut self, form: RequestTarget) -> Self {
+        self.form = Some(form);
+        self
+    }
+
+    /// Only match origin-form and absolute-form targets whose normalized path matches
+    /// the given glob, where an empty absolute-form path is treated like `/`.
+    pub const fn path(mut self, glob: Glob<'p>) -> Self {
+        self.path = Some(glob);
+        self
+    }
+
+    /// Only match absolute-form and authority-form targets with the given host,
+    /// compared case-insensitively to the decoded host without any trailing `.`.
+    ///
+    /// A host starting with `*.` matches any subdomain of the rest, so `*.example.com`
+    /// matches `api.example.com` and `a.b.example.com` but not `example.com` itself.
+    pub const fn host(mut self, host: &'p str) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Only match absolute-form targets with the given scheme, compared
+    /// case-insensitively.
+    pub const fn scheme(mut self, scheme: &'p str) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    /// Only match targets that the given custom matcher matches.
+    ///
+    /// Rules with custom matchers compare equal only if they borrow the same matcher.
+    pub const fn matcher(mut self, matcher: &'p dyn TargetMatcher) -> Self {
+        self.matcher = Some(Custom(matcher));
+        self
+    }
+
+    /// Retrieve the action taken by the rule.
+    pub const fn action(&self) -> Action { self.action }
+
+    /// Check if the given target meets all the conditions of the rule.
+    pub fn matches(&self, target: &ParsedTarget) -> bool {
+        let form = self.form.is_none_or(|form| target.kind() == form);
+
+        // A component that can't be normalized only matches if that fails closed.
+        let closed = self.action == Action::Deny;
+
+        let path = self.path.is_none_or(|glob| {
+            let path = match *target {
+                ParsedTarget::AbsPath(ref t) => t.path(),
+                ParsedTarget::AbsUri(ref t) => t.path_or_root(),
+                ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => return false,
+            };
+
+            let mut buf = [0; PATH_LEN];
+            normalize_path(path, &mut buf).map_or(closed, |path| glob.is_match(path))
+        });
+
+        let host = self.host.is_none_or(|rule| {
+            let host = match *target {
+                ParsedTarget::AbsUri(ref t) => t.host(),
+                ParsedTarget::Authority(ref t) => t.host(),
+                ParsedTarget::AbsPath(_) | ParsedTarget::ServerOptions => return false,
+            };
+
+            let mut buf = [0; HOST_LEN];
+            decode_host(host, &mut buf).map_or(closed, |host| host_match(rule, host))
+        });
+
+        let scheme = self.scheme.is_none_or(|scheme| match *target {
+            ParsedTarget::AbsUri(ref t) => t.scheme().eq_ignore_ascii_case(scheme),
+            _ => false,
+        });
+
+        let custom = self.matcher.is_none_or(|Custom(m)| m.matches(target));
+
+        form && path && host && scheme && custom
+    }
+}
+
+/// Normalize the given path into the given buffer for matching, or return `None` if it
+/// doesn't fit.
+fn normalize_path<'b>(path: &str, buf: &'b mut [u8]) -> Option<&'b str> {
+    let len = decode_unreserved_into(path.as_bytes(), buf).ok()?;
+    let len = collapse_slashes_in_place(&mut buf[..len]);
+    let len = remove_dot_segments_in_place(&mut buf[..len]);
+
+    // Only escapes of ASCII characters were decoded, so the path is still UTF-8.
+    core::str::from_utf8(&buf[..len]).ok()
+}
+
+/// Decode the given raw host into the given buffer without any trailing `.`, or return
+/// `None` if it doesn't fit or decodes to a delimiter that can't be in a host.
+fn decode_host<'b>(host: &str, buf: &'b mut [u8]) -> Option<&'b [u8]> {
+    // The host never runs into a fragment, but one would change what it names.
+    let host = host.as_bytes();
+    let host = &host[..host.iter().position(|&b| b == b'#').unwrap_or(host.len())];
+    let (mut idx, mut len) = (0, 0);
+
+    while idx < host.len() {
+        let (b, n) = decoded_unit(host, idx);
+
+        if b"#/?@\\".contains(&b) {
+            return None;
+        }
+
+        *buf.get_mut(len)? = b;
+        idx += n;
+        len += 1;
+    }
+
+    let host = &buf[..len];
+    Some(host.strip_suffix(b".").unwrap_or(host))
+}
+
+/// Check if the given decoded target host matches the given rule host.
+fn host_match(rule: &str, host: &[u8]) -> bool {
+    let rule = rule.strip_suffix('.').unwrap_or(rule).as_bytes();
+
+    match rule.strip_prefix(b"*.") {
+        Some(domain) => {
+            host.len() > domain.len() + 1 &&
+            host[host.len() - domain.len() - 1] == b'.' &&
+            host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+        },
+        None => host.eq_ignore_ascii_case(rule),
+    }
+}
+
+/// Access control list of ordered rules, where the first rule that matches a target
+/// decides its action.
+///
+/// Rules are borrowed, so a list can be built once at startup and shared, and evaluation
+/// does no allocation.
+///
+/// ```rust
+/// use uhttp_request_target::{Acl, Action, Glob, ParsedTarget, RequestTarget, Rule};
+///
+/// let rules = [
+///     Rule::deny().path(Glob::parse("/admin/**").unwrap()),
+///     Rule::allow().form(RequestTarget::AbsPath),
+///     Rule::allow().form(RequestTarget::Authority).host("*.example.com"),
+/// ];
+/// let acl = Acl::new(&rules, Action::Deny);
+///
+/// let d = acl.evaluate(&ParsedTarget::parse("/admin/users").unwrap());
+/// assert_eq!((d.action(), d.rule()), (Action::Deny, Some(0)));
+///
+/// let d = acl.evaluate(&ParsedTarget::parse("/index.html").unwrap());
+/// assert_eq!((d.action(), d.rule()), (Action::Allow, Some(1)));
+///
+/// let d = acl.evaluate(&ParsedTarget::parse("evil.com:443").unwrap());
+/// assert_eq!((d.action(), d.rule()), (Action::Deny, None));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Acl<'r, 'p> {
+    rules: &'r [Rule<'p>],
+    default: Action,
+}
+
+impl<'r, 'p> Acl<'r, 'p> {
+    /// Create a list of the given rules, taking the given default action for targets
+    /// that match none of them.
+    pub const fn new(rules: &'r [Rule<'p>], default: Action) -> Self {
+        Acl { rules, default }
+    }
+
+    /// Retrieve the rules of the list.
+    pub const fn rules(&self) -> &'r [Rule<'p>] { self.rules }
+
+    /// Retrieve the action taken for targets that match no rule.
+    pub const fn default_action(&self) -> Action { self.default }
+
+    /// Decide the action for the given target by its first matching rule.
+    pub fn evaluate(&self, target: &ParsedTarget) -> Decision {
+        match self.rules.iter().position(|rule| rule.matches(target)) {
+            Some(idx) => Decision { action: self.rules[idx].action, rule: Some(idx) },
+            None => Decision { action: self.default, rule: None },
+        }
+    }
+}
+
+/// Decision of an access control list for a target.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Decision {
+    action: Action,
+    rule: Option<usize>,
+}
+
+impl Decision {
+    /// Retrieve the action taken for the target.
+    pub const fn action(&self) -> Action { self.action }
+
+    /// Retrieve the index of the rule that decided the action, or `None` if no rule
+    /// matched and the default action was taken.
+    pub const fn rule(&self) -> Option<usize> { self.rule }
+
+    /// Check if the target is allowed.
+    pub fn is_allowed(&self) -> bool { self.action == Action::Allow }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> ParsedTarget<'_> { ParsedTarget::parse(s).unwrap() }
+
+    #[test]
+    fn test_rule() {
+        let all = Rule::allow();
+        assert_eq!(all.action(), Action::Allow);
+        assert_eq!(Rule::new(Action::Deny), Rule::deny());
+
+        for &s in &["/", "http://a/b", "a:443", "*"] {
+            assert!(all.matches(&parse(s)), "{}", s);
+        }
+
+        let form = Rule::deny().form(RequestTarget::Authority);
+        assert!(form.matches(&parse("a:443")));
+        assert!(!form.matches(&parse("/a")));
+
+        let path = Rule::deny().path(Glob::parse("/a/*").unwrap());
+        assert!(path.matches(&parse("/a/b?c")));
+        assert!(path.matches(&parse("http://x/a/b")));
+        assert!(!path.matches(&parse("/b/a")));
+        assert!(!path.matches(&parse("a:443")));
+        assert!(!path.matches(&parse("*")));
+
+        let root = Rule::deny().path(Glob::parse("/").unwrap());
+        assert!(root.matches(&parse("http://x")));
+
+        let host = Rule::deny().host("Example.com");
+        assert!(host.matches(&parse("http://EXAMPLE.COM/a")));
+        assert!(host.matches(&parse("example.com:443")));
+        assert!(!host.matches(&parse("http://api.example.com/a")));
+        assert!(!host.matches(&parse("/a")));
+        assert!(!host.matches(&parse("*")));
+
+        let scheme = Rule::deny().scheme("http");
+        assert!(scheme.matches(&parse("HTTP://a/")));
+        assert!(!scheme.matches(&parse("https://a/")));
+        assert!(!scheme.matches(&parse("a:80")));
+        assert!(!scheme.matches(&parse("/")));
+
+        let all = Rule::deny()
+            .form(RequestTarget::AbsUri)
+            .scheme("https")
+            .host("*.example.com")
+            .path(Glob::parse("/api/**").unwrap());
+        assert!(all.matches(&parse("https://a.example.com/api/v1")));
+        assert!(!all.matches(&parse("http://a.example.com/api/v1")));
+        assert!(!all.matches(&parse("https://example.com/api/v1")));
+        assert!(!all.matches(&parse("https://a.example.com/apis")));
+
+        let long = |t: &ParsedTarget| t.as_str().len() > 8;
+        let custom = Rule::deny().form(RequestTarget::AbsPath).matcher(&long);
+        assert!(custom.matches(&parse("/a/b/c/d/e")));
+        assert!(!custom.matches(&parse("/a")));
+        assert!(!custom.matches(&parse("http://a/b/c/d")));
+        assert_eq!(custom, Rule::deny().form(RequestTarget::AbsPath).matcher(&long));
+        assert_ne!(custom, Rule::deny().form(RequestTarget::AbsPath));
+    }
+
+    #[test]
+    fn test_host_match() {
+        assert!(host_match("a.com", b"A.COM"));
+        assert!(!host_match("a.com", b"b.a.com"));
+        assert!(host_match("*.a.com", b"b.a.com"));
+        assert!(host_match("*.a.com", b"c.B.A.com"));
+        assert!(!host_match("*.a.com", b"a.com"));
+        assert!(!host_match("*.a.com", b".a.com"));
+        assert!(!host_match("*.a.com", b"ba.com"));
+        assert!(!host_match("*.a.com", b"b.a.com.evil"));
+        assert!(host_match("[::1]", b"[::1]"));
+        assert!(host_match("a.com.", b"a.com"));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        let mut buf = [0; PATH_LEN];
+
+        for &(path, normal) in &[
+            ("/a/b", "/a/b"),
+            ("/./admin/x", "/admin/x"),
+            ("//admin/x", "/admin/x"),
+            ("/a/../admin/x", "/admin/x"),
+            ("/a//../admin", "/admin"),
+            ("/a/%2E%2E/admin", "/admin"),
+            ("/%61dmin/%2Fx", "/admin/%2Fx"),
+            ("/..", "/"),
+            ("", ""),
+        ] {
+            assert_eq!(normalize_path(path, &mut buf), Some(normal), "{}", path);
+        }
+
+        assert_eq!(normalize_path("/a", &mut [0; 1]), None);
+    }
+
+    #[test]
+    fn test_decode_host() {
+        let mut buf = [0; HOST_LEN];
+
+        assert_eq!(decode_host("a.com", &mut buf), Some(&b"a.com"[..]));
+        assert_eq!(decode_host("a.com.", &mut buf), Some(&b"a.com"[..]));
+        assert_eq!(decode_host("EV%69L.com%2E", &mut buf), Some(&b"EViL.com"[..]));
+        assert_eq!(decode_host("a%zz", &mut buf), Some(&b"a%zz"[..]));
+        assert_eq!(decode_host("a.com", &mut [0; 4]), None);
+        assert_eq!(decode_host("a.com#.b.com", &mut buf), Some(&b"a.com"[..]));
+        assert_eq!(decode_host("a.com%23.b.com", &mut buf), None);
+        assert_eq!(decode_host("a.com%2F.b.com", &mut buf), None);
+    }
+
+    #[test]
+    fn test_bypass() {
+        let admin = Rule::deny().path(Glob::parse("/admin/**").unwrap());
+
+        for &s in &["/./admin/x", "//admin/x", "/a/../admin/x", "http://a//admin/x"] {
+            assert!(admin.matches(&parse(s)), "{}", s);
+        }
+
+        let evil = Rule::deny().host("evil.com");
+
+        for &s in &["http://evil.com./", "http://EV%69L.com/", "evil.com.:443"] {
+            assert!(evil.matches(&parse(s)), "{}", s);
+        }
+
+        let good = Rule::allow().host("*.good.com");
+
+        for &s in &["http://evil.com#.good.com/", "http://evil.com%23.good.com/",
+                    "http://evil.com%2F.good.com/"] {
+            assert!(!good.matches(&parse(s)), "{}", s);
+        }
+
+        assert!(evil.matches(&parse("http://evil.com#.good.com/")));
+        let not_good = Rule::deny().host("*.good.com");
+        assert!(not_good.matches(&parse("http://evil.com%23.good.com/")));
+
+        let rules = [admin, evil];
+        let acl = Acl::new(&rules, Action::Allow);
+        assert_eq!(acl.evaluate(&parse("/a/../admin/x")).rule(), Some(0));
+        assert_eq!(acl.evaluate(&parse("evil.com.:443")).rule(), Some(1));
+        assert!(acl.evaluate(&parse("/administrator")).is_allowed());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_fail_closed() {
+        let long = "/a".repeat(PATH_LEN);
+        let t = ParsedTarget::parse(&long).unwrap();
+        let glob = Glob::parse("/admin/**").unwrap();
+
+        assert!(Rule::deny().path(glob).matches(&t));
+        assert!(!Rule::allow().path(glob).matches(&t));
+
+        let long = alloc::format!("http://{}/", "a".repeat(HOST_LEN + 1));
+        let t = ParsedTarget::parse(&long).unwrap();
+
+        assert!(Rule::deny().host("evil.com").matches(&t));
+        assert!(!Rule::allow().host("evil.com").matches(&t));
+    }
+
+    #[test]
+    fn test_acl() {
+        let rules = [
+            Rule::deny().path(Glob::parse("/admin/**").unwrap()),
+            Rule::allow().path(Glob::parse("/admin/**").unwrap()),
+            Rule::allow().form(RequestTarget::AbsPath),
+            Rule::deny().form(RequestTarget::ServerOptions),
+        ];
+
+        let acl = Acl::new(&rules, Action::Deny);
+        assert_eq!(acl.rules().len(), 4);
+        assert_eq!(acl.default_action(), Action::Deny);
+
+        let d = acl.evaluate(&parse("/admin"));
+        assert_eq!((d.action(), d.rule()), (Action::Deny, Some(0)));
+        assert!(!d.is_allowed());
+        let d = acl.evaluate(&parse("/a"));
+        assert_eq!((d.action(), d.rule()), (Action::Allow, Some(2)));
+        assert!(d.is_allowed());
+        let d = acl.evaluate(&parse("*"));
+        assert_eq!((d.action(), d.rule()), (Action::Deny, Some(3)));
+        let d = acl.evaluate(&parse("http://a/"));
+        assert_eq!((d.action(), d.rule()), (Action::Deny, None));
+
+        let d = Acl::new(&[], Action::Allow).evaluate(&parse("/"));
+        assert_eq!((d.action(), d.rule()), (Action::Allow, None));
+    }
+}
@@ -0,0 +1,399 @@
+//! Decomposition and validation of the authority component [RFC3986§3.2].
+
+use core::ops::Range;
+
+use {ErrorKind, ParseError};
+use charset::{check, is_sub_delim, is_unreserved};
+use form::offset;
+
+/// The syntactic kind of an authority host [RFC3986§3.2.2].
+///
+/// A host that looks like a dotted-decimal IPv4 address is always `Ipv4`, even though it
+/// also matches the reg-name grammar.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HostKind {
+    /// Registered name, such as `example.com`.
+    RegName,
+    /// Dotted-decimal IPv4 address, such as `127.0.0.1`.
+    Ipv4,
+    /// Bracketed IPv6 address, such as `[::1]`.
+    Ipv6,
+    /// Bracketed future IP literal, such as `[v1.fe80::a+en1]`.
+    IpvFuture,
+}
+
+impl HostKind {
+    /// Determine the kind of the given host by its syntax, without validating it.
+    pub(crate) fn of(host: &str) -> Self {
+        let s = host.as_bytes();
+
+        match s.first() {
+            Some(&b'[') => match s.get(1) {
+                Some(&b'v') | Some(&b'V') => HostKind::IpvFuture,
+                _ => HostKind::Ipv6,
+            },
+            _ if check_ipv4(s).is_ok() => HostKind::Ipv4,
+            _ => HostKind::RegName,
+        }
+    }
+}
+
+/// An authority split into optional userinfo, host, and optional port.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct Authority<'a> {
+    pub userinfo: Option<&'a str>,
+    pub host: &'a str,
+    pub port: Option<&'a str>,
+}
+
+impl<'a> Authority<'a> {
+    /// Split the given authority into components.
+    ///
+    /// Malformed brackets leave the whole remainder in the host, to be rejected by
+    /// `check_structure`.
+    pub fn split(s: &'a str) -> Self {
+        let (userinfo, rest) = match s.rfind('@') {
+            Some(idx) => (Some(&s[..idx]), &s[idx + 1..]),
+            None => (None, s),
+        };
+
+        // An IP literal is enclosed in brackets and may itself contain colons
+        // [RFC3986§3.2.2].
+        let end = if rest.starts_with('[') {
+            rest.find(']').map_or(rest.len(), |idx| idx + 1)
+        } else {
+            rest.find(':').unwrap_or(rest.len())
+        };
+
+        let (host, port) = match rest.split_at(end) {
+            (host, port) if port.starts_with(':') => (host, Some(&port[1..])),
+            _ => (rest, None),
+        };
+
+        Authority { userinfo, host, port }
+    }
+
+    /// Check that any IP literal is properly bracketed and any port is numeric, with
+    /// error offsets relative to the given target containing the authority.
+    pub fn check_structure(&self, target: &str) -> Result<(), ParseError> {
+        let s = target.as_bytes();
+
+        if self.host.starts_with('[') && !self.host.ends_with(']') {
+            return Err(ParseError::at(ErrorKind::InvalidHost, s, offset(target, self.host)));
+        }
+
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+
+        let start = offset(target, port);
+
+        // The port is a string of decimal digits [RFC3986§3.2.3].
+        if let Some(idx) = port.bytes().position(|b| !b.is_ascii_digit()) {
+            return Err(ParseError::at(ErrorKind::InvalidPort, s, start + idx));
+        }
+
+        if !port.is_empty() && port.parse::<u16>().is_err() {
+            return Err(ParseError::at(ErrorKind::InvalidPort, s, start));
+        }
+
+        Ok(())
+    }
+
+    /// Check the structure of the authority, then that the userinfo and host contain
+    /// only the bytes allowed by their grammar.
+    pub fn validate(&self, target: &str) -> Result<(), ParseError> {
+        self.check_structure(target)?;
+
+        let s = target.as_bytes();
+
+        if let Some(userinfo) = self.userinfo {
+            let start = offset(target, userinfo);
+            check(s, start..start + userinfo.len(), is_userinfo_byte,
+                  ErrorKind::InvalidUserinfo)?;
+        }
+
+        let start = offset(target, self.host);
+
+        // HTTP doesn't allow an empty host, though the generic syntax does
+        // [RFC7230§2.7.1].
+        if self.host.is_empty() {
+            return Err(ParseError::at(ErrorKind::InvalidHost, s, start));
+        }
+
+        if self.host.starts_with('[') {
+            validate_ip_literal(s, start + 1..start + self.host.len() - 1)
+        } else {
+            // An IPv4 address is also a syntactically valid reg-name.
+            check(s, start..start + self.host.len(), is_reg_name_byte,
+                  ErrorKind::InvalidHost)
+        }
+    }
+}
+
+/// Check if the given byte may appear literally in userinfo [RFC3986§3.2.1].
+fn is_userinfo_byte(b: u8) -> bool {
+    is_unreserved(b) || is_sub_delim(b) || b == b':'
+}
+
+/// Check if the given byte may appear literally in a registered name [RFC3986§3.2.2].
+fn is_reg_name_byte(b: u8) -> bool {
+    is_unreserved(b) || is_sub_delim(b)
+}
+
+/// Check that the given range of the target, within the brackets of an IP literal, is
+/// either an IPvFuture literal or an IPv6 address with an optional zone ID
+/// [RFC6874§2].
+fn validate_ip_literal(s: &[u8], range: Range<usize>) -> Result<(), ParseError> {
+    let inner = &s[range.clone()];
+
+    if let Some(&b'v') | Some(&b'V') = inner.first() {
+        return check_ipvfuture(inner)
+            .map_err(|idx| ParseError::at(ErrorKind::InvalidHost, s, range.start + idx));
+    }
+
+    // The zone ID is delimited by an encoded `%` and can't be empty.
+    let end = match inner.iter().position(|&b| b == b'%') {
+        Some(idx) => {
+            let zone = range.start + idx + 3;
+
+            if !inner[idx..].starts_with(b"%25") || zone == range.end {
+                return Err(ParseError::at(ErrorKind::InvalidHost, s, range.start + idx));
+            }
+
+            check(s, zone..range.end, is_unreserved, ErrorKind::InvalidHost)?;
+
+            idx
+        },
+        None => inner.len(),
+    };
+
+    check_ipv6(&inner[..end])
+        .map_err(|idx| ParseError::at(ErrorKind::InvalidHost, s, range.start + idx))
+}
+
+/// Check that the given bytes are an IPv6 address in any of its textual forms,
+/// returning the offset of the malformed part otherwise [RFC3986§3.2.2].
+fn check_ipv6(s: &[u8]) -> Result<(), usize> {
+    let mut groups = 0;
+    let mut compressed = false;
+    let mut idx = 0;
+
+    if s.starts_with(b"::") {
+        if s.len() == 2 {
+            return Ok(());
+        }
+
+        compressed = true;
+        idx = 2;
+    }
+
+    loop {
+        let start = idx;
+
+        while idx < s.len() && s[idx].is_ascii_hexdigit() {
+            idx += 1;
+        }
+
+        // The address may end in a dotted IPv4 address taking the place of two groups.
+        if idx < s.len() && s[idx] == b'.' {
+            check_ipv4(&s[start..]).map_err(|idx| start + idx)?;
+            groups += 2;
+            break;
+        }
+
+        if idx == start || idx - start > 4 {
+            return Err(start);
+        }
+
+        groups += 1;
+
+        if idx == s.len() {
+            break;
+        }
+
+        if s[idx] != b':' {
+            return Err(idx);
+        }
+
+        idx += 1;
+
+        if idx < s.len() && s[idx] == b':' {
+            if compressed {
+                return Err(idx);
+            }
+
+            compressed = true;
+            idx += 1;
+
+            if idx == s.len() {
+                break;
+            }
+        }
+    }
+
+    // A `::` stands for at least one zero group.
+    if (compressed && groups > 7) || (!compressed && groups != 8) {
+        return Err(0);
+    }
+
+    Ok(())
+}
+
+/// Check that the given bytes are an IPvFuture literal, consisting of a hex version and
+/// an address of unreserved, sub-delims, or `:` bytes, returning the offset of the
+/// malformed part otherwise [RFC3986§3.2.2].
+fn check_ipvfuture(s: &[u8]) -> Result<(), usize> {
+    let dot = s.iter().position(|&b| b == b'.').unwrap_or(s.len());
+
+    if let Some(idx) = s[1..dot].iter().position(|b| !b.is_ascii_hexdigit()) {
+        return Err(1 + idx);
+    }
+
+    if dot == 1 || dot == s.len() {
+        return Err(dot);
+    }
+
+    if dot + 1 == s.len() {
+        return Err(dot + 1);
+    }
+
+    match s[dot + 1..].iter().position(|&b| !is_userinfo_byte(b)) {
+        Some(idx) => Err(dot + 1 + idx),
+        None => Ok(()),
+    }
+}
+
+/// Check that the given bytes are a dotted-decimal IPv4 address, returning the offset of
+/// the malformed octet otherwise [RFC3986§3.2.2].
+fn check_ipv4(s: &[u8]) -> Result<(), usize> {
+    let mut start = 0;
+
+    for n in 0..4 {
+        let len = s[start..].iter().position(|&b| b == b'.').unwrap_or(s.len() - start);
+        let octet = &s[start..start + len];
+
+        // Octets have no leading zeros and can't exceed 255.
+        let valid = matches!(*octet,
+            [b'0'..=b'9'] |
+            [b'1'..=b'9', b'0'..=b'9'] |
+            [b'1', b'0'..=b'9', b'0'..=b'9'] |
+            [b'2', b'0'..=b'4', b'0'..=b'9'] |
+            [b'2', b'5', b'0'..=b'5']);
+
+        if !valid || (n < 3) == (start + len == s.len()) {
+            return Err(start);
+        }
+
+        start += len + 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let a = Authority::split("u:p@example.com:80");
+        assert_eq!(a.userinfo, Some("u:p"));
+        assert_eq!(a.host, "example.com");
+        assert_eq!(a.port, Some("80"));
+
+        let a = Authority::split("a@b@[::1]");
+        assert_eq!(a.userinfo, Some("a@b"));
+        assert_eq!(a.host, "[::1]");
+        assert_eq!(a.port, None);
+
+        let a = Authority::split("");
+        assert_eq!((a.userinfo, a.host, a.port), (None, "", None));
+    }
+
+    #[test]
+    fn test_validate() {
+        for &s in &["example.com", "a:80", "a:", "127.0.0.1:8080", "u:p@a", "@a",
+                    "%41!$&'()*+,;=-._~", "[::1]:443", "[::ffff:1.2.3.4]", "%7eu@a",
+                    "[fe80::1%25eth0]:443", "[::1%25%41]", "[v1.a]", "[VaF.a:b+c]:80"] {
+            assert_eq!(Authority::split(s).validate(s), Ok(()), "{}", s);
+        }
+
+        let err = |s| {
+            let e = Authority::split(s).validate(s).unwrap_err();
+            (e.kind(), e.offset())
+        };
+
+        assert_eq!(err("user name@example.com"), (ErrorKind::InvalidUserinfo, 4));
+        assert_eq!(err("a@b@c"), (ErrorKind::InvalidUserinfo, 1));
+        assert_eq!(err("u[@a"), (ErrorKind::InvalidUserinfo, 1));
+        assert_eq!(err("u%4@a"), (ErrorKind::InvalidPercentEncoding, 1));
+        assert_eq!(err("exa mple.com"), (ErrorKind::InvalidHost, 3));
+        assert_eq!(err("a\"b"), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("u@:80"), (ErrorKind::InvalidHost, 2));
+        assert_eq!(err(""), (ErrorKind::InvalidHost, 0));
+        assert_eq!(err("[]"), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("[::g]"), (ErrorKind::InvalidHost, 3));
+        assert_eq!(err("[::1"), (ErrorKind::InvalidHost, 0));
+        assert_eq!(err("[fe80::1%eth0]"), (ErrorKind::InvalidHost, 8));
+        assert_eq!(err("[fe80::1%25]"), (ErrorKind::InvalidHost, 8));
+        assert_eq!(err("[fe80::1%25a/b]"), (ErrorKind::InvalidHost, 12));
+        assert_eq!(err("[fe80::1%25a%2]"), (ErrorKind::InvalidPercentEncoding, 12));
+        assert_eq!(err("a]b"), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("[v]"), (ErrorKind::InvalidHost, 2));
+        assert_eq!(err("[v.a]"), (ErrorKind::InvalidHost, 2));
+        assert_eq!(err("[v1]"), (ErrorKind::InvalidHost, 3));
+        assert_eq!(err("[v1.]"), (ErrorKind::InvalidHost, 4));
+        assert_eq!(err("[vg.a]"), (ErrorKind::InvalidHost, 2));
+        assert_eq!(err("[v1.a%41]"), (ErrorKind::InvalidHost, 5));
+        assert_eq!(err("[v1.a/b]"), (ErrorKind::InvalidHost, 5));
+        assert_eq!(err("a:8a"), (ErrorKind::InvalidPort, 3));
+        assert_eq!(err("a:65536"), (ErrorKind::InvalidPort, 2));
+    }
+
+    #[test]
+    fn test_ipv6() {
+        for &s in &["::", "::1", "1::", "1::2", "a:b:c:d:e:f:0:1", "A:B:C:D:E:F:0:1",
+                    "ffff::", "1:2:3:4:5:6:7::", "::2:3:4:5:6:7:8", "1:2:3:4:5:6:1.2.3.4",
+                    "::1.2.3.4", "::ffff:255.255.255.255", "1:0:0:0:0:0:0:0", "fe80::abcd"] {
+            assert_eq!(check_ipv6(s.as_bytes()), Ok(()), "{}", s);
+        }
+
+        for &(s, idx) in &[("", 0), (":", 0), (":1", 0), (":::", 2), ("1:", 2),
+                           ("1::2::3", 5), ("12345::", 0), ("g::", 0), ("1:2:3:4:5:6:7", 0),
+                           ("1:2:3:4:5:6:7:8:9", 0), ("1:2:3:4:5:6:7:8::", 0),
+                           ("::1.2.3", 6), ("::1.2.3.256", 8), ("1.2.3.4", 0),
+                           ("::1.2.3.4:5", 8), ("::01.2.3.4", 2), ("::1 ", 3)] {
+            assert_eq!(check_ipv6(s.as_bytes()), Err(idx), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_ipv4() {
+        for &s in &["0.0.0.0", "1.2.3.4", "255.255.255.255", "10.199.249.9"] {
+            assert_eq!(check_ipv4(s.as_bytes()), Ok(()), "{}", s);
+        }
+
+        for &(s, idx) in &[("", 0), ("1.2.3", 4), ("1.2.3.4.5", 6), ("1.2.3.", 6),
+                           ("256.1.1.1", 0), ("1.01.1.1", 2), ("1..1.1", 2),
+                           ("1.2.3.a", 6), ("1.2.300.4", 4)] {
+            assert_eq!(check_ipv4(s.as_bytes()), Err(idx), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_host_kind() {
+        assert_eq!(HostKind::of("example.com"), HostKind::RegName);
+        assert_eq!(HostKind::of(""), HostKind::RegName);
+        assert_eq!(HostKind::of("1.2.3"), HostKind::RegName);
+        assert_eq!(HostKind::of("1.2.3.256"), HostKind::RegName);
+        assert_eq!(HostKind::of("1.2.3.4"), HostKind::Ipv4);
+        assert_eq!(HostKind::of("[::1]"), HostKind::Ipv6);
+        assert_eq!(HostKind::of("["), HostKind::Ipv6);
+        assert_eq!(HostKind::of("[v1.a]"), HostKind::IpvFuture);
+        assert_eq!(HostKind::of("[V1.a]"), HostKind::IpvFuture);
+    }
+}
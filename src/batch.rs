@@ -0,0 +1,81 @@
+//! Classification of many targets at once.
+
+use {ParseError, RequestTarget};
+
+/// Classify each of the given targets, yielding the results in order.
+///
+/// The lookup tables used by the classifier are static and it keeps no state between
+/// targets, so the batch costs nothing beyond classifying each target individually, and
+/// no allocation is performed.
+///
+/// ```rust
+/// use uhttp_request_target::{classify_all, RequestTarget};
+///
+/// let count = classify_all(&["/a", "*", "/b", "a/b"])
+///     .filter(|r| *r == Ok(RequestTarget::AbsPath))
+///     .count();
+///
+/// assert_eq!(count, 2);
+/// ```
+pub fn classify_all<I>(targets: I) -> ClassifyAll<I::IntoIter>
+    where I: IntoIterator, I::Item: AsRef<[u8]>
+{
+    ClassifyAll(targets.into_iter())
+}
+
+/// Iterator over the classifications of a batch of targets.
+///
+/// This is created by `classify_all`.
+#[derive(Clone, Debug)]
+pub struct ClassifyAll<I>(I);
+
+impl<I> Iterator for ClassifyAll<I>
+    where I: Iterator, I::Item: AsRef<[u8]>
+{
+    type Item = Result<RequestTarget, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|t| RequestTarget::classify(t.as_ref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+impl<I> DoubleEndedIterator for ClassifyAll<I>
+    where I: DoubleEndedIterator, I::Item: AsRef<[u8]>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|t| RequestTarget::classify(t.as_ref()))
+    }
+}
+
+impl<I> ExactSizeIterator for ClassifyAll<I>
+    where I: ExactSizeIterator, I::Item: AsRef<[u8]> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ErrorKind;
+
+    #[test]
+    fn test_classify_all() {
+        use RequestTarget::*;
+
+        let results: Vec<_> = classify_all(vec!["*", "/a", "http://a", "a:80", ""]).collect();
+        assert_eq!(results, vec![Ok(ServerOptions), Ok(AbsPath), Ok(AbsUri), Ok(Authority),
+            Err(ParseError::at(ErrorKind::Empty, b"", 0))]);
+
+        let bytes: &[&[u8]] = &[b"/\xff", b" /"];
+        let mut iter = classify_all(bytes);
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next_back(),
+            Some(Err(ParseError::at(ErrorKind::SurroundingWhitespace, b" /", 0))));
+        assert_eq!(iter.next(), Some(Ok(AbsPath)));
+        assert_eq!(iter.next(), None);
+
+        let owned = vec![String::from("/a"), String::from("a/")];
+        assert_eq!(classify_all(&owned).collect::<Vec<_>>(),
+            vec![Ok(AbsPath),
+                 Err(ParseError::at(ErrorKind::SlashInAuthority, b"a/", 1))]);
+    }
+}
@@ -0,0 +1,93 @@
+//! Character classes of the URI grammar.
+
+use core::ops::Range;
+
+use {ErrorKind, ParseError};
+
+/// Check if the given byte is unreserved [RFC3986§2.3].
+pub(crate) fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+/// Check if the given byte is a subcomponent delimiter [RFC3986§2.2].
+pub(crate) fn is_sub_delim(b: u8) -> bool {
+    matches!(b, b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=')
+}
+
+/// Check if the given byte may appear literally in a path segment [RFC3986§3.3].
+pub(crate) fn is_pchar(b: u8) -> bool {
+    is_unreserved(b) || is_sub_delim(b) || b == b':' || b == b'@'
+}
+
+/// Check if the given byte may appear literally in a path, including the separators
+/// between segments.
+pub(crate) fn is_path_byte(b: u8) -> bool { is_pchar(b) || b == b'/' }
+
+/// Check if the given byte may appear literally in a query [RFC3986§3.4].
+pub(crate) fn is_query_byte(b: u8) -> bool { is_pchar(b) || b == b'/' || b == b'?' }
+
+/// Check that each byte in the given range of the target is either allowed by the given
+/// class or part of a percent-encoded octet, returning an error of the given kind at the
+/// first byte that isn't.
+pub(crate) fn check(s: &[u8], range: Range<usize>, allowed: fn(u8) -> bool, kind: ErrorKind)
+    -> Result<(), ParseError>
+{
+    let end = range.end;
+    let mut idx = range.start;
+
+    while idx < end {
+        match s[idx] {
+            // A percent-encoded octet is a `%` followed by two hex digits [RFC3986§2.1].
+            b'%' => {
+                let hex = |i: usize| i < end && s[i].is_ascii_hexdigit();
+
+                if !hex(idx + 1) || !hex(idx + 2) {
+                    return Err(ParseError::at(ErrorKind::InvalidPercentEncoding, s, idx));
+                }
+
+                idx += 3;
+            },
+            b if allowed(b) => idx += 1,
+            _ => return Err(ParseError::at(kind, s, idx)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classes() {
+        for b in 0..=255u8 {
+            let unreserved = b.is_ascii_alphanumeric() || b"-._~".contains(&b);
+            assert_eq!(is_unreserved(b), unreserved);
+            assert_eq!(is_sub_delim(b), b"!$&'()*+,;=".contains(&b));
+            assert_eq!(is_pchar(b), is_unreserved(b) || is_sub_delim(b) || b":@".contains(&b));
+        }
+
+        assert!(!is_path_byte(b'?'));
+        assert!(is_query_byte(b'?'));
+        assert!(!is_query_byte(b'#'));
+        assert!(!is_path_byte(b' '));
+        assert!(!is_path_byte(0x80));
+    }
+
+    #[test]
+    fn test_check() {
+        let s = b"/a%20b?c%zz";
+        assert_eq!(check(s, 0..6, is_path_byte, ErrorKind::InvalidPath), Ok(()));
+
+        let e = check(s, 7..11, is_query_byte, ErrorKind::InvalidQuery).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::InvalidPercentEncoding, 8));
+
+        let e = check(s, 0..7, is_path_byte, ErrorKind::InvalidPath).unwrap_err();
+        assert_eq!((e.kind(), e.offset(), e.byte()), (ErrorKind::InvalidPath, 6, Some(b'?')));
+
+        // A percent-encoding can't extend past the end of the range.
+        let e = check(b"/%2f", 0..3, is_path_byte, ErrorKind::InvalidPath).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+    }
+}
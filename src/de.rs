@@ -0,0 +1,562 @@
+//! Deserialization of queries into typed parameters with serde.
+
+use alloc::borrow::Cow;
+use alloc::collections::btree_map;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess,
+                Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+
+use {AbsoluteForm, OriginForm, ParseError};
+use form::offset;
+use query::{DuplicateKeyPolicy, QueryOptions};
+
+/// Error deserializing typed parameters from a query, available with the `serde`
+/// feature.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum QueryDeError {
+    /// A key or value couldn't be decoded.
+    Parse(ParseError),
+    /// The parameters couldn't be deserialized into the requested type, such as when a
+    /// required field is missing or a value isn't a number.
+    Custom(String),
+}
+
+impl fmt::Display for QueryDeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QueryDeError::Parse(ref e) => fmt::Display::fmt(e, fmt),
+            QueryDeError::Custom(ref msg) => fmt.write_str(msg),
+        }
+    }
+}
+
+impl core::error::Error for QueryDeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match *self {
+            QueryDeError::Parse(ref e) => Some(e),
+            QueryDeError::Custom(_) => None,
+        }
+    }
+}
+
+impl de::Error for QueryDeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self { QueryDeError::Custom(msg.to_string()) }
+}
+
+impl From<ParseError> for QueryDeError {
+    fn from(e: ParseError) -> Self { QueryDeError::Parse(e) }
+}
+
+/// Deserialize the parameters of the given query into the given type, available with the
+/// `serde` feature.
+///
+/// This is `QueryOptions::deserialize` with the default options.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate uhttp_request_target;
+/// use serde::Deserialize;
+/// use uhttp_request_target::from_query;
+///
+/// #[derive(Deserialize)]
+/// struct Search<'a> {
+///     q: &'a str,
+///     page: Option<u32>,
+///     tag: Vec<String>,
+/// }
+///
+/// # fn main() {
+/// let s: Search = from_query("q=rust&tag=web&tag=http%2F2").unwrap();
+///
+/// assert_eq!(s.q, "rust");
+/// assert_eq!(s.page, None);
+/// assert_eq!(s.tag, ["web", "http/2"]);
+/// # }
+/// ```
+pub fn from_query<'a, T: Deserialize<'a>>(query: &'a str) -> Result<T, QueryDeError> {
+    QueryOptions::new().deserialize(query)
+}
+
+impl QueryOptions {
+    /// Deserialize the parameters of the given query into the given type, available with
+    /// the `serde` feature.
+    ///
+    /// The query is decoded like `QueryOptions::map`, and the type is deserialized from
+    /// the resulting map, so a struct has a field for each key, and unknown keys are
+    /// ignored unless the struct denies them. Values are parsed as numbers, `true` or
+    /// `false`, or unit enum variants when the field asks for them, and are borrowed when
+    /// they have no escapes. An `Option` field is `None` when its key is missing or its
+    /// value is empty. A sequence field, such as a `Vec`, takes every value for its key,
+    /// while any other field takes the first value under `DuplicateKeyPolicy::First` and
+    /// the last otherwise.
+    pub fn deserialize<'a, T: Deserialize<'a>>(&self, query: &'a str)
+        -> Result<T, QueryDeError>
+    {
+        let map = self.duplicates(DuplicateKeyPolicy::All).map(query)?;
+
+        T::deserialize(QueryDeserializer {
+            params: map.into_iter(),
+            values: None,
+            first: self.duplicate_policy() == DuplicateKeyPolicy::First,
+        })
+    }
+}
+
+impl<'a> OriginForm<'a> {
+    /// Deserialize the parameters of the query into the given type like `from_query`,
+    /// with decoding errors reported at their offset in the target, available with the
+    /// `serde` feature.
+    pub fn query_as<T: Deserialize<'a>>(&self) -> Result<T, QueryDeError> {
+        self.query_as_with(&QueryOptions::new())
+    }
+
+    /// Deserialize the parameters of the query into the given type like
+    /// `QueryOptions::deserialize`, with decoding errors reported at their offset in the
+    /// target, available with the `serde` feature.
+    pub fn query_as_with<T: Deserialize<'a>>(&self, opts: &QueryOptions)
+        -> Result<T, QueryDeError>
+    {
+        target_query_as(self.as_str(), self.query(), opts)
+    }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Deserialize the parameters of the query into the given type like `from_query`,
+    /// with decoding errors reported at their offset in the target, available with the
+    /// `serde` feature.
+    pub fn query_as<T: Deserialize<'a>>(&self) -> Result<T, QueryDeError> {
+        self.query_as_with(&QueryOptions::new())
+    }
+
+    /// Deserialize the parameters of the query into the given type like
+    /// `QueryOptions::deserialize`, with decoding errors reported at their offset in the
+    /// target, available with the `serde` feature.
+    pub fn query_as_with<T: Deserialize<'a>>(&self, opts: &QueryOptions)
+        -> Result<T, QueryDeError>
+    {
+        target_query_as(self.as_str(), self.query(), opts)
+    }
+}
+
+/// Deserialize the given optional query of the given target, reporting decoding errors
+/// at target offsets.
+fn target_query_as<'a, T>(target: &'a str, query: Option<&'a str>, opts: &QueryOptions)
+    -> Result<T, QueryDeError>
+    where T: Deserialize<'a>
+{
+    let query = query.unwrap_or("");
+
+    opts.deserialize(query).map_err(|e| match e {
+        QueryDeError::Parse(e) if !query.is_empty() => {
+            QueryDeError::Parse(e.shift(offset(target, query)))
+        },
+        e => e,
+    })
+}
+
+/// Deserializer for the map of parameters of a query.
+struct QueryDeserializer<'a> {
+    params: btree_map::IntoIter<Cow<'a, str>, Vec<Cow<'a, str>>>,
+    values: Option<(Cow<'a, str>, Vec<Cow<'a, str>>)>,
+    first: bool,
+}
+
+impl<'de> de::Deserializer<'de> for QueryDeserializer<'de> {
+    type Error = QueryDeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+        visitor.visit_map(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for QueryDeserializer<'de> {
+    type Error = QueryDeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, QueryDeError>
+        where K: DeserializeSeed<'de>
+    {
+        match self.params.next() {
+            Some((key, values)) => {
+                self.values = Some((key.clone(), values));
+                seed.deserialize(Value(key)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, QueryDeError>
+        where V: DeserializeSeed<'de>
+    {
+        let (key, values) = match self.values.take() {
+            Some(kv) => kv,
+            None => return Err(de::Error::custom("value requested before key")),
+        };
+
+        seed.deserialize(Values { values, first: self.first }).map_err(|e| match e {
+            QueryDeError::Custom(msg) => {
+                QueryDeError::Custom(format!("parameter `{}`: {}", key, msg))
+            },
+            e => e,
+        })
+    }
+}
+
+/// Deserializer for every value of a key, which has at least one.
+struct Values<'a> {
+    values: Vec<Cow<'a, str>>,
+    first: bool,
+}
+
+impl<'a> Values<'a> {
+    /// Retrieve the index of the single value used for a field that isn't a sequence.
+    fn index(&self) -> usize {
+        if self.first { 0 } else { self.values.len().saturating_sub(1) }
+    }
+
+    /// Retrieve the single value used for a field that isn't a sequence.
+    fn pick(mut self) -> Value<'a> {
+        let idx = self.index();
+
+        if idx < self.values.len() {
+            Value(self.values.swap_remove(idx))
+        } else {
+            Value(Cow::default())
+        }
+    }
+}
+
+/// Forward the given deserializer methods to the picked value.
+macro_rules! forward_to_pick {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+            self.pick().$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = QueryDeError;
+
+    forward_to_pick! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        match self.values.get(self.index()) {
+            Some(v) if !v.is_empty() => visitor.visit_some(self),
+            _ => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+        visitor.visit_seq(Seq(self.values.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        self.pick().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str,
+                                           fields: &'static [&'static str], visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        self.pick().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str,
+                                         variants: &'static [&'static str], visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        self.pick().deserialize_enum(name, variants, visitor)
+    }
+}
+
+/// Access to each value of a key as an element of a sequence.
+struct Seq<'a>(vec::IntoIter<Cow<'a, str>>);
+
+impl<'de> SeqAccess<'de> for Seq<'de> {
+    type Error = QueryDeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, QueryDeError>
+        where T: DeserializeSeed<'de>
+    {
+        match self.0.next() {
+            Some(value) => seed.deserialize(Value(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> { Some(self.0.len()) }
+}
+
+/// Deserializer for a single decoded key or value.
+struct Value<'a>(Cow<'a, str>);
+
+/// Deserialize the value by parsing it as the given type with the given visitor method.
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident $ty:ty, $exp:expr;)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+            match self.0.parse::<$ty>() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.0), &$exp)),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = QueryDeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+        match self.0 {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool bool, "`true` or `false`";
+        deserialize_i8 => visit_i8 i8, "an 8-bit integer";
+        deserialize_i16 => visit_i16 i16, "a 16-bit integer";
+        deserialize_i32 => visit_i32 i32, "a 32-bit integer";
+        deserialize_i64 => visit_i64 i64, "a 64-bit integer";
+        deserialize_i128 => visit_i128 i128, "a 128-bit integer";
+        deserialize_u8 => visit_u8 u8, "an 8-bit unsigned integer";
+        deserialize_u16 => visit_u16 u16, "a 16-bit unsigned integer";
+        deserialize_u32 => visit_u32 u32, "a 32-bit unsigned integer";
+        deserialize_u64 => visit_u64 u64, "a 64-bit unsigned integer";
+        deserialize_u128 => visit_u128 u128, "a 128-bit unsigned integer";
+        deserialize_f32 => visit_f32 f32, "a number";
+        deserialize_f64 => visit_f64 f64, "a number";
+        deserialize_char => visit_char char, "a single character";
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryDeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str,
+                                         _variants: &'static [&'static str], visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V)
+        -> Result<V::Value, QueryDeError>
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use serde::Deserialize;
+    use ErrorKind;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Order {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params<'a> {
+        q: &'a str,
+        owned: Option<String>,
+        page: Option<u32>,
+        offset: Option<i64>,
+        ratio: Option<f64>,
+        debug: Option<bool>,
+        sep: Option<char>,
+        order: Option<Order>,
+        #[serde(default)]
+        tag: Vec<u16>,
+        #[serde(default)]
+        pair: Option<(u8, u8)>,
+    }
+
+    #[test]
+    fn test_from_query() {
+        let p: Params = from_query(
+            "q=a+b&owned=%7E&page=2&offset=-3&ratio=0.5&debug=true&sep=%3B&order=desc\
+             &tag=1&tag=2&pair=3&pair=4&unknown=x").unwrap();
+
+        assert_eq!(p, Params {
+            q: "a+b",
+            owned: Some("~".to_string()),
+            page: Some(2),
+            offset: Some(-3),
+            ratio: Some(0.5),
+            debug: Some(true),
+            sep: Some(';'),
+            order: Some(Order::Desc),
+            tag: vec![1, 2],
+            pair: Some((3, 4)),
+        });
+
+        let p: Params = from_query("q=&page=&order=asc").unwrap();
+        assert_eq!(p.q, "");
+        assert_eq!(p.page, None);
+        assert_eq!(p.order, Some(Order::Asc));
+        assert_eq!(p.tag, []);
+        assert_eq!(p.pair, None);
+
+        let m: HashMap<String, String> = from_query("a=1&b=%32").unwrap();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m["b"], "2");
+        let m: HashMap<&str, Vec<&str>> = from_query("a=1&b&a=3").unwrap();
+        assert_eq!(m["a"], ["1", "3"]);
+        assert_eq!(m["b"], [""]);
+        let v: (u8,) = from_query::<HashMap<&str, u8>>("x=7").map(|m| (m["x"],)).unwrap();
+        assert_eq!(v, (7,));
+    }
+
+    #[test]
+    fn test_from_query_errors() {
+        let e = from_query::<Params>("page=2").unwrap_err();
+        assert_eq!(e, QueryDeError::Custom("missing field `q`".to_string()));
+
+        let e = from_query::<Params>("q=a&page=two").unwrap_err();
+        assert_eq!(e.to_string(), "parameter `page`: invalid value: string \"two\", \
+                                   expected a 32-bit unsigned integer");
+        let e = from_query::<Params>("q=a&debug=1").unwrap_err();
+        assert_eq!(e.to_string(), "parameter `debug`: invalid value: string \"1\", \
+                                   expected `true` or `false`");
+        let e = from_query::<Params>("q=a&order=up").unwrap_err();
+        assert!(e.to_string().starts_with("parameter `order`: unknown variant `up`"));
+        let e = from_query::<Params>("q=a&tag=1&tag=x").unwrap_err();
+        assert!(e.to_string().starts_with("parameter `tag`: invalid value"));
+
+        let e = from_query::<Params>("q=a&page=%2").unwrap_err();
+        match e {
+            QueryDeError::Parse(e) => {
+                assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+                assert_eq!(e.offset(), 9);
+            },
+            _ => panic!(),
+        }
+
+        let e = from_query::<Params>("q=%41").unwrap_err();
+        assert!(e.to_string().starts_with("parameter `q`: invalid type: string \"A\""));
+    }
+
+    #[test]
+    fn test_deserialize_options() {
+        #[derive(Deserialize)]
+        struct P {
+            a: String,
+            b: Vec<String>,
+        }
+
+        let q = "a=1&b=x+y;b=z&a=2";
+
+        let p: P = QueryOptions::new().deserialize(q).unwrap();
+        assert_eq!((&p.a[..], p.b), ("2", vec!["x+y;b=z".to_string()]));
+
+        let opts = QueryOptions::new()
+            .duplicates(DuplicateKeyPolicy::First)
+            .plus_as_space(true)
+            .semicolons(true);
+        let p: P = opts.deserialize(q).unwrap();
+        assert_eq!((&p.a[..], p.b), ("1", vec!["x y".to_string(), "z".to_string()]));
+
+        let opts = QueryOptions::new().duplicates(DuplicateKeyPolicy::All);
+        let p: P = opts.deserialize(q).unwrap();
+        assert_eq!(p.a, "2");
+    }
+
+    #[test]
+    fn test_query_as() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct P {
+            page: Option<u32>,
+        }
+
+        let t = OriginForm::parse("/a?page=3").unwrap();
+        assert_eq!(t.query_as::<P>(), Ok(P { page: Some(3) }));
+        let t = OriginForm::parse("/a").unwrap();
+        assert_eq!(t.query_as::<P>(), Ok(P { page: None }));
+        let t = AbsoluteForm::parse("http://a/?page=4;").unwrap();
+        let opts = QueryOptions::new().semicolons(true);
+        assert_eq!(t.query_as_with::<P>(&opts), Ok(P { page: Some(4) }));
+
+        let t = AbsoluteForm::parse("http://a/?page=%").unwrap();
+        match t.query_as::<P>().unwrap_err() {
+            QueryDeError::Parse(e) => assert_eq!(e.offset(), 15),
+            _ => panic!(),
+        }
+
+        let e = QueryDeError::from(t.query_map().unwrap_err());
+        assert!(core::error::Error::source(&e).is_some());
+        assert_eq!(e.to_string(), "target has malformed percent-encoding at offset 15");
+    }
+}
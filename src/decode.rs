@@ -0,0 +1,348 @@
+//! Percent-decoding and -encoding of target components without allocation.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use {ErrorKind, ParseError};
+
+/// Policy for a `%` that isn't followed by two hex digits when decoding.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InvalidEscapePolicy {
+    /// Fail with `ErrorKind::InvalidPercentEncoding` at the offset of the `%`.
+    Error,
+    /// Pass the `%` through unchanged, along with the bytes after it, as many browsers
+    /// do.
+    PassThrough,
+    /// Replace the `%` with U+FFFD REPLACEMENT CHARACTER, passing the bytes after it
+    /// through unchanged.
+    Replace,
+}
+
+/// Percent-decode the given path or query component into the given buffer, returning
+/// the number of bytes written.
+///
+/// Each `%` must be followed by two hex digits [RFC3986§2.1], otherwise
+/// `ErrorKind::InvalidPercentEncoding` is returned. If the decoded component doesn't
+/// fit in the buffer, `ErrorKind::TooLong` is returned. The error offset locates the
+/// source byte or escape where the failure occurred. Since decoding never lengthens
+/// the input, a buffer the size of the source is always large enough. The decoded bytes
+/// aren't required to be UTF-8, and `+` is passed through unchanged.
+pub fn percent_decode_into(src: &[u8], dst: &mut [u8]) -> Result<usize, ParseError> {
+    let mut idx = 0;
+    let mut len = 0;
+
+    while idx < src.len() {
+        let start = idx;
+
+        let b = if src[idx] == b'%' {
+            let hi = src.get(idx + 1).and_then(|&b| hex_value(b));
+            let lo = src.get(idx + 2).and_then(|&b| hex_value(b));
+
+            match (hi, lo) {
+                (Some(hi), Some(lo)) => {
+                    idx += 3;
+                    hi << 4 | lo
+                },
+                _ => {
+                    return Err(ParseError::at(ErrorKind::InvalidPercentEncoding, src, start))
+                },
+            }
+        } else {
+            idx += 1;
+            src[idx - 1]
+        };
+
+        match dst.get_mut(len) {
+            Some(slot) => *slot = b,
+            None => return Err(ParseError::at(ErrorKind::TooLong, src, start)),
+        }
+
+        len += 1;
+    }
+
+    Ok(len)
+}
+
+/// Percent-decode the given path or query component, applying the given policy to
+/// invalid escapes, available with the `alloc` feature.
+///
+/// The component is borrowed unchanged if it contains no `%`, and `+` is passed through
+/// unchanged. The decoded bytes aren't required to be UTF-8.
+///
+/// ```rust
+/// use uhttp_request_target::{percent_decode, InvalidEscapePolicy};
+///
+/// assert_eq!(&*percent_decode("/a%20b", InvalidEscapePolicy::Error).unwrap(), b"/a b");
+/// assert_eq!(&*percent_decode("100%", InvalidEscapePolicy::PassThrough).unwrap(),
+///            b"100%");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn percent_decode(s: &str, policy: InvalidEscapePolicy)
+    -> Result<Cow<'_, [u8]>, ParseError>
+{
+    let src = s.as_bytes();
+
+    if !src.contains(&b'%') {
+        return Ok(Cow::Borrowed(src));
+    }
+
+    let mut out = Vec::with_capacity(src.len());
+    let mut idx = 0;
+
+    while idx < src.len() {
+        match escape_at(src, idx) {
+            Some(b) => {
+                out.push(b);
+                idx += 3;
+                continue;
+            },
+            None if src[idx] != b'%' => out.push(src[idx]),
+            None => match policy {
+                InvalidEscapePolicy::Error => {
+                    return Err(ParseError::at(ErrorKind::InvalidPercentEncoding, src, idx))
+                },
+                InvalidEscapePolicy::PassThrough => out.push(b'%'),
+                InvalidEscapePolicy::Replace => {
+                    out.extend_from_slice("\u{FFFD}".as_bytes())
+                },
+            },
+        }
+
+        idx += 1;
+    }
+
+    Ok(Cow::Owned(out))
+}
+
+/// Percent-decode the given path or query component into a string like `percent_decode`,
+/// available with the `alloc` feature.
+///
+/// Decoded bytes that aren't valid UTF-8 are replaced with U+FFFD under
+/// `InvalidEscapePolicy::Replace`, and are otherwise rejected with
+/// `ErrorKind::InvalidUtf8` at the offset of the source byte or escape that started the
+/// invalid sequence.
+///
+/// ```rust
+/// use uhttp_request_target::{percent_decode_str, InvalidEscapePolicy};
+///
+/// let s = percent_decode_str("/caf%C3%A9", InvalidEscapePolicy::Error).unwrap();
+/// assert_eq!(s, "/caf\u{e9}");
+/// let s = percent_decode_str("/%zz%ff", InvalidEscapePolicy::Replace).unwrap();
+/// assert_eq!(s, "/\u{FFFD}zz\u{FFFD}");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn percent_decode_str(s: &str, policy: InvalidEscapePolicy)
+    -> Result<Cow<'_, str>, ParseError>
+{
+    let bytes = match percent_decode(s, policy)? {
+        Cow::Borrowed(_) => return Ok(Cow::Borrowed(s)),
+        Cow::Owned(bytes) => bytes,
+    };
+
+    match String::from_utf8(bytes) {
+        Ok(decoded) => Ok(Cow::Owned(decoded)),
+        Err(e) if policy == InvalidEscapePolicy::Replace => {
+            Ok(Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned()))
+        },
+        Err(e) => {
+            let idx = source_offset(s.as_bytes(), e.utf8_error().valid_up_to());
+            Err(ParseError::at(ErrorKind::InvalidUtf8, s.as_bytes(), idx))
+        },
+    }
+}
+
+/// Map the given offset into bytes decoded from the given source, with invalid escapes
+/// passed through, back to the offset of the source byte or escape that produced it.
+pub(crate) fn source_offset(src: &[u8], decoded: usize) -> usize {
+    let mut idx = 0;
+
+    for _ in 0..decoded {
+        idx += if escape_at(src, idx).is_some() { 3 } else { 1 };
+    }
+
+    idx
+}
+
+/// Retrieve the octet encoded by a well-formed escape at the given offset, if any.
+fn escape_at(src: &[u8], idx: usize) -> Option<u8> {
+    match src[idx..] {
+        [b'%', hi, lo, ..] => match (hex_value(hi), hex_value(lo)) {
+            (Some(hi), Some(lo)) => Some(hi << 4 | lo),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Percent-encode each byte above 0x7F in the given target into the given buffer,
+/// returning the number of bytes written.
+///
+/// Other bytes, including any existing escapes, are copied unchanged. If the encoded
+/// target doesn't fit in the buffer, `ErrorKind::TooLong` is returned at the offset of
+/// the source byte that didn't fit. A buffer three times the size of the source is
+/// always large enough.
+pub(crate) fn percent_encode_non_ascii_into(src: &[u8], dst: &mut [u8])
+    -> Result<usize, ParseError>
+{
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let mut len = 0;
+
+    for (idx, &b) in src.iter().enumerate() {
+        let encoded = [b'%', HEX[(b >> 4) as usize], HEX[(b & 0xF) as usize]];
+        let bytes = if b < 0x80 { &[b][..] } else { &encoded[..] };
+
+        match dst.get_mut(len..len + bytes.len()) {
+            Some(slots) => slots.copy_from_slice(bytes),
+            None => return Err(ParseError::at(ErrorKind::TooLong, src, idx)),
+        }
+
+        len += bytes.len();
+    }
+
+    Ok(len)
+}
+
+/// Check if the given raw string decodes to the given string, optionally with `+` as a
+/// space.
+///
+/// A `%` that doesn't start a valid escape is taken as is, so this does no allocation and
+/// never fails.
+pub(crate) fn decodes_to(raw: &str, s: &str, plus_as_space: bool) -> bool {
+    let (raw, s) = (raw.as_bytes(), s.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < raw.len() && j < s.len() {
+        let (b, len) = match raw[i] {
+            b'+' if plus_as_space => (b' ', 1),
+            _ => decoded_unit(raw, i),
+        };
+
+        if b != s[j] {
+            return false;
+        }
+
+        i += len;
+        j += 1;
+    }
+
+    i == raw.len() && j == s.len()
+}
+
+/// Retrieve the octet at the given offset of the given raw string, decoding it if it
+/// starts a valid escape, along with the number of bytes it spans.
+pub(crate) fn decoded_unit(s: &[u8], idx: usize) -> (u8, usize) {
+    match s[idx..] {
+        [b'%', hi, lo, ..] => match (hex_value(hi), hex_value(lo)) {
+            (Some(hi), Some(lo)) => (hi << 4 | lo, 3),
+            _ => (b'%', 1),
+        },
+        _ => (s[idx], 1),
+    }
+}
+
+/// Retrieve the value of the given hex digit.
+pub(crate) fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(s: &str) -> Result<Vec<u8>, ParseError> {
+        let mut buf = [0; 64];
+        percent_decode_into(s.as_bytes(), &mut buf).map(|len| buf[..len].to_vec())
+    }
+
+    #[test]
+    fn test_percent_decode_into() {
+        assert_eq!(decode(""), Ok(vec![]));
+        assert_eq!(decode("/a/b"), Ok(b"/a/b".to_vec()));
+        assert_eq!(decode("/a%20b"), Ok(b"/a b".to_vec()));
+        assert_eq!(decode("%2f%2F"), Ok(b"//".to_vec()));
+        assert_eq!(decode("a+b"), Ok(b"a+b".to_vec()));
+        assert_eq!(decode("%e2%9C%93"), Ok("\u{2713}".as_bytes().to_vec()));
+        assert_eq!(decode("%ff%00"), Ok(vec![0xFF, 0x00]));
+        assert_eq!(decode("%").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPercentEncoding));
+        assert_eq!(decode("%2").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPercentEncoding));
+        assert_eq!(decode("%2g").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPercentEncoding));
+        assert_eq!(decode("%g2").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPercentEncoding));
+        assert_eq!(decode("a%").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPercentEncoding));
+
+        let mut buf = [0; 3];
+        assert_eq!(percent_decode_into(b"%41%42%43", &mut buf), Ok(3));
+        assert_eq!(&buf, b"ABC");
+        assert_eq!(percent_decode_into(b"abcd", &mut buf).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(percent_decode_into(b"", &mut []), Ok(0));
+        assert_eq!(percent_decode_into(b"a", &mut []).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+
+        let e = percent_decode_into(b"/a%2fb%zz", &mut [0; 16]).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::InvalidPercentEncoding, 6));
+        let e = percent_decode_into(b"%41%42%43", &mut [0; 2]).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 6));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_percent_decode() {
+        use self::InvalidEscapePolicy::*;
+
+        let err = |s, policy| percent_decode(s, policy).map_err(|e| (e.kind(), e.offset()));
+
+        assert!(matches!(percent_decode("/a+b", Error), Ok(Cow::Borrowed(b"/a+b"))));
+        assert_eq!(err("/a%2fb%41", Error).unwrap(), &b"/a/bA"[..]);
+        assert_eq!(err("%ff", Error).unwrap(), &[0xFF][..]);
+        assert_eq!(err("/a%2", Error), Err((ErrorKind::InvalidPercentEncoding, 2)));
+        assert_eq!(err("/%41%zz", Error), Err((ErrorKind::InvalidPercentEncoding, 4)));
+        assert_eq!(err("/%41%zz%", PassThrough).unwrap(), &b"/A%zz%"[..]);
+        assert_eq!(err("%%41", PassThrough).unwrap(), &b"%A"[..]);
+        assert_eq!(err("/%4", Replace).unwrap(), "/\u{FFFD}4".as_bytes());
+
+        let err = |s, policy| {
+            percent_decode_str(s, policy).map_err(|e| (e.kind(), e.offset()))
+        };
+
+        assert!(matches!(percent_decode_str("/a", Error), Ok(Cow::Borrowed("/a"))));
+        assert_eq!(err("/a%20%E2%9C%93", Error).unwrap(), "/a \u{2713}");
+        assert_eq!(err("/a%", Error), Err((ErrorKind::InvalidPercentEncoding, 2)));
+        assert_eq!(err("/%41%ff", Error), Err((ErrorKind::InvalidUtf8, 4)));
+        assert_eq!(err("/%+%c3", PassThrough), Err((ErrorKind::InvalidUtf8, 3)));
+        assert_eq!(err("/%41%ff%", Replace).unwrap(), "/A\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_percent_encode_non_ascii_into() {
+        let encode = |s: &[u8]| {
+            let mut buf = [0; 64];
+            percent_encode_non_ascii_into(s, &mut buf).map(|len| buf[..len].to_vec())
+        };
+
+        assert_eq!(encode(b""), Ok(vec![]));
+        assert_eq!(encode(b"/a%20b?c"), Ok(b"/a%20b?c".to_vec()));
+        assert_eq!(encode("/\u{e9}".as_bytes()), Ok(b"/%C3%A9".to_vec()));
+        assert_eq!(encode(b"/\xff\x80"), Ok(b"/%FF%80".to_vec()));
+
+        let mut buf = [0; 3];
+        assert_eq!(percent_encode_non_ascii_into(b"\xff", &mut buf), Ok(3));
+        let e = percent_encode_non_ascii_into(b"/\xff", &mut buf).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 1));
+    }
+}
@@ -0,0 +1,246 @@
+//! Comparison of targets for equivalence [RFC9110§4.2.3].
+
+use {AbsoluteForm, ParsedTarget, Scheme};
+use charset::is_unreserved;
+use decode::hex_value;
+
+/// Options controlling how `CompareOptions::equivalent` compares targets.
+///
+/// The default options follow the comparison rules for http and https URIs exactly, as
+/// with `targets_equivalent`.
+///
+/// ```rust
+/// use uhttp_request_target::{CompareOptions, ParsedTarget};
+///
+/// let opts = CompareOptions::new().ignore_trailing_slash(true);
+/// let a = ParsedTarget::parse("/users").unwrap();
+/// let b = ParsedTarget::parse("/users/").unwrap();
+///
+/// assert!(opts.equivalent(&a, &b));
+/// assert!(!CompareOptions::new().equivalent(&a, &b));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CompareOptions {
+    ignore_trailing_slash: bool,
+}
+
+impl CompareOptions {
+    /// Create options that follow the comparison rules exactly.
+    pub const fn new() -> Self {
+        CompareOptions {
+            ignore_trailing_slash: false,
+        }
+    }
+
+    /// Set whether a single trailing `/` on a path is ignored, so `/users` and
+    /// `/users/` are equivalent.
+    ///
+    /// These are distinct resources in general [RFC3986§6.2.4], but many routing layers
+    /// treat them the same. Only a slash that ends a non-empty segment is ignored, so
+    /// `/` is still distinct from `//` and `/a/` from `/a//`.
+    pub const fn ignore_trailing_slash(mut self, ignore: bool) -> Self {
+        self.ignore_trailing_slash = ignore;
+        self
+    }
+
+    /// Check if the given targets are equivalent like `targets_equivalent`, with the
+    /// relaxations enabled in these options.
+    pub fn equivalent(&self, a: &ParsedTarget, b: &ParsedTarget) -> bool {
+        match (*a, *b) {
+            (ParsedTarget::AbsPath(a), ParsedTarget::AbsPath(b)) => {
+                self.path_eq(a.path(), b.path()) && query_eq(a.query(), b.query())
+            },
+            (ParsedTarget::AbsUri(a), ParsedTarget::AbsUri(b)) => {
+                a.scheme().eq_ignore_ascii_case(b.scheme()) &&
+                a.userinfo() == b.userinfo() &&
+                a.host().eq_ignore_ascii_case(b.host()) &&
+                effective_port(&a) == effective_port(&b) &&
+                self.path_eq(a.path_or_root(), b.path_or_root()) &&
+                query_eq(a.query(), b.query())
+            },
+            (ParsedTarget::Authority(a), ParsedTarget::Authority(b)) => {
+                a.userinfo() == b.userinfo() &&
+                a.host().eq_ignore_ascii_case(b.host()) &&
+                a.port() == b.port()
+            },
+            (ParsedTarget::ServerOptions, ParsedTarget::ServerOptions) => true,
+            _ => false,
+        }
+    }
+
+    /// Check if the given paths are equivalent.
+    fn path_eq(&self, a: &str, b: &str) -> bool {
+        if self.ignore_trailing_slash {
+            escaped_eq(trim_slash(a), trim_slash(b))
+        } else {
+            escaped_eq(a, b)
+        }
+    }
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self { CompareOptions::new() }
+}
+
+/// Check if the given targets identify the same resource under the comparison rules for
+/// http and https URIs [RFC9110§4.2.3].
+///
+/// The targets must be of the same form. Schemes and hosts are compared
+/// case-insensitively, an absent or empty port is equivalent to the default port of a
+/// known scheme, an empty absolute-form path is equivalent to `/`, and a percent-encoded
+/// unreserved character is equivalent to the character itself, regardless of the case
+/// of its hex digits. Everything else is compared exactly, so this does no allocation.
+///
+/// ```rust
+/// use uhttp_request_target::{targets_equivalent, ParsedTarget};
+///
+/// let a = ParsedTarget::parse("http://example.com:80/~smith/home.html").unwrap();
+/// let b = ParsedTarget::parse("http://EXAMPLE.com/%7Esmith/home.html").unwrap();
+/// let c = ParsedTarget::parse("http://EXAMPLE.com:/%7esmith/home.html").unwrap();
+///
+/// assert!(targets_equivalent(&a, &b));
+/// assert!(targets_equivalent(&b, &c));
+/// ```
+pub fn targets_equivalent(a: &ParsedTarget, b: &ParsedTarget) -> bool {
+    CompareOptions::new().equivalent(a, b)
+}
+
+/// Retrieve the port of the given target, or the default port of its scheme if it has
+/// none.
+fn effective_port(form: &AbsoluteForm) -> Option<u16> {
+    form.port().or_else(|| Scheme::from_name(form.scheme()).map(Scheme::default_port))
+}
+
+/// Remove a single trailing `/` that ends a non-empty last segment from the given path.
+fn trim_slash(path: &str) -> &str {
+    match path.strip_suffix('/') {
+        Some(trimmed) if !trimmed.is_empty() && !trimmed.ends_with('/') => trimmed,
+        _ => path,
+    }
+}
+
+/// Check if the given optional queries are equivalent.
+fn query_eq(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => escaped_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Check if the given components are equivalent after decoding percent-encoded
+/// unreserved characters and ignoring the case of other escapes.
+fn escaped_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (x, len_a) = unit(a, i);
+        let (y, len_b) = unit(b, j);
+
+        if x != y {
+            return false;
+        }
+
+        i += len_a;
+        j += len_b;
+    }
+
+    i == a.len() && j == b.len()
+}
+
+/// Retrieve the octet at the given offset, along with whether it must stay encoded and
+/// the number of bytes it spans.
+fn unit(s: &[u8], idx: usize) -> ((u8, bool), usize) {
+    match s[idx..] {
+        [b'%', hi, lo, ..] => match (hex_value(hi), hex_value(lo)) {
+            (Some(hi), Some(lo)) => ((hi << 4 | lo, !is_unreserved(hi << 4 | lo)), 3),
+            _ => ((b'%', false), 1),
+        },
+        _ => ((s[idx], false), 1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ParseOptions;
+
+    fn equiv(a: &str, b: &str) -> bool {
+        let (a, b) = (ParsedTarget::parse(a).unwrap(), ParsedTarget::parse(b).unwrap());
+        assert_eq!(targets_equivalent(&a, &b), targets_equivalent(&b, &a));
+        targets_equivalent(&a, &b)
+    }
+
+    #[test]
+    fn test_targets_equivalent() {
+        assert!(equiv("/a/b?c", "/a/b?c"));
+        assert!(equiv("/%7Ea", "/~a"));
+        assert!(equiv("/%7ea?%41", "/~a?A"));
+        assert!(equiv("/a%2fb", "/a%2Fb"));
+        assert!(equiv("/a%zz", "/a%zz"));
+        assert!(!equiv("/a%2Fb", "/a/b"));
+        assert!(!equiv("/A", "/a"));
+        assert!(!equiv("/a?", "/a"));
+        assert!(!equiv("/a/", "/a"));
+        assert!(!equiv("/a%41", "/a%4"));
+
+        assert!(equiv("http://example.com", "http://example.com/"));
+        assert!(equiv("HTTP://EXAMPLE.COM:80/", "http://example.com/"));
+        assert!(equiv("http://example.com:/", "http://example.com:80"));
+        assert!(equiv("https://a:443/b", "HTTPS://A/b"));
+        assert!(!equiv("https://a:80/", "https://a/"));
+        assert!(!equiv("http://a/", "https://a/"));
+        assert!(!equiv("http://a/", "http://b/"));
+        assert!(!equiv("http://u@a/", "http://a/"));
+        assert!(!equiv("http://a/?b", "http://a/"));
+        assert!(!equiv("http://a/B", "http://a/b"));
+        assert!(!equiv("http://a/b", "/b"));
+        assert!(equiv("http://a#x", "http://a#y"));
+        assert!(equiv("http://A:80#x", "http://a/"));
+        assert!(equiv("http://a?q#x", "http://a/?q#y"));
+        assert!(!equiv("http://a#x", "http://b#x"));
+
+        assert!(equiv("A:443", "a:443"));
+        assert!(equiv("a:0443", "a:443"));
+        assert!(equiv("a:", "a"));
+        assert!(!equiv("a:443", "a"));
+        assert!(!equiv("a:443", "b:443"));
+
+        let opts = ParseOptions::new().allow_scheme(Scheme::Ws, true).any_scheme(true);
+        let (a, b) = (opts.parse("ws://a:80/").unwrap(), opts.parse("WS://a").unwrap());
+        assert!(targets_equivalent(&a, &b));
+        let (a, b) = (opts.parse("ftp://a:21/").unwrap(), opts.parse("ftp://a/").unwrap());
+        assert!(!targets_equivalent(&a, &b));
+        let (a, b) = (opts.parse("ftp://a:/").unwrap(), opts.parse("ftp://a/").unwrap());
+        assert!(targets_equivalent(&a, &b));
+
+        assert!(equiv("*", "*"));
+        assert!(!equiv("*", "/"));
+    }
+
+    #[test]
+    fn test_ignore_trailing_slash() {
+        assert_eq!(CompareOptions::default(), CompareOptions::new());
+
+        let opts = CompareOptions::new().ignore_trailing_slash(true);
+        let equiv = |a, b| {
+            let (a, b) = (ParsedTarget::parse(a).unwrap(), ParsedTarget::parse(b).unwrap());
+            assert_eq!(opts.equivalent(&a, &b), opts.equivalent(&b, &a));
+            opts.equivalent(&a, &b)
+        };
+
+        assert!(equiv("/users", "/users/"));
+        assert!(equiv("/users/?a", "/users?a"));
+        assert!(equiv("/%7Eu/", "/~u"));
+        assert!(equiv("/", "/"));
+        assert!(equiv("http://a/b/", "HTTP://A:80/b"));
+        assert!(equiv("http://a", "http://a/"));
+        assert!(!equiv("/", "//"));
+        assert!(!equiv("/users//", "/users"));
+        assert!(!equiv("/users//", "/users/"));
+        assert!(!equiv("/users/", "/users/?"));
+        assert!(!equiv("/users%2F", "/users"));
+        assert!(!equiv("http://a/", "http://a//"));
+    }
+}
@@ -0,0 +1,448 @@
+//! Errors that can occur while parsing a target.
+
+use core::fmt;
+
+/// Error returned when a target is rejected, locating the first violation.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseError {
+    kind: ErrorKind,
+    offset: usize,
+    byte: Option<u8>,
+}
+
+impl ParseError {
+    /// Create a new error of the given kind at the given offset, with the byte found
+    /// there.
+    pub(crate) const fn new(kind: ErrorKind, offset: usize, byte: Option<u8>) -> Self {
+        ParseError { kind, offset, byte }
+    }
+
+    /// Create a new error of the given kind at the given offset into the given input.
+    pub(crate) const fn at(kind: ErrorKind, s: &[u8], offset: usize) -> Self {
+        let byte = if offset < s.len() { Some(s[offset]) } else { None };
+        ParseError::new(kind, offset, byte)
+    }
+
+    /// Move the error forward by the given number of bytes, for an error found in a slice
+    /// starting at that offset.
+    pub(crate) const fn shift(self, by: usize) -> Self {
+        ParseError::new(self.kind, self.offset + by, self.byte)
+    }
+
+    /// Retrieve the reason the target was rejected.
+    pub const fn kind(&self) -> ErrorKind { self.kind }
+
+    /// Retrieve the offset of the first violation from the start of the input.
+    ///
+    /// This is the length of the input if the violation is a missing byte, such as in an
+    /// empty target.
+    pub const fn offset(&self) -> usize { self.offset }
+
+    /// Retrieve the byte at the offset, if the offset is within the input.
+    pub const fn byte(&self) -> Option<u8> { self.byte }
+
+    /// Retrieve the stable numeric code of the error kind.
+    pub const fn code(&self) -> u16 { self.kind.code() }
+
+    /// Retrieve an explanation of the error kind, citing the relevant RFC section.
+    pub const fn explain(&self) -> &'static str { self.kind.explain() }
+}
+
+/// Reason a target was rejected.
+///
+/// New kinds may be added as checks are added, so matches on the kind need a wildcard
+/// arm. Each kind keeps its `code` across releases.
+///
+/// ```rust
+/// use uhttp_request_target::{ErrorKind, RequestTarget};
+///
+/// let status = match RequestTarget::classify(b"/a\nb").unwrap_err().kind() {
+///     ErrorKind::TooLong => 414,
+///     _ => 400,
+/// };
+///
+/// assert_eq!(status, 400);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The target is empty [RFC7230§5.3].
+    Empty,
+    /// The target begins or ends with whitespace [RFC7230§3.1.1].
+    SurroundingWhitespace,
+    /// The target contains a CR or LF octet, which can never appear within a request
+    /// line [RFC7230§3.1.1].
+    IllegalCharacter,
+    /// The target begins with a scheme other than `http` or `https`, or one that isn't
+    /// allowed by the `ParseOptions` in use [RFC7230§5.3.2].
+    UnknownScheme,
+    /// The target has no HTTP scheme or leading slash but contains a slash, which isn't
+    /// allowed in the authority form [RFC7230§5.3.3].
+    SlashInAuthority,
+    /// The host isn't a well-formed IP literal or registered name, or has unbalanced
+    /// brackets [RFC3986§3.2.2].
+    InvalidHost,
+    /// The port isn't a decimal number in the 16-bit range [RFC3986§3.2.3].
+    InvalidPort,
+    /// A `%` isn't followed by two hex digits [RFC3986§2.1].
+    InvalidPercentEncoding,
+    /// The target bytes aren't valid UTF-8.
+    InvalidUtf8,
+    /// The target is valid but not of the form required by the caller.
+    UnexpectedForm,
+    /// The target is longer than the allowed maximum, which can be answered with
+    /// `414 URI Too Long` [RFC7231§6.5.12].
+    TooLong,
+    /// The path contains a byte that must be percent-encoded [RFC3986§3.3].
+    InvalidPath,
+    /// The query contains a byte that must be percent-encoded [RFC3986§3.4].
+    InvalidQuery,
+    /// The target contains SP, HTAB, or another control byte [RFC3986§2].
+    ControlCharacter,
+    /// The target contains a `#` fragment, which is never sent in a request
+    /// [RFC7230§5.1].
+    Fragment,
+    /// The authority of an absolute-form target contains deprecated userinfo
+    /// [RFC7230§2.7.1].
+    Userinfo,
+    /// The userinfo contains a byte that must be percent-encoded [RFC3986§3.2.1].
+    InvalidUserinfo,
+    /// The target contains a byte above 0x7F, which isn't allowed by the `ParseOptions`
+    /// in use [RFC3986§2].
+    NonAscii,
+    /// The target contains a percent-encoded CR or LF, which may split a response or
+    /// forge log lines once decoded [RFC7230§9.4].
+    EncodedLineBreak,
+    /// The target contains a NUL octet, which can never appear within a request line and
+    /// may be treated as a terminator by backends written in C [RFC7230§3.1.1].
+    NulByte,
+    /// The path is longer than the limit set by `ParseOptions::max_path_len`.
+    PathTooLong,
+    /// The query is longer than the limit set by `ParseOptions::max_query_len`.
+    QueryTooLong,
+    /// The host is longer than the limit set by `ParseOptions::max_host_len`.
+    HostTooLong,
+    /// The path has more segments than the limit set by `ParseOptions::max_segments`.
+    TooManySegments,
+    /// The query has more parameters than the limit set by `ParseOptions::max_params`.
+    TooManyParams,
+    /// The target contains a backslash, which some backends treat as a path separator.
+    Backslash,
+    /// The host can't be converted to an ASCII domain name under IDNA.
+    InvalidIdn,
+    /// A path segment decodes to a separator or other name that can't be safely mapped onto
+    /// a file system.
+    UnsafePathSegment,
+    /// A reference can't be resolved against the base target into another request target.
+    UnresolvableReference,
+    /// A route pattern or other path matcher is malformed.
+    InvalidPattern,
+}
+
+/// Every kind of error, in order of code.
+const KINDS: [ErrorKind; 30] = [
+    ErrorKind::Empty,
+    ErrorKind::SurroundingWhitespace,
+    ErrorKind::IllegalCharacter,
+    ErrorKind::UnknownScheme,
+    ErrorKind::SlashInAuthority,
+    ErrorKind::InvalidHost,
+    ErrorKind::InvalidPort,
+    ErrorKind::InvalidPercentEncoding,
+    ErrorKind::InvalidUtf8,
+    ErrorKind::UnexpectedForm,
+    ErrorKind::TooLong,
+    ErrorKind::InvalidPath,
+    ErrorKind::InvalidQuery,
+    ErrorKind::ControlCharacter,
+    ErrorKind::Fragment,
+    ErrorKind::Userinfo,
+    ErrorKind::InvalidUserinfo,
+    ErrorKind::NonAscii,
+    ErrorKind::EncodedLineBreak,
+    ErrorKind::NulByte,
+    ErrorKind::PathTooLong,
+    ErrorKind::QueryTooLong,
+    ErrorKind::HostTooLong,
+    ErrorKind::TooManySegments,
+    ErrorKind::TooManyParams,
+    ErrorKind::Backslash,
+    ErrorKind::InvalidIdn,
+    ErrorKind::UnsafePathSegment,
+    ErrorKind::UnresolvableReference,
+    ErrorKind::InvalidPattern,
+];
+
+impl ErrorKind {
+    /// Retrieve the numeric code of the error kind.
+    ///
+    /// Codes are stable across releases, so they can be used to identify errors in logs
+    /// or across FFI boundaries. New kinds are assigned new codes, and codes of removed
+    /// kinds are never reused.
+    pub const fn code(&self) -> u16 {
+        match *self {
+            ErrorKind::Empty => 1,
+            ErrorKind::SurroundingWhitespace => 2,
+            ErrorKind::IllegalCharacter => 3,
+            ErrorKind::UnknownScheme => 4,
+            ErrorKind::SlashInAuthority => 5,
+            ErrorKind::InvalidHost => 6,
+            ErrorKind::InvalidPort => 7,
+            ErrorKind::InvalidPercentEncoding => 8,
+            ErrorKind::InvalidUtf8 => 9,
+            ErrorKind::UnexpectedForm => 10,
+            ErrorKind::TooLong => 11,
+            ErrorKind::InvalidPath => 12,
+            ErrorKind::InvalidQuery => 13,
+            ErrorKind::ControlCharacter => 14,
+            ErrorKind::Fragment => 15,
+            ErrorKind::Userinfo => 16,
+            ErrorKind::InvalidUserinfo => 17,
+            ErrorKind::NonAscii => 18,
+            ErrorKind::EncodedLineBreak => 19,
+            ErrorKind::NulByte => 20,
+            ErrorKind::PathTooLong => 21,
+            ErrorKind::QueryTooLong => 22,
+            ErrorKind::HostTooLong => 23,
+            ErrorKind::TooManySegments => 24,
+            ErrorKind::TooManyParams => 25,
+            ErrorKind::Backslash => 26,
+            ErrorKind::InvalidIdn => 27,
+            ErrorKind::UnsafePathSegment => 28,
+            ErrorKind::UnresolvableReference => 29,
+            ErrorKind::InvalidPattern => 30,
+        }
+    }
+
+    /// Retrieve the error kind with the given numeric code, if any.
+    pub fn from_code(code: u16) -> Option<Self> {
+        KINDS.iter().copied().find(|k| k.code() == code)
+    }
+
+    /// Retrieve an explanation of the rule that was violated, citing the relevant RFC
+    /// section.
+    ///
+    /// This is meant for developer-facing output, such as the body of a `400 Bad
+    /// Request` response or the output of debug tooling.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RequestTarget;
+    ///
+    /// let err = RequestTarget::classify(b"example.com/a").unwrap_err();
+    /// assert_eq!(err.explain(), "authority-form must not contain '/', RFC 9112 §3.2.3");
+    /// ```
+    pub const fn explain(&self) -> &'static str {
+        match *self {
+            ErrorKind::Empty => "request-target must not be empty, RFC 9112 §3.2",
+            ErrorKind::SurroundingWhitespace => {
+                "request-target must be separated from the method and version by a single \
+                 SP, RFC 9112 §3"
+            },
+            ErrorKind::IllegalCharacter => {
+                "request-target must not contain CR or LF, RFC 9112 §2.2"
+            },
+            ErrorKind::UnknownScheme => {
+                "absolute-form must use the http or https scheme, RFC 9110 §4.2"
+            },
+            ErrorKind::SlashInAuthority => {
+                "authority-form must not contain '/', RFC 9112 §3.2.3"
+            },
+            ErrorKind::InvalidHost => {
+                "host must be a bracketed IP literal or a reg-name, RFC 3986 §3.2.2"
+            },
+            ErrorKind::InvalidPort => {
+                "port must be a decimal number from 0 to 65535, RFC 3986 §3.2.3"
+            },
+            ErrorKind::InvalidPercentEncoding => {
+                "'%' must be followed by two hexadecimal digits, RFC 3986 §2.1"
+            },
+            ErrorKind::InvalidUtf8 => {
+                "request-target must be UTF-8 to be processed as text, RFC 3629 §3"
+            },
+            ErrorKind::UnexpectedForm => {
+                "request-target form must be the one required by the method, RFC 9112 §3.2"
+            },
+            ErrorKind::TooLong => {
+                "request-target longer than the server allows is answered with 414 URI Too \
+                 Long, RFC 9110 §15.5.15"
+            },
+            ErrorKind::InvalidPath => {
+                "path bytes other than pchar and '/' must be percent-encoded, RFC 3986 §3.3"
+            },
+            ErrorKind::InvalidQuery => {
+                "query bytes other than pchar, '/', and '?' must be percent-encoded, RFC 3986 \
+                 §3.4"
+            },
+            ErrorKind::ControlCharacter => {
+                "request-target must not contain SP, HTAB, or other control characters, RFC \
+                 3986 §2"
+            },
+            ErrorKind::Fragment => {
+                "request-target must not contain a fragment, RFC 9112 §3.2"
+            },
+            ErrorKind::Userinfo => {
+                "http URIs must not contain userinfo, RFC 9110 §4.2.4"
+            },
+            ErrorKind::InvalidUserinfo => {
+                "userinfo bytes other than unreserved, sub-delims, and ':' must be \
+                 percent-encoded, RFC 3986 §3.2.1"
+            },
+            ErrorKind::NonAscii => {
+                "request-target bytes outside US-ASCII must be percent-encoded, RFC 3986 §2.1"
+            },
+            ErrorKind::EncodedLineBreak => {
+                "percent-encoded CR or LF can split responses or logs once decoded, RFC 9112 \
+                 §11.1"
+            },
+            ErrorKind::NulByte => {
+                "request-target must not contain NUL, which other parsers may treat as its \
+                 end, RFC 9112 §3.2"
+            },
+            ErrorKind::PathTooLong => {
+                "request-target path exceeds the configured limit, which may be answered \
+                 with 414 URI Too Long, RFC 9110 §15.5.15"
+            },
+            ErrorKind::QueryTooLong => {
+                "request-target query exceeds the configured limit, which may be answered \
+                 with 414 URI Too Long, RFC 9110 §15.5.15"
+            },
+            ErrorKind::HostTooLong => {
+                "request-target host exceeds the configured limit, which may be answered \
+                 with 400 Bad Request, RFC 9110 §15.5.1"
+            },
+            ErrorKind::TooManySegments => {
+                "request-target path has more segments than the configured limit, RFC 9110 \
+                 §4.2.1"
+            },
+            ErrorKind::TooManyParams => {
+                "request-target query has more parameters than the configured limit, RFC \
+                 9110 §4.2.1"
+            },
+            ErrorKind::Backslash => {
+                "request-target contains a backslash, which isn't allowed unencoded and \
+                 may be treated as a path separator, RFC 3986 §2"
+            },
+            ErrorKind::InvalidIdn => {
+                "internationalized host must convert to ASCII labels under IDNA before use in \
+                 a URI, RFC 5891 §4"
+            },
+            ErrorKind::UnsafePathSegment => {
+                "request-target path segment decodes to a name that would escape or alias the \
+                 served directory, RFC 9110 §17.3"
+            },
+            ErrorKind::UnresolvableReference => {
+                "reference must resolve against a base with a scheme and hierarchical path \
+                 into an absolute URI or absolute path, RFC 3986 §5.2"
+            },
+            ErrorKind::InvalidPattern => {
+                "path pattern must be an absolute path whose segments are literals or matcher \
+                 syntax, such as `{name}` captures, RFC 3986 §3.3"
+            },
+        }
+    }
+
+    /// Retrieve a short description of the error.
+    pub(crate) fn message(&self) -> &'static str {
+        match *self {
+            ErrorKind::Empty => "target is empty",
+            ErrorKind::SurroundingWhitespace => "target has surrounding whitespace",
+            ErrorKind::IllegalCharacter => "target contains CR or LF",
+            ErrorKind::UnknownScheme => "target has a scheme other than http or https",
+            ErrorKind::SlashInAuthority => "authority-form target contains a slash",
+            ErrorKind::InvalidHost => "host is not a valid IP literal or registered name",
+            ErrorKind::InvalidPort => "port is not a 16-bit decimal number",
+            ErrorKind::InvalidPercentEncoding => "target has malformed percent-encoding",
+            ErrorKind::InvalidUtf8 => "target is not valid UTF-8",
+            ErrorKind::UnexpectedForm => "target is not of the expected form",
+            ErrorKind::TooLong => "target exceeds maximum length",
+            ErrorKind::InvalidPath => "path contains a byte that must be percent-encoded",
+            ErrorKind::InvalidQuery => "query contains a byte that must be percent-encoded",
+            ErrorKind::ControlCharacter => "target contains whitespace or a control character",
+            ErrorKind::Fragment => "target contains a fragment",
+            ErrorKind::Userinfo => "target contains userinfo",
+            ErrorKind::InvalidUserinfo => "userinfo contains an invalid character",
+            ErrorKind::NonAscii => "target contains a non-ASCII byte",
+            ErrorKind::EncodedLineBreak => "target contains a percent-encoded CR or LF",
+            ErrorKind::NulByte => "target contains a NUL byte",
+            ErrorKind::PathTooLong => "target path is too long",
+            ErrorKind::QueryTooLong => "target query is too long",
+            ErrorKind::HostTooLong => "target host is too long",
+            ErrorKind::TooManySegments => "target path has too many segments",
+            ErrorKind::TooManyParams => "target query has too many parameters",
+            ErrorKind::Backslash => "target contains a backslash",
+            ErrorKind::InvalidIdn => "host is not a valid internationalized domain name",
+            ErrorKind::UnsafePathSegment => "path segment can't be mapped onto a file system",
+            ErrorKind::UnresolvableReference => "reference can't be resolved against base",
+            ErrorKind::InvalidPattern => "path pattern is malformed",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt.write_str(self.message()) }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Empty => fmt::Display::fmt(&self.kind, fmt),
+            kind => write!(fmt, "{} at offset {}", kind, self.offset),
+        }
+    }
+}
+
+// This is the same trait as `std::error::Error`, so errors can be boxed or passed up
+// with `?` in std code while remaining available without std.
+impl core::error::Error for ParseError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ErrorKind::TooLong.to_string(), "target exceeds maximum length");
+        assert_eq!(ParseError::at(ErrorKind::Empty, b"", 0).to_string(),
+            "target is empty");
+        assert_eq!(ParseError::at(ErrorKind::SlashInAuthority, b"a/b", 1).to_string(),
+            "authority-form target contains a slash at offset 1");
+    }
+
+    #[test]
+    fn test_code() {
+        for (idx, kind) in KINDS.iter().enumerate() {
+            assert_eq!(kind.code() as usize, idx + 1);
+            assert_eq!(ErrorKind::from_code(kind.code()), Some(*kind));
+        }
+
+        assert_eq!(ErrorKind::from_code(0), None);
+        assert_eq!(ErrorKind::from_code(31), None);
+        assert_eq!(ErrorKind::TooLong.code(), 11);
+        assert_eq!(ErrorKind::InvalidPattern.code(), 30);
+        assert_eq!(::RequestTarget::classify(b"a/b").unwrap_err().code(), 5);
+    }
+
+    #[test]
+    fn test_explain() {
+        for kind in &KINDS {
+            assert!(kind.explain().contains(", RFC "));
+        }
+
+        let e = ::RequestTarget::classify(b"").unwrap_err();
+        assert_eq!(e.explain(), "request-target must not be empty, RFC 9112 §3.2");
+    }
+
+    #[test]
+    fn test_error() {
+        use std::error::Error;
+
+        fn parse(s: &str) -> Result<::RequestTarget, Box<dyn Error>> {
+            Ok(s.parse()?)
+        }
+
+        assert_eq!(parse("a/b").unwrap_err().to_string(),
+            "authority-form target contains a slash at offset 1");
+    }
+}
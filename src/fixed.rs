@@ -0,0 +1,117 @@
+//! Fixed-capacity owned targets, available with the `heapless` feature.
+
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Deref;
+
+use heapless::String;
+
+use {ErrorKind, ParseError, ParsedTarget, RequestTargetStr};
+
+/// Owned request target string stored inline with a capacity of `N` bytes.
+///
+/// This stands in for `RequestTargetBuf` on targets without a heap, so parsed targets
+/// can be kept in static tables or on the stack.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct HeaplessTarget<const N: usize>(String<N>);
+
+impl<const N: usize> HeaplessTarget<N> {
+    /// Parse the given target and copy it in.
+    ///
+    /// Return `ErrorKind::TooLong` if the target doesn't fit in the capacity.
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        if s.len() > N {
+            return Err(ParseError::at(ErrorKind::TooLong, s.as_bytes(), N));
+        }
+
+        Self::from_target_str(RequestTargetStr::new(s)?)
+    }
+
+    /// Copy the given already-parsed target in, returning `ErrorKind::TooLong` if it
+    /// doesn't fit in the capacity.
+    pub fn from_target_str(t: &RequestTargetStr) -> Result<Self, ParseError> {
+        String::try_from(t.as_str()).map(HeaplessTarget)
+            .map_err(|()| ParseError::at(ErrorKind::TooLong, t.as_str().as_bytes(), N))
+    }
+
+    /// Borrow the target.
+    pub fn as_target_str(&self) -> &RequestTargetStr {
+        RequestTargetStr::from_str_unchecked(&self.0)
+    }
+
+    /// Consume the target, returning the underlying string.
+    pub fn into_string(self) -> String<N> { self.0 }
+}
+
+impl<'a, const N: usize> TryFrom<ParsedTarget<'a>> for HeaplessTarget<N> {
+    type Error = ParseError;
+
+    fn try_from(t: ParsedTarget<'a>) -> Result<Self, ParseError> {
+        Self::from_target_str(t.as_target_str())
+    }
+}
+
+impl<const N: usize> Deref for HeaplessTarget<N> {
+    type Target = RequestTargetStr;
+
+    fn deref(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl<const N: usize> Borrow<RequestTargetStr> for HeaplessTarget<N> {
+    fn borrow(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl<const N: usize> Borrow<str> for HeaplessTarget<N> {
+    fn borrow(&self) -> &str { &self.0 }
+}
+
+impl<const N: usize> AsRef<RequestTargetStr> for HeaplessTarget<N> {
+    fn as_ref(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl<const N: usize> AsRef<str> for HeaplessTarget<N> {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl<const N: usize> fmt::Debug for HeaplessTarget<N> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt::Debug::fmt(&*self.0, fmt) }
+}
+
+impl<const N: usize> fmt::Display for HeaplessTarget<N> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt::Display::fmt(&*self.0, fmt) }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for HeaplessTarget<N> {
+    fn format(&self, fmt: defmt::Formatter) { defmt::write!(fmt, "{=str}", &*self.0) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use RequestTarget;
+
+    #[test]
+    fn test_heapless_target() {
+        let t = HeaplessTarget::<8>::new("/a?b").unwrap();
+        assert_eq!(t.as_str(), "/a?b");
+        assert_eq!(t.kind(), RequestTarget::AbsPath);
+        assert_eq!(t.parsed(), ParsedTarget::parse("/a?b").unwrap());
+
+        assert!(HeaplessTarget::<8>::new("/abcdefg").is_ok());
+        assert_eq!(HeaplessTarget::<8>::new("/abcdefgh").map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(HeaplessTarget::<8>::new("a/b").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+
+        let p = ParsedTarget::parse("http://a/b").unwrap();
+        assert_eq!(HeaplessTarget::<16>::try_from(p).unwrap().kind(), RequestTarget::AbsUri);
+        assert_eq!(HeaplessTarget::<4>::try_from(p).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+
+        let mut table = [None, None];
+        table[0] = HeaplessTarget::<16>::new("/index.html").ok();
+        assert_eq!(table[0].as_ref().map(|t| t.as_str()), Some("/index.html"));
+    }
+}
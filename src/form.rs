@@ -0,0 +1,759 @@
+//! Structured decomposition of classified request targets.
+
+use {ErrorKind, ParseError, RequestTarget, RequestTargetStr};
+use authority::{Authority, HostKind};
+use charset::{check, is_path_byte, is_query_byte};
+#[cfg(feature = "alloc")]
+use alloc::borrow::ToOwned;
+
+#[cfg(feature = "alloc")]
+use RequestTargetBuf;
+
+/// A request target split into the components relevant to its form.
+///
+/// Each variant borrows slices of the original target string, so classification and
+/// decomposition happen in a single pass without copying.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParsedTarget<'a> {
+    /// Origin form, such as `/where?q=now`.
+    AbsPath(OriginForm<'a>),
+    /// Absolute form, such as `http://example.com/where?q=now`.
+    AbsUri(AbsoluteForm<'a>),
+    /// Authority form, such as `example.com:443`.
+    Authority(AuthorityForm<'a>),
+    /// Asterisk form, `*`.
+    ServerOptions,
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Classify the given target and split it into its components.
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        let parsed = Self::split(s, s.parse()?);
+        parsed.check_structure()?;
+        Ok(parsed)
+    }
+
+    /// Check that any IP literal in the authority is properly bracketed and any port is
+    /// numeric.
+    pub(crate) fn check_structure(&self) -> Result<(), ParseError> {
+        match *self {
+            ParsedTarget::AbsUri(ref form) => form.check_structure(),
+            ParsedTarget::Authority(ref form) => form.authority.check_structure(form.target),
+            ParsedTarget::AbsPath(_) | ParsedTarget::ServerOptions => Ok(()),
+        }
+    }
+
+    /// Split the given target, already classified as the given kind, into components.
+    pub(crate) fn split(s: &'a str, kind: RequestTarget) -> Self {
+        match kind {
+            RequestTarget::AbsPath => ParsedTarget::AbsPath(OriginForm::split(s)),
+            RequestTarget::AbsUri => ParsedTarget::AbsUri(AbsoluteForm::split(s)),
+            RequestTarget::Authority => ParsedTarget::Authority(AuthorityForm::split(s)),
+            RequestTarget::ServerOptions => ParsedTarget::ServerOptions,
+        }
+    }
+
+    /// Check the target against the strict grammar of its form.
+    ///
+    /// Classification only rejects what can never appear in a request line, so targets
+    /// like `/path/sub boop/42` are accepted by `parse`. This additionally checks that
+    /// each path and query byte is in the pchar or query character class
+    /// [RFC3986§3.3–3.4], or is part of a well-formed percent-encoded octet, and that any
+    /// authority is well formed, as with `AuthorityForm::validate`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{ParsedTarget, ErrorKind};
+    ///
+    /// let t = ParsedTarget::parse("/path/sub boop/42").unwrap();
+    /// let err = t.validate().unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::InvalidPath);
+    /// assert_eq!(err.offset(), 9);
+    /// ```
+    pub fn validate(&self) -> Result<(), ParseError> {
+        match *self {
+            ParsedTarget::AbsPath(ref form) => form.validate(),
+            ParsedTarget::AbsUri(ref form) => form.validate(),
+            ParsedTarget::Authority(ref form) => form.validate(),
+            ParsedTarget::ServerOptions => Ok(()),
+        }
+    }
+
+    /// Retrieve the fragment after the first `#` of an origin-form or absolute-form
+    /// target, if any.
+    ///
+    /// Fragments are only meaningful to the client and are never sent in a request
+    /// [RFC7230§5.1], so one here indicates a broken or malicious client. The path,
+    /// query, and authority all end before it.
+    pub fn fragment(&self) -> Option<&'a str> {
+        match *self {
+            ParsedTarget::AbsPath(_) | ParsedTarget::AbsUri(_) => {
+                let s = self.as_str();
+                s.find('#').map(|idx| &s[idx + 1..])
+            },
+            ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => None,
+        }
+    }
+
+    /// Retrieve the form of the target.
+    pub fn kind(&self) -> RequestTarget {
+        match *self {
+            ParsedTarget::AbsPath(_) => RequestTarget::AbsPath,
+            ParsedTarget::AbsUri(_) => RequestTarget::AbsUri,
+            ParsedTarget::Authority(_) => RequestTarget::Authority,
+            ParsedTarget::ServerOptions => RequestTarget::ServerOptions,
+        }
+    }
+
+    /// Retrieve the entire target string.
+    pub fn as_str(&self) -> &'a str {
+        match *self {
+            ParsedTarget::AbsPath(ref form) => form.as_str(),
+            ParsedTarget::AbsUri(ref form) => form.as_str(),
+            ParsedTarget::Authority(ref form) => form.as_str(),
+            ParsedTarget::ServerOptions => "*",
+        }
+    }
+
+    /// Retrieve the entire target as a borrowed, already-classified target string.
+    pub fn as_target_str(&self) -> &'a RequestTargetStr {
+        RequestTargetStr::from_str_unchecked(self.as_str())
+    }
+
+    /// Copy the target into an owned target that no longer borrows the input.
+    ///
+    /// The components can be recovered with `RequestTargetBuf::parsed`.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> RequestTargetBuf { self.as_target_str().to_owned() }
+}
+
+/// Origin-form target, consisting of an absolute path and optional query
+/// [RFC7230§5.3.1].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OriginForm<'a> {
+    target: &'a str,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> OriginForm<'a> {
+    /// Parse the given origin-form target.
+    ///
+    /// Targets of any other form are rejected with `ErrorKind::UnexpectedForm`.
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        match ParsedTarget::parse(s) {
+            Ok(ParsedTarget::AbsPath(form)) => Ok(form),
+            Ok(_) => Err(ParseError::at(ErrorKind::UnexpectedForm, s.as_bytes(), 0)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Split the given target, already classified as origin form, into components.
+    fn split(s: &'a str) -> Self {
+        let (path, query) = split_query(s);
+        OriginForm { target: s, path, query }
+    }
+
+    /// Check that the path and query contain only the bytes allowed by their grammar, as
+    /// with `ParsedTarget::validate`.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        validate_path_query(self.target, self.path, self.query)
+    }
+
+    /// Retrieve the form of the target, which is always `RequestTarget::AbsPath`.
+    pub fn kind(&self) -> RequestTarget { RequestTarget::AbsPath }
+
+    /// Retrieve the entire target string.
+    pub fn as_str(&self) -> &'a str { self.target }
+
+    /// Copy the target into an owned target that no longer borrows the input.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> RequestTargetBuf {
+        RequestTargetStr::from_str_unchecked(self.target).to_owned()
+    }
+
+    /// Retrieve the path, up to but not including the first `?` or `#`.
+    ///
+    /// The path always begins with a slash.
+    pub fn path(&self) -> &'a str { self.path }
+
+    /// Retrieve the query after the first `?`, up to any `#`, if any.
+    ///
+    /// A target ending in a bare `?` has an empty query.
+    pub fn query(&self) -> Option<&'a str> { self.query }
+}
+
+/// Absolute-form target, consisting of a scheme, authority, path, and optional query
+/// [RFC7230§5.3.2].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AbsoluteForm<'a> {
+    target: &'a str,
+    scheme: &'a str,
+    authority: &'a str,
+    parts: Authority<'a>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Parse the given absolute-form target.
+    ///
+    /// Targets of any other form are rejected with `ErrorKind::UnexpectedForm`,
+    /// targets with a port that isn't a decimal number in the 16-bit range with
+    /// `ErrorKind::InvalidPort`, and http and https targets with an empty host, such as
+    /// `http:///a`, with `ErrorKind::InvalidHost` at the authority.
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        match ParsedTarget::parse(s) {
+            Ok(ParsedTarget::AbsUri(form)) => Ok(form),
+            Ok(_) => Err(ParseError::at(ErrorKind::UnexpectedForm, s.as_bytes(), 0)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Split the given target, already classified as absolute form, into components.
+    fn split(s: &'a str) -> Self {
+        let (scheme, rest) = match s.find("://") {
+            Some(idx) => (&s[..idx], &s[idx + 3..]),
+            None => ("", s),
+        };
+
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(end);
+        let (path, query) = split_query(rest);
+
+        let parts = Authority::split(authority);
+
+        AbsoluteForm { target: s, scheme, authority, parts, path, query }
+    }
+
+    /// Check the structure of the authority, and that an http or https target has a
+    /// host, which its URIs require [RFC9110§4.2.1].
+    fn check_structure(&self) -> Result<(), ParseError> {
+        let http = self.scheme.eq_ignore_ascii_case("http") ||
+                   self.scheme.eq_ignore_ascii_case("https");
+
+        if http && self.parts.host.is_empty() {
+            let idx = offset(self.target, self.authority);
+            return Err(ParseError::at(ErrorKind::InvalidHost, self.target.as_bytes(), idx));
+        }
+
+        self.parts.check_structure(self.target)
+    }
+
+    /// Check that the authority, path, and query contain only the bytes allowed by their
+    /// grammar, as with `ParsedTarget::validate`.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        self.parts.validate(self.target)?;
+        validate_path_query(self.target, self.path, self.query)
+    }
+
+    /// Retrieve the form of the target, which is always `RequestTarget::AbsUri`.
+    pub fn kind(&self) -> RequestTarget { RequestTarget::AbsUri }
+
+    /// Retrieve the entire target string.
+    pub fn as_str(&self) -> &'a str { self.target }
+
+    /// Copy the target into an owned target that no longer borrows the input.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> RequestTargetBuf {
+        RequestTargetStr::from_str_unchecked(self.target).to_owned()
+    }
+
+    /// Retrieve the scheme before the `://` separator, such as `http`.
+    pub fn scheme(&self) -> &'a str { self.scheme }
+
+    /// Retrieve the authority between the `://` separator and the path, query, or
+    /// fragment.
+    ///
+    /// This may include userinfo and a port, such as `user@example.com:8080`.
+    pub fn authority(&self) -> &'a str { self.authority }
+
+    /// Retrieve the userinfo before the last `@` in the authority, if any.
+    ///
+    /// Userinfo is deprecated in http and https URIs and should be rejected by servers
+    /// [RFC7230§2.7.1].
+    pub fn userinfo(&self) -> Option<&'a str> { self.parts.userinfo }
+
+    /// Retrieve the host within the authority, such as `example.com` or `[::1]`.
+    ///
+    /// IP literals keep their enclosing brackets.
+    pub fn host(&self) -> &'a str { self.parts.host }
+
+    /// Retrieve the kind of the host, determined by its syntax.
+    pub fn host_kind(&self) -> HostKind { HostKind::of(self.parts.host) }
+
+    /// Retrieve the port digits after the host, if any.
+    pub fn port_str(&self) -> Option<&'a str> { self.parts.port }
+
+    /// Retrieve the numeric port, if any.
+    ///
+    /// The port was already checked by `parse`, and an empty port is treated the same as
+    /// a missing one [RFC3986§6.2.3].
+    pub fn port(&self) -> Option<u16> {
+        self.parts.port.and_then(|p| p.parse().ok())
+    }
+
+    /// Retrieve the path after the authority, up to but not including the first `?` or
+    /// `#`.
+    ///
+    /// The path is empty when the target has no slash after the authority.
+    pub fn path(&self) -> &'a str { self.path }
+
+    /// Retrieve the path like `path`, but as `/` when it's empty.
+    ///
+    /// An empty path is equivalent to `/` in http and https URIs [RFC3986§6.2.3], so
+    /// `http://example.com` and `http://example.com/` request the same resource.
+    pub fn path_or_root(&self) -> &'a str {
+        if self.path.is_empty() { "/" } else { self.path }
+    }
+
+    /// Retrieve the query after the first `?`, up to any `#`, if any.
+    pub fn query(&self) -> Option<&'a str> { self.query }
+}
+
+/// Authority-form target, consisting of a host and optional port [RFC7230§5.3.3].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AuthorityForm<'a> {
+    target: &'a str,
+    authority: Authority<'a>,
+}
+
+impl<'a> AuthorityForm<'a> {
+    /// Parse the given authority-form target.
+    ///
+    /// Targets of any other form are rejected with `ErrorKind::UnexpectedForm`, and
+    /// targets with a port that isn't a decimal number in the 16-bit range with
+    /// `ErrorKind::InvalidPort`.
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        match ParsedTarget::parse(s) {
+            Ok(ParsedTarget::Authority(form)) => Ok(form),
+            Ok(_) => Err(ParseError::at(ErrorKind::UnexpectedForm, s.as_bytes(), 0)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Split the given target, already classified as authority form, into components.
+    fn split(s: &'a str) -> Self {
+        AuthorityForm { target: s, authority: Authority::split(s) }
+    }
+
+    /// Check that the userinfo, host, and port are each allowed by their grammar
+    /// [RFC3986§3.2].
+    ///
+    /// Any userinfo and registered name may only contain unreserved, sub-delims, or
+    /// percent-encoded bytes, and userinfo may also contain `:`. An IP literal must be
+    /// an IPv6 address or IPvFuture literal enclosed in brackets, and an IPv6 address may
+    /// include a `%25`-encoded zone ID [RFC6874§2]. The host can't be empty, and any port
+    /// must be numeric.
+    /// Only the bracket and port checks are done by `parse`.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        self.authority.validate(self.target)
+    }
+
+    /// Retrieve the form of the target, which is always `RequestTarget::Authority`.
+    pub fn kind(&self) -> RequestTarget { RequestTarget::Authority }
+
+    /// Retrieve the entire target string.
+    pub fn as_str(&self) -> &'a str { self.target }
+
+    /// Copy the target into an owned target that no longer borrows the input.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> RequestTargetBuf {
+        RequestTargetStr::from_str_unchecked(self.target).to_owned()
+    }
+
+    /// Retrieve the userinfo before the `@` separator, if any.
+    ///
+    /// Authority-form targets aren't expected to carry userinfo, so servers may choose to
+    /// reject targets where this is present, such as with `ParseOptions::reject_userinfo`.
+    pub fn userinfo(&self) -> Option<&'a str> { self.authority.userinfo }
+
+    /// Retrieve the host, such as `example.com` or `[::1]`.
+    ///
+    /// IP literals keep their enclosing brackets.
+    pub fn host(&self) -> &'a str { self.authority.host }
+
+    /// Retrieve the kind of the host, determined by its syntax.
+    ///
+    /// The host itself is only checked against the grammar of its kind by `validate`.
+    pub fn host_kind(&self) -> HostKind { HostKind::of(self.authority.host) }
+
+    /// Retrieve the port digits after the `:` separator, if any.
+    ///
+    /// This may be empty for a target like `example.com:`.
+    pub fn port_str(&self) -> Option<&'a str> { self.authority.port }
+
+    /// Retrieve the numeric port, if any.
+    ///
+    /// The port was already checked by `parse`, and an empty port is treated the same as
+    /// a missing one [RFC3986§6.2.3].
+    pub fn port(&self) -> Option<u16> {
+        self.authority.port.and_then(|p| p.parse().ok())
+    }
+}
+
+/// Check that the given path and optional query, which are subslices of the given
+/// target, contain only bytes allowed by their grammar.
+fn validate_path_query(target: &str, path: &str, query: Option<&str>)
+    -> Result<(), ParseError>
+{
+    let s = target.as_bytes();
+    let start = offset(target, path);
+
+    check(s, start..start + path.len(), is_path_byte, ErrorKind::InvalidPath)?;
+
+    let (end, kind) = match query {
+        Some(query) => {
+            let start = offset(target, query);
+            check(s, start..start + query.len(), is_query_byte, ErrorKind::InvalidQuery)?;
+            (start + query.len(), ErrorKind::InvalidQuery)
+        },
+        None => (start + path.len(), ErrorKind::InvalidPath),
+    };
+
+    // Any fragment follows the last component and is invalid in its place.
+    match s.get(end) {
+        Some(&b'#') => Err(ParseError::at(kind, s, end)),
+        _ => Ok(()),
+    }
+}
+
+/// Compute the byte offset of the given subslice within the given base string.
+pub(crate) fn offset(base: &str, part: &str) -> usize {
+    part.as_ptr() as usize - base.as_ptr() as usize
+}
+
+/// Split the given string at the first `?` into path and optional query, both ending at
+/// any `#` fragment.
+fn split_query(s: &str) -> (&str, Option<&str>) {
+    let s = &s[..s.find('#').unwrap_or(s.len())];
+
+    match s.find('?') {
+        Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+        None => (s, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parsed_target() {
+        assert_eq!(ParsedTarget::parse("*"), Ok(ParsedTarget::ServerOptions));
+        assert_eq!(ParsedTarget::parse(" *").map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+
+        assert_eq!(ParsedTarget::parse("/where?q=now"),
+            Ok(ParsedTarget::AbsPath(OriginForm::split("/where?q=now"))));
+
+        assert_eq!(ParsedTarget::parse("example.com:80"),
+            Ok(ParsedTarget::Authority(AuthorityForm::split("example.com:80"))));
+        assert_eq!(ParsedTarget::parse("example.com:http").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(ParsedTarget::parse("example.com/").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+
+        assert_eq!(ParsedTarget::parse("http://zombo.com/a"),
+            Ok(ParsedTarget::AbsUri(AbsoluteForm::split("http://zombo.com/a"))));
+        assert_eq!(ParsedTarget::parse("ftp://rust-lang.org").map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+    }
+
+    #[test]
+    fn test_kind() {
+        for &s in &["*", "/a?b", "http://a/b", "a:80"] {
+            assert_eq!(ParsedTarget::parse(s).unwrap().kind(), s.parse().unwrap());
+        }
+
+        assert_eq!(OriginForm::parse("/").unwrap().kind(), RequestTarget::AbsPath);
+        assert_eq!(AbsoluteForm::parse("http://a").unwrap().kind(), RequestTarget::AbsUri);
+        assert_eq!(AuthorityForm::parse("a").unwrap().kind(), RequestTarget::Authority);
+    }
+
+    #[test]
+    fn test_origin_form() {
+        let f = OriginForm::parse("/path/sub/42").unwrap();
+        assert_eq!(f.path(), "/path/sub/42");
+        assert_eq!(f.query(), None);
+
+        let f = OriginForm::parse("/where?q=now").unwrap();
+        assert_eq!(f.path(), "/where");
+        assert_eq!(f.query(), Some("q=now"));
+
+        let f = OriginForm::parse("/where?").unwrap();
+        assert_eq!(f.path(), "/where");
+        assert_eq!(f.query(), Some(""));
+
+        let f = OriginForm::parse("/a?b?c").unwrap();
+        assert_eq!(f.path(), "/a");
+        assert_eq!(f.query(), Some("b?c"));
+
+        let f = OriginForm::parse("/?").unwrap();
+        assert_eq!(f.path(), "/");
+        assert_eq!(f.query(), Some(""));
+
+        assert_eq!(OriginForm::parse("*").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(OriginForm::parse("example.com").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(OriginForm::parse("http://example.com/a").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(OriginForm::parse(" /a").map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+    }
+
+    #[test]
+    fn test_absolute_form() {
+        let f = AbsoluteForm::parse("http://zombo.com").unwrap();
+        assert_eq!(f.scheme(), "http");
+        assert_eq!(f.authority(), "zombo.com");
+        assert_eq!(f.path(), "");
+        assert_eq!(f.path_or_root(), "/");
+        assert_eq!(f.query(), None);
+
+        assert_eq!(f.host(), "zombo.com");
+        assert_eq!(f.port_str(), None);
+        assert_eq!(f.port(), None);
+
+        let f = AbsoluteForm::parse("https://rust-lang.org:443/a/b?c=d").unwrap();
+        assert_eq!(f.scheme(), "https");
+        assert_eq!(f.authority(), "rust-lang.org:443");
+        assert_eq!(f.host(), "rust-lang.org");
+        assert_eq!(f.host_kind(), HostKind::RegName);
+        assert_eq!(f.port_str(), Some("443"));
+        assert_eq!(f.port(), Some(443));
+        assert_eq!(f.path(), "/a/b");
+        assert_eq!(f.path_or_root(), f.path());
+        assert_eq!(f.query(), Some("c=d"));
+
+        let f = AbsoluteForm::parse("http://zombo.com?q").unwrap();
+        assert_eq!(f.authority(), "zombo.com");
+        assert_eq!(f.path(), "");
+        assert_eq!(f.query(), Some("q"));
+
+        assert_eq!(f.userinfo(), None);
+
+        let f = AbsoluteForm::parse("http://user@zombo.com/").unwrap();
+        assert_eq!(f.authority(), "user@zombo.com");
+        assert_eq!(f.userinfo(), Some("user"));
+        assert_eq!(f.path(), "/");
+        assert_eq!(f.query(), None);
+
+        let f = AbsoluteForm::parse("http://a:b@c@zombo.com?d@e").unwrap();
+        assert_eq!(f.userinfo(), Some("a:b@c"));
+
+        let f = AbsoluteForm::parse("http://@zombo.com").unwrap();
+        assert_eq!(f.userinfo(), Some(""));
+
+        let f = AbsoluteForm::parse("http://u@[::1]:/").unwrap();
+        assert_eq!(f.host(), "[::1]");
+        assert_eq!(f.host_kind(), HostKind::Ipv6);
+        assert_eq!(f.port_str(), Some(""));
+        assert_eq!(f.port(), None);
+
+        for &(s, idx) in &[("http://", 7), ("http://?q", 7), ("HTTPS:///a", 8),
+                           ("http://#f", 7), ("http://u@/", 7), ("http://:80/", 7)] {
+            let e = AbsoluteForm::parse(s).unwrap_err();
+            assert_eq!((e.kind(), e.offset()), (ErrorKind::InvalidHost, idx), "{}", s);
+        }
+
+        assert_eq!(AbsoluteForm::parse("/a").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(AbsoluteForm::parse("zombo.com").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(AbsoluteForm::parse("ftp://zombo.com").map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+
+        let err = |s| {
+            let e = AbsoluteForm::parse(s).unwrap_err();
+            (e.kind(), e.offset())
+        };
+
+        assert_eq!(err("http://a:65536/"), (ErrorKind::InvalidPort, 9));
+        assert_eq!(err("http://a:8a"), (ErrorKind::InvalidPort, 10));
+        assert_eq!(err("http://a:-1?b"), (ErrorKind::InvalidPort, 9));
+        assert_eq!(err("http://u@a:1:2/"), (ErrorKind::InvalidPort, 12));
+        assert_eq!(err("https://[::1/"), (ErrorKind::InvalidHost, 8));
+    }
+
+    #[test]
+    fn test_fragment() {
+        let fragment = |s| ParsedTarget::parse(s).unwrap().fragment();
+
+        assert_eq!(fragment("/a?b"), None);
+        assert_eq!(fragment("/a#b"), Some("b"));
+        assert_eq!(fragment("/a?b#c#d"), Some("c#d"));
+        assert_eq!(fragment("/#"), Some(""));
+        assert_eq!(fragment("http://a/b#c"), Some("c"));
+        assert_eq!(fragment("a#b:80"), None);
+        assert_eq!(fragment("*"), None);
+
+        let f = OriginForm::parse("/a?b#c?d").unwrap();
+        assert_eq!((f.path(), f.query()), ("/a", Some("b")));
+        let f = OriginForm::parse("/a#b?c").unwrap();
+        assert_eq!((f.path(), f.query()), ("/a", None));
+
+        for &(s, authority, port, query) in &[
+            ("http://a#f", "a", None, None),
+            ("http://a:80#f", "a:80", Some(80), None),
+            ("http://a?q#f", "a", None, Some("q")),
+            ("http://a/b#f/c?d", "a", None, None),
+        ] {
+            let f = AbsoluteForm::parse(s).unwrap();
+            assert_eq!(f.authority(), authority, "{}", s);
+            assert_eq!(f.host(), "a", "{}", s);
+            assert_eq!(f.port(), port, "{}", s);
+            assert_eq!(f.query(), query, "{}", s);
+            assert_eq!(fragment(s), Some(&s[s.find('#').unwrap() + 1..]));
+        }
+
+        let f = AbsoluteForm::parse("http://a/b#f/c?d").unwrap();
+        assert_eq!(f.path(), "/b");
+        assert_eq!(AbsoluteForm::parse("http://a#f").unwrap().path(), "");
+    }
+
+    #[test]
+    fn test_validate() {
+        for &s in &["*", "/", "/a/b;c=d/@:!$&'()*+,=-._~", "/a%20b?c=d&e=/f?g", "/?",
+                    "http://a/b%2Fc?d", "http://a", "a:80", "[fe80::1%25eth0]:443",
+                    "http://[::1]:8080/", "https://[::ffff:10.0.0.1]"] {
+            assert_eq!(ParsedTarget::parse(s).unwrap().validate(), Ok(()), "{}", s);
+        }
+
+        let err = |s| {
+            let e = ParsedTarget::parse(s).unwrap().validate().unwrap_err();
+            (e.kind(), e.offset())
+        };
+
+        assert_eq!(err("/path/sub boop/42"), (ErrorKind::InvalidPath, 9));
+        assert_eq!(err("/a\"b"), (ErrorKind::InvalidPath, 2));
+        assert_eq!(err("/a\u{e9}"), (ErrorKind::InvalidPath, 2));
+        assert_eq!(err("/a#b"), (ErrorKind::InvalidPath, 2));
+        assert_eq!(err("/a?b c"), (ErrorKind::InvalidQuery, 4));
+        assert_eq!(err("/a?b#c"), (ErrorKind::InvalidQuery, 4));
+        assert_eq!(err("/a?b[c]"), (ErrorKind::InvalidQuery, 4));
+        assert_eq!(err("/a%2"), (ErrorKind::InvalidPercentEncoding, 2));
+        assert_eq!(err("/a?%g0"), (ErrorKind::InvalidPercentEncoding, 3));
+        assert_eq!(err("http://a/b|c"), (ErrorKind::InvalidPath, 10));
+        assert_eq!(err("http://a?b^"), (ErrorKind::InvalidQuery, 10));
+        let e = ParsedTarget::parse("http://").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::InvalidHost, 7));
+        assert_eq!(err("http://a b@c/"), (ErrorKind::InvalidUserinfo, 8));
+        assert_eq!(err("user name@example.com"), (ErrorKind::InvalidUserinfo, 4));
+        assert_eq!(err("a{b}:80"), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("[fe80::1%eth0]:443"), (ErrorKind::InvalidHost, 8));
+        assert_eq!(err("[1:2]:443"), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("http://[::g]/"), (ErrorKind::InvalidHost, 10));
+        assert_eq!(err("http://[v1]:80/"), (ErrorKind::InvalidHost, 10));
+
+        assert_eq!(OriginForm::parse("/a b").unwrap().validate().unwrap_err().kind(),
+            ErrorKind::InvalidPath);
+    }
+
+    #[test]
+    fn test_authority_form_offset() {
+        let e = AuthorityForm::parse("u@example.com:8a").unwrap_err();
+        assert_eq!((e.kind(), e.offset(), e.byte()),
+            (ErrorKind::InvalidPort, 15, Some(b'a')));
+
+        let e = AuthorityForm::parse("example.com:65536").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::InvalidPort, 12));
+
+        let e = AuthorityForm::parse("u@[::1").unwrap_err();
+        assert_eq!((e.kind(), e.offset(), e.byte()),
+            (ErrorKind::InvalidHost, 2, Some(b'[')));
+    }
+
+    #[test]
+    fn test_authority_form() {
+        let f = AuthorityForm::parse("www.example.com:80").unwrap();
+        assert_eq!(f.userinfo(), None);
+        assert_eq!(f.host(), "www.example.com");
+        assert_eq!(f.host_kind(), HostKind::RegName);
+        assert_eq!(f.port_str(), Some("80"));
+        assert_eq!(f.port(), Some(80));
+
+        let f = AuthorityForm::parse("example.com").unwrap();
+        assert_eq!(f.host(), "example.com");
+        assert_eq!(f.port_str(), None);
+        assert_eq!(f.port(), None);
+
+        let f = AuthorityForm::parse("example.com:").unwrap();
+        assert_eq!(f.host(), "example.com");
+        assert_eq!(f.port_str(), Some(""));
+        assert_eq!(f.port(), None);
+
+        let f = AuthorityForm::parse("user@example.com:65535").unwrap();
+        assert_eq!(f.userinfo(), Some("user"));
+        assert_eq!(f.host(), "example.com");
+        assert_eq!(f.port(), Some(65535));
+
+        let f = AuthorityForm::parse("[::1]:443").unwrap();
+        assert_eq!(f.host(), "[::1]");
+        assert_eq!(f.port(), Some(443));
+
+        assert_eq!(f.host_kind(), HostKind::Ipv6);
+
+        let f = AuthorityForm::parse("[v1.a]:443").unwrap();
+        assert_eq!(f.host(), "[v1.a]");
+        assert_eq!(f.host_kind(), HostKind::IpvFuture);
+        assert_eq!(f.port(), Some(443));
+
+        let f = AuthorityForm::parse("127.0.0.1:80").unwrap();
+        assert_eq!(f.host_kind(), HostKind::Ipv4);
+
+        let f = AuthorityForm::parse("[::1]").unwrap();
+        assert_eq!(f.host(), "[::1]");
+        assert_eq!(f.port(), None);
+
+        assert_eq!(AuthorityForm::parse("example.com:65536").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("example.com:-1").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("example.com:+1").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("example.com:8a").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("example.com:80:80").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("::1").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidPort));
+        assert_eq!(AuthorityForm::parse("[::1").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidHost));
+        assert_eq!(AuthorityForm::parse("[::1]80").map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidHost));
+        assert_eq!(AuthorityForm::parse("/a").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+        assert_eq!(AuthorityForm::parse("*").map_err(|e| e.kind()),
+            Err(ErrorKind::UnexpectedForm));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_into_owned() {
+        let buf = {
+            let s = String::from("/a?b");
+            ParsedTarget::parse(&s).unwrap().into_owned()
+        };
+        assert_eq!(buf.as_str(), "/a?b");
+        assert_eq!(buf.kind(), RequestTarget::AbsPath);
+
+        let buf = ParsedTarget::parse("*").unwrap().into_owned();
+        assert_eq!(buf.as_str(), "*");
+
+        let buf = OriginForm::parse("/a?b").unwrap().into_owned();
+        assert_eq!(buf.parsed(), ParsedTarget::parse("/a?b").unwrap());
+
+        let buf = AbsoluteForm::parse("http://a/b").unwrap().into_owned();
+        assert_eq!(buf.as_str(), "http://a/b");
+        assert_eq!(buf.kind(), RequestTarget::AbsUri);
+
+        let buf = AuthorityForm::parse("a:80").unwrap().into_owned();
+        assert_eq!(buf.as_str(), "a:80");
+        assert_eq!(buf.kind(), RequestTarget::Authority);
+    }
+}
@@ -0,0 +1,132 @@
+//! File system conveniences, available with the `std` feature.
+
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+use {ErrorKind, OriginForm, ParseError};
+use form::offset;
+use path::decode_segment_into;
+
+impl<'a> OriginForm<'a> {
+    /// Map the path onto a file under the given root directory, available with the `std`
+    /// feature.
+    ///
+    /// Each segment is percent-decoded and must be UTF-8, then `.` segments are removed
+    /// and `..` segments remove the previous segment without ever climbing above the root
+    /// [RFC3986§5.2.4], so the result always stays within the root, even for encoded dots
+    /// like `%2e%2e`. Empty segments are skipped, so `//a/` maps to the same file as `/a`.
+    /// A segment that decodes to anything but a single plain file name, such as one
+    /// containing `/` from `%2F`, a backslash, a NUL, or a drive prefix on Windows, is
+    /// rejected with `ErrorKind::UnsafePathSegment` at its start, and each segment is
+    /// checked the same way on every platform except for what the platform considers a
+    /// prefix [RFC9110§17.3]. The query is ignored, and symbolic links under the root
+    /// aren't resolved.
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use uhttp_request_target::{ErrorKind, OriginForm};
+    ///
+    /// let t = OriginForm::parse("/static/../%2e%2e/caf%C3%A9/menu.html?v=1").unwrap();
+    /// let path = t.to_fs_path("/srv/www").unwrap();
+    /// assert_eq!(path, Path::new("/srv/www/caf\u{e9}/menu.html"));
+    ///
+    /// let t = OriginForm::parse("/static/..%2F..%2Fetc/passwd").unwrap();
+    /// assert_eq!(t.to_fs_path("/srv/www").unwrap_err().kind(),
+    ///            ErrorKind::UnsafePathSegment);
+    /// ```
+    pub fn to_fs_path<P: AsRef<Path>>(&self, root: P) -> Result<PathBuf, ParseError> {
+        let target = self.as_str();
+        let mut path = root.as_ref().to_path_buf();
+        let mut depth = 0usize;
+        let mut buf = vec![0; self.path().len()];
+
+        for raw in self.segments() {
+            let start = offset(target, raw);
+            let seg = decode_segment_into(raw, &mut buf).map_err(|e| e.shift(start))?;
+
+            match seg {
+                ".." if depth > 0 => {
+                    path.pop();
+                    depth -= 1;
+                },
+                "" | "." | ".." => {},
+                _ if is_file_name(seg) => {
+                    path.push(seg);
+                    depth += 1;
+                },
+                _ => {
+                    return Err(ParseError::at(ErrorKind::UnsafePathSegment,
+                                              target.as_bytes(), start));
+                },
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+/// Check if the given decoded segment is a single plain file name on this platform.
+fn is_file_name(seg: &str) -> bool {
+    if seg.contains(['/', '\\', '\0']) {
+        return false;
+    }
+
+    let mut components = Path::new(seg).components();
+
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) => name == OsStr::new(seg),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn map(s: &str) -> Result<PathBuf, (ErrorKind, usize)> {
+        OriginForm::parse(s).unwrap().to_fs_path("/srv").map_err(|e| (e.kind(), e.offset()))
+    }
+
+    #[test]
+    fn test_to_fs_path() {
+        let ok = |s| map(s).unwrap();
+
+        assert_eq!(ok("/"), Path::new("/srv"));
+        assert_eq!(ok("/?a=/../b"), Path::new("/srv"));
+        assert_eq!(ok("/a/b"), Path::new("/srv/a/b"));
+        assert_eq!(ok("/a/b/"), Path::new("/srv/a/b"));
+        assert_eq!(ok("//a//b"), Path::new("/srv/a/b"));
+        assert_eq!(ok("/a/./b/../c"), Path::new("/srv/a/c"));
+        assert_eq!(ok("/../../etc/passwd"), Path::new("/srv/etc/passwd"));
+        assert_eq!(ok("/a/%2e%2E/%2e/b"), Path::new("/srv/b"));
+        assert_eq!(ok("/a/../../b"), Path::new("/srv/b"));
+        assert_eq!(ok("/%7Euser/caf%C3%A9"), Path::new("/srv/~user/caf\u{e9}"));
+        assert_eq!(ok("/a.b/...."), Path::new("/srv/a.b/...."));
+        assert_eq!(ok("/a#/../../b"), Path::new("/srv/a"));
+
+        assert_eq!(map("/a/b%2Fc"), Err((ErrorKind::UnsafePathSegment, 3)));
+        assert_eq!(map("/a/..%2F..%2Fb"), Err((ErrorKind::UnsafePathSegment, 3)));
+        assert_eq!(map("/a%5Cb"), Err((ErrorKind::UnsafePathSegment, 1)));
+        assert_eq!(map("/a/b\\c"), Err((ErrorKind::UnsafePathSegment, 3)));
+        assert_eq!(map("/a%00"), Err((ErrorKind::UnsafePathSegment, 1)));
+        assert_eq!(map("/a/%zz"), Err((ErrorKind::InvalidPercentEncoding, 3)));
+        assert_eq!(map("/a/b%FF"), Err((ErrorKind::InvalidUtf8, 4)));
+
+        let t = OriginForm::parse("/a/b").unwrap();
+        assert_eq!(t.to_fs_path("rel").unwrap(), Path::new("rel/a/b"));
+        assert_eq!(t.to_fs_path("").unwrap(), Path::new("a/b"));
+    }
+
+    #[test]
+    fn test_is_file_name() {
+        assert!(is_file_name("a"));
+        assert!(is_file_name("a.txt"));
+        assert!(is_file_name("..."));
+        assert!(!is_file_name(""));
+        assert!(!is_file_name("."));
+        assert!(!is_file_name(".."));
+        assert!(!is_file_name("a/b"));
+        assert!(!is_file_name("a\\b"));
+        assert!(!is_file_name("a\0"));
+    }
+}
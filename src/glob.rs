@@ -0,0 +1,302 @@
+//! Matching of paths against glob patterns.
+
+use {AbsoluteForm, ErrorKind, OriginForm, ParseError};
+use decode::decoded_unit;
+use form::offset;
+use path::path_segments;
+
+/// Glob pattern, such as `/static/**` or `/assets/*.js`, that matches paths segment by
+/// segment.
+///
+/// Within a segment of the pattern, `*` matches any run of characters, including none,
+/// and `?` matches any single character, but neither crosses into another segment, so
+/// only an encoded `%2F` can be matched as a `/`. A segment that's exactly `**` matches
+/// any number of whole segments, including none, so `/static/**` matches `/static`,
+/// `/static/`, and `/static/css/app.css`. Everything else is literal text compared to
+/// the decoded path, so `/assets/*.js` matches `/assets/app%2Ejs` and a `%` in the
+/// pattern is just a literal `%`. As with `path_segments`, the path ends at any `#`
+/// fragment.
+///
+/// Matching does no allocation and never recurses, and it takes time proportional to
+/// the product of the pattern and path lengths at worst.
+///
+/// ```rust
+/// use uhttp_request_target::{Glob, OriginForm};
+///
+/// let assets = Glob::parse("/assets/*.js").unwrap();
+/// let statics = Glob::parse("/static/**").unwrap();
+///
+/// let t = OriginForm::parse("/assets/app.min.js?v=2").unwrap();
+/// assert!(t.matches_glob(&assets));
+/// assert!(!t.matches_glob(&statics));
+///
+/// let t = OriginForm::parse("/static/css/app.css").unwrap();
+/// assert!(t.matches_glob(&statics));
+/// assert!(!assets.is_match("/assets/js/app.js"));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Glob<'p> {
+    pattern: &'p str,
+}
+
+impl<'p> Glob<'p> {
+    /// Parse the given glob pattern.
+    ///
+    /// The pattern must begin with `/` and may not contain a `#`, and `**` must be a
+    /// whole segment. Errors are `ErrorKind::InvalidPattern` at the offending offset in
+    /// the pattern.
+    pub fn parse(pattern: &'p str) -> Result<Self, ParseError> {
+        let invalid = |idx| {
+            ParseError::at(ErrorKind::InvalidPattern, pattern.as_bytes(), idx)
+        };
+
+        if !pattern.starts_with('/') {
+            return Err(invalid(0));
+        }
+
+        if let Some(idx) = pattern.find('#') {
+            return Err(invalid(idx));
+        }
+
+        for seg in path_segments(pattern) {
+            match seg.find("**") {
+                Some(_) if seg == "**" => {},
+                Some(idx) => return Err(invalid(offset(pattern, seg) + idx)),
+                None => {},
+            }
+        }
+
+        Ok(Glob { pattern })
+    }
+
+    /// Retrieve the pattern string.
+    pub fn as_str(&self) -> &'p str { self.pattern }
+
+    /// Check if the given path matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        let mut pats = path_segments(self.pattern);
+        let mut segs = path_segments(path);
+        // The pattern after the last `**` and the path it was last tried against.
+        let mut retry = None;
+
+        loop {
+            let matched = match pats.next() {
+                Some("**") => {
+                    retry = Some((pats, segs));
+                    continue;
+                },
+                Some(pat) => segs.next().is_some_and(|seg| segment_match(pat, seg)),
+                None if segs.next().is_none() => return true,
+                None => false,
+            };
+
+            if matched {
+                continue;
+            }
+
+            // Let the last `**` consume one more segment and try the rest again.
+            let (p, mut s) = match retry {
+                Some(retry) => retry,
+                None => return false,
+            };
+
+            if s.next().is_none() {
+                return false;
+            }
+
+            retry = Some((p, s));
+            pats = p;
+            segs = s;
+        }
+    }
+}
+
+/// Check if the given raw path segment matches the given segment of a glob pattern.
+fn segment_match(pat: &str, seg: &str) -> bool {
+    let (pat, seg) = (pat.as_bytes(), seg.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    // The pattern after the last `*` and the segment offset it was last tried at.
+    let mut retry = None;
+
+    loop {
+        if i < pat.len() && pat[i] == b'*' {
+            i += 1;
+            retry = Some((i, j));
+            continue;
+        }
+
+        if j == seg.len() && i == pat.len() {
+            return true;
+        }
+
+        if j < seg.len() && i < pat.len() {
+            if pat[i] == b'?' {
+                i += 1;
+                j += char_len(seg, j);
+                continue;
+            }
+
+            let (b, len) = decoded_unit(seg, j);
+
+            if pat[i] == b {
+                i += 1;
+                j += len;
+                continue;
+            }
+        }
+
+        // Let the last `*` consume one more character and try the rest again.
+        match retry {
+            Some((p, s)) if s < seg.len() => {
+                let s = s + char_len(seg, s);
+                retry = Some((p, s));
+                i = p;
+                j = s;
+            },
+            _ => return false,
+        }
+    }
+}
+
+/// Retrieve the number of raw bytes spanned by the decoded character at the given offset
+/// of the given raw segment.
+///
+/// A byte that doesn't start valid UTF-8 is taken as a character of its own.
+fn char_len(seg: &[u8], idx: usize) -> usize {
+    let (lead, mut len) = decoded_unit(seg, idx);
+    let width = match lead {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    };
+
+    for _ in 1..width {
+        match seg.get(idx + len) {
+            Some(_) => match decoded_unit(seg, idx + len) {
+                (b, n) if b & 0xC0 == 0x80 => len += n,
+                _ => break,
+            },
+            None => break,
+        }
+    }
+
+    len
+}
+
+impl<'a> OriginForm<'a> {
+    /// Check if the path matches the given glob pattern.
+    pub fn matches_glob(&self, glob: &Glob) -> bool {
+        glob.is_match(self.path())
+    }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Check if the path, or `/` if it's empty, matches the given glob pattern.
+    pub fn matches_glob(&self, glob: &Glob) -> bool {
+        glob.is_match(self.path_or_root())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn glob(pattern: &str, path: &str) -> bool {
+        Glob::parse(pattern).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn test_parse() {
+        for &pattern in &["/", "/**", "/a/**/b", "/*.js", "/?", "/a%zz", "/*/**/"] {
+            assert_eq!(Glob::parse(pattern).unwrap().as_str(), pattern);
+        }
+
+        for &(pattern, idx) in &[
+            ("", 0),
+            ("a/**", 0),
+            ("**", 0),
+            ("/a#b", 2),
+            ("/a**", 2),
+            ("/**a", 1),
+            ("/b/***", 3),
+        ] {
+            let e = Glob::parse(pattern).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidPattern);
+            assert_eq!(e.offset(), idx, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_is_match() {
+        assert!(glob("/", "/"));
+        assert!(!glob("/", "//"));
+        assert!(!glob("/", ""));
+        assert!(glob("/a/b", "/a/b"));
+        assert!(glob("/a/b", "/%61/b#c"));
+        assert!(!glob("/a/b", "/a/b/"));
+        assert!(!glob("/a/b", "/a"));
+
+        assert!(glob("/static/**", "/static"));
+        assert!(glob("/static/**", "/static/"));
+        assert!(glob("/static/**", "/static/a"));
+        assert!(glob("/static/**", "/static/a/b/c.css"));
+        assert!(!glob("/static/**", "/statics/a"));
+        assert!(!glob("/static/**", "/"));
+        assert!(glob("/**", "/"));
+        assert!(glob("/**", "/a/b"));
+        assert!(glob("/**", ""));
+        assert!(glob("/a/**/b", "/a/b"));
+        assert!(glob("/a/**/b", "/a/x/y/b"));
+        assert!(glob("/a/**/b", "/a/b/b"));
+        assert!(!glob("/a/**/b", "/a/x/y/c"));
+        assert!(!glob("/a/**/b", "/a/b/c"));
+        assert!(glob("/**/b/**/d", "/a/b/c/d"));
+        assert!(glob("/**/b/**/d", "/b/d"));
+        assert!(!glob("/**/b/**/d", "/a/d/c/b"));
+        assert!(glob("/**/*.js", "/a/b/c.js"));
+        assert!(!glob("/**/*.js", "/a/b/c.css"));
+
+        assert!(glob("/assets/*.js", "/assets/app.js"));
+        assert!(glob("/assets/*.js", "/assets/app.min.js"));
+        assert!(glob("/assets/*.js", "/assets/.js"));
+        assert!(glob("/assets/*.js", "/assets/app%2Ejs"));
+        assert!(!glob("/assets/*.js", "/assets/js/app.js"));
+        assert!(!glob("/assets/*.js", "/assets/app.jsx"));
+        assert!(!glob("/assets/*.js", "/assets/app.js/"));
+        assert!(glob("/*", "/"));
+        assert!(glob("/*", "/a"));
+        assert!(!glob("/*", "/a/b"));
+        assert!(glob("/a*b*c", "/abc"));
+        assert!(glob("/a*b*c", "/axxbyyc"));
+        assert!(glob("/a*b*c", "/abcbc"));
+        assert!(!glob("/a*b*c", "/abcb"));
+        assert!(glob("/x*y", "/xa%2Fy"));
+        assert!(glob("/x?y", "/x%2Fy"));
+        assert!(!glob("/*/*", "/xa%2Fy"));
+
+        assert!(glob("/?", "/a"));
+        assert!(glob("/?", "/%C3%A9"));
+        assert!(glob("/?", "/\u{e9}"));
+        assert!(glob("/?", "/%FF"));
+        assert!(!glob("/?", "/"));
+        assert!(!glob("/?", "/ab"));
+        assert!(glob("/caf?", "/caf%C3%A9"));
+        assert!(glob("/*?", "/%C3%A9"));
+        assert!(glob("/?b", "/%C3b"));
+        assert!(glob("/%zz", "/%zz"));
+        assert!(glob("/a%2F", "/a%252F"));
+        assert!(!glob("/a%2F", "/a%2F"));
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        let g = Glob::parse("/a/*").unwrap();
+
+        assert!(OriginForm::parse("/a/b?c").unwrap().matches_glob(&g));
+        assert!(!OriginForm::parse("/a/b/c").unwrap().matches_glob(&g));
+        assert!(AbsoluteForm::parse("http://x/a/b").unwrap().matches_glob(&g));
+        assert!(AbsoluteForm::parse("http://x").unwrap()
+            .matches_glob(&Glob::parse("/").unwrap()));
+    }
+}
@@ -0,0 +1,149 @@
+//! Checking of HTTP/2 and HTTP/3 request pseudo-header fields [RFC9113§8.3.1].
+
+use {AuthorityForm, ErrorKind, ParseError, ParsedTarget, RequestTarget};
+
+impl RequestTarget {
+    /// Check if a target of this form may be carried by the pseudo-header fields of an
+    /// HTTP/2 or HTTP/3 request with the given method and `:protocol`, if any.
+    ///
+    /// A CONNECT request normally carries an authority-form target in `:authority`
+    /// [RFC9113§8.5], but an extended CONNECT request, one with a `:protocol`, carries an
+    /// origin-form target in `:path` like other requests [RFC8441§4]. The `:protocol`
+    /// field isn't allowed with any other method. Otherwise, origin form is always
+    /// allowed, and asterisk form only for `OPTIONS`. Absolute form never is, since its
+    /// scheme and authority are carried in their own fields.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RequestTarget;
+    ///
+    /// assert!(RequestTarget::Authority.valid_for_h2("CONNECT", None));
+    /// assert!(RequestTarget::AbsPath.valid_for_h2("CONNECT", Some("websocket")));
+    /// assert!(!RequestTarget::Authority.valid_for_h2("CONNECT", Some("websocket")));
+    /// ```
+    pub fn valid_for_h2(&self, method: &str, protocol: Option<&str>) -> bool {
+        match (method, protocol, *self) {
+            ("CONNECT", Some(_), RequestTarget::AbsPath) => true,
+            (_, Some(_), _) => false,
+            ("CONNECT", None, t) => t == RequestTarget::Authority,
+            ("OPTIONS", None, RequestTarget::ServerOptions) => true,
+            (_, None, t) => t == RequestTarget::AbsPath,
+        }
+    }
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Parse the given value of the `:path` pseudo-header field of a request with the
+    /// given method.
+    ///
+    /// The value can't be empty and must be either origin form or, for `OPTIONS`
+    /// requests only, asterisk form [RFC9113§8.3.1]. Any other form is rejected with
+    /// `ErrorKind::UnexpectedForm`, since the scheme and authority are carried in their
+    /// own pseudo-header fields. The method is compared case-sensitively [RFC9110§9.1].
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{ParsedTarget, ErrorKind};
+    ///
+    /// assert!(ParsedTarget::parse_h2_path("/a?b", "GET").is_ok());
+    /// assert!(ParsedTarget::parse_h2_path("*", "OPTIONS").is_ok());
+    /// assert_eq!(ParsedTarget::parse_h2_path("*", "GET").unwrap_err().kind(),
+    ///            ErrorKind::UnexpectedForm);
+    /// ```
+    pub fn parse_h2_path(s: &'a str, method: &str) -> Result<Self, ParseError> {
+        match ParsedTarget::parse(s)? {
+            t @ ParsedTarget::AbsPath(_) => Ok(t),
+            t @ ParsedTarget::ServerOptions if method == "OPTIONS" => Ok(t),
+            _ => Err(ParseError::at(ErrorKind::UnexpectedForm, s.as_bytes(), 0)),
+        }
+    }
+}
+
+impl<'a> AuthorityForm<'a> {
+    /// Parse the given value of the `:authority` pseudo-header field, or a `Host` header
+    /// field forwarded with it.
+    ///
+    /// The value must be a host with an optional port, checked against the same grammar
+    /// as `validate`. Userinfo isn't allowed [RFC9113§8.3.1] and is rejected with
+    /// `ErrorKind::Userinfo` at its start.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{AuthorityForm, ErrorKind};
+    ///
+    /// let a = AuthorityForm::parse_h2_authority("example.com:8443").unwrap();
+    /// assert_eq!(a.port(), Some(8443));
+    /// assert_eq!(AuthorityForm::parse_h2_authority("u@example.com").unwrap_err().kind(),
+    ///            ErrorKind::Userinfo);
+    /// ```
+    pub fn parse_h2_authority(s: &'a str) -> Result<Self, ParseError> {
+        let form = AuthorityForm::parse(s)?;
+
+        if form.userinfo().is_some() {
+            return Err(ParseError::at(ErrorKind::Userinfo, s.as_bytes(), 0));
+        }
+
+        form.validate()?;
+
+        Ok(form)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_for_h2() {
+        use RequestTarget::*;
+
+        assert!(AbsPath.valid_for_h2("GET", None));
+        assert!(AbsPath.valid_for_h2("OPTIONS", None));
+        assert!(ServerOptions.valid_for_h2("OPTIONS", None));
+        assert!(Authority.valid_for_h2("CONNECT", None));
+        assert!(AbsPath.valid_for_h2("CONNECT", Some("websocket")));
+        assert!(!ServerOptions.valid_for_h2("GET", None));
+        assert!(!ServerOptions.valid_for_h2("options", None));
+        assert!(!Authority.valid_for_h2("GET", None));
+        assert!(!AbsUri.valid_for_h2("GET", None));
+        assert!(!AbsPath.valid_for_h2("CONNECT", None));
+        assert!(!Authority.valid_for_h2("CONNECT", Some("websocket")));
+        assert!(!ServerOptions.valid_for_h2("CONNECT", Some("websocket")));
+        assert!(!AbsPath.valid_for_h2("GET", Some("websocket")));
+        assert!(!ServerOptions.valid_for_h2("OPTIONS", Some("websocket")));
+    }
+
+    #[test]
+    fn test_parse_h2_path() {
+        let err = |s, method| {
+            ParsedTarget::parse_h2_path(s, method).map_err(|e| (e.kind(), e.offset()))
+        };
+
+        assert!(err("/", "GET").is_ok());
+        assert!(err("/a/b?c=d", "POST").is_ok());
+        assert!(err("/", "OPTIONS").is_ok());
+        assert!(err("*", "OPTIONS").is_ok());
+        assert_eq!(err("", "GET"), Err((ErrorKind::Empty, 0)));
+        assert_eq!(err("*", "GET"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("*", "options"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("http://a/b", "GET"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("a:443", "CONNECT"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("/a\r", "GET"), Err((ErrorKind::IllegalCharacter, 2)));
+    }
+
+    #[test]
+    fn test_parse_h2_authority() {
+        let err = |s| AuthorityForm::parse_h2_authority(s).map_err(|e| (e.kind(), e.offset()));
+
+        assert_eq!(err("example.com").map(|a| a.host()), Ok("example.com"));
+        assert_eq!(err("example.com:80").map(|a| a.port()), Ok(Some(80)));
+        assert_eq!(err("[::1]:443").map(|a| a.host()), Ok("[::1]"));
+        assert_eq!(err("127.0.0.1").map(|a| a.port()), Ok(None));
+        assert_eq!(err(""), Err((ErrorKind::Empty, 0)));
+        assert_eq!(err("u:p@a"), Err((ErrorKind::Userinfo, 0)));
+        assert_eq!(err("/a"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("*"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("http://a"), Err((ErrorKind::UnexpectedForm, 0)));
+        assert_eq!(err("a:b"), Err((ErrorKind::InvalidPort, 2)));
+        assert_eq!(err("a|b"), Err((ErrorKind::InvalidHost, 1)));
+        assert_eq!(err("[::1"), Err((ErrorKind::InvalidHost, 0)));
+        assert_eq!(err("[1::2::3]"), Err((ErrorKind::InvalidHost, 6)));
+    }
+}
@@ -0,0 +1,144 @@
+//! Conversion of internationalized hosts to their ASCII form [RFC5890].
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use idna::{domain_to_ascii_from_cow, AsciiDenyList};
+
+use {AbsoluteForm, AuthorityForm, ErrorKind, HostKind, ParseError, ParsedTarget};
+use decode::{percent_decode, InvalidEscapePolicy};
+use form::offset;
+
+/// Convert the given host to the ASCII form used on the wire, available with the `idna`
+/// feature.
+///
+/// A reg-name is percent-decoded [RFC3986§3.2.2] and then mapped and converted label by
+/// label under UTS #46, so Unicode labels become punycode `xn--` labels and ASCII letters
+/// are lowercased. IP literals and addresses are returned unchanged, and the host itself
+/// is borrowed if it's already in ASCII form. A host that can't be converted, including
+/// one that decodes to a character not allowed in a URI host, is rejected with
+/// `ErrorKind::InvalidIdn` at its start.
+///
+/// ```rust
+/// use uhttp_request_target::host_to_ascii;
+///
+/// assert_eq!(host_to_ascii("b\u{fc}cher.example").unwrap(), "xn--bcher-kva.example");
+/// assert_eq!(host_to_ascii("b%C3%BCcher.example").unwrap(), "xn--bcher-kva.example");
+/// assert_eq!(host_to_ascii("[::1]").unwrap(), "[::1]");
+/// ```
+pub fn host_to_ascii(host: &str) -> Result<Cow<'_, str>, ParseError> {
+    if HostKind::of(host) != HostKind::RegName {
+        return Ok(Cow::Borrowed(host));
+    }
+
+    let decoded = percent_decode(host, InvalidEscapePolicy::Error)?;
+
+    domain_to_ascii_from_cow(decoded, AsciiDenyList::URL)
+        .map_err(|_| ParseError::at(ErrorKind::InvalidIdn, host.as_bytes(), 0))
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Retrieve the target with its host converted to ASCII like `host_to_ascii`,
+    /// borrowing the target string if it's already in ASCII form, available with the
+    /// `idna` feature.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::AbsoluteForm;
+    ///
+    /// let t = AbsoluteForm::parse("http://\u{4f8b}\u{3048}.jp:8080/a?b").unwrap();
+    /// assert_eq!(t.to_ascii().unwrap(), "http://xn--r8jz45g.jp:8080/a?b");
+    /// ```
+    pub fn to_ascii(&self) -> Result<Cow<'a, str>, ParseError> {
+        replace_host(self.as_str(), self.host())
+    }
+}
+
+impl<'a> AuthorityForm<'a> {
+    /// Retrieve the target with its host converted to ASCII like `host_to_ascii`,
+    /// borrowing the target string if it's already in ASCII form, available with the
+    /// `idna` feature.
+    pub fn to_ascii(&self) -> Result<Cow<'a, str>, ParseError> {
+        replace_host(self.as_str(), self.host())
+    }
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Retrieve the target with any host converted to ASCII like `host_to_ascii`,
+    /// borrowing the target string if it has no host or it's already in ASCII form,
+    /// available with the `idna` feature.
+    pub fn to_ascii(&self) -> Result<Cow<'a, str>, ParseError> {
+        match *self {
+            ParsedTarget::AbsUri(ref form) => form.to_ascii(),
+            ParsedTarget::Authority(ref form) => form.to_ascii(),
+            _ => Ok(Cow::Borrowed(self.as_str())),
+        }
+    }
+}
+
+/// Replace the given host within the given target with its ASCII form, reporting errors
+/// at target offsets.
+fn replace_host<'a>(target: &'a str, host: &'a str) -> Result<Cow<'a, str>, ParseError> {
+    let start = offset(target, host);
+
+    let ascii = match host_to_ascii(host) {
+        Ok(Cow::Borrowed(_)) => return Ok(Cow::Borrowed(target)),
+        Ok(Cow::Owned(ascii)) => ascii,
+        Err(e) => return Err(e.shift(start)),
+    };
+
+    let mut out = String::with_capacity(target.len() - host.len() + ascii.len());
+    out.push_str(&target[..start]);
+    out.push_str(&ascii);
+    out.push_str(&target[start + host.len()..]);
+
+    Ok(Cow::Owned(out))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_host_to_ascii() {
+        assert!(matches!(host_to_ascii("example.com"), Ok(Cow::Borrowed("example.com"))));
+        assert!(matches!(host_to_ascii("xn--bcher-kva.de"), Ok(Cow::Borrowed(_))));
+        assert!(matches!(host_to_ascii("127.0.0.1"), Ok(Cow::Borrowed("127.0.0.1"))));
+        assert!(matches!(host_to_ascii("[::1]"), Ok(Cow::Borrowed("[::1]"))));
+        assert!(matches!(host_to_ascii("[v1.x]"), Ok(Cow::Borrowed("[v1.x]"))));
+
+        assert_eq!(host_to_ascii("Example.COM").unwrap(), "example.com");
+        assert_eq!(host_to_ascii("b\u{fc}cher.de").unwrap(), "xn--bcher-kva.de");
+        assert_eq!(host_to_ascii("B\u{dc}CHER.de").unwrap(), "xn--bcher-kva.de");
+        assert_eq!(host_to_ascii("%62%C3%BCcher.de").unwrap(), "xn--bcher-kva.de");
+        assert_eq!(host_to_ascii("\u{4f8b}\u{3048}.jp").unwrap(), "xn--r8jz45g.jp");
+
+        let e = host_to_ascii("a%2Fb").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidIdn);
+        assert_eq!(e.offset(), 0);
+        assert_eq!(host_to_ascii("xn--a").unwrap_err().kind(), ErrorKind::InvalidIdn);
+        assert_eq!(host_to_ascii("%C3").unwrap_err().kind(), ErrorKind::InvalidIdn);
+
+        let e = host_to_ascii("a%zz").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 1);
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let t = ParsedTarget::parse("http://u@b\u{fc}cher.de:80/\u{fc}?q#f").unwrap();
+        assert_eq!(t.to_ascii().unwrap(), "http://u@xn--bcher-kva.de:80/\u{fc}?q#f");
+
+        let t = ParsedTarget::parse("b\u{fc}cher.de:443").unwrap();
+        assert_eq!(t.to_ascii().unwrap(), "xn--bcher-kva.de:443");
+
+        for s in &["http://a.de/b", "a.de:443", "/b\u{fc}", "*"] {
+            let t = ParsedTarget::parse(s).unwrap();
+            assert!(matches!(t.to_ascii(), Ok(Cow::Borrowed(b)) if b == *s));
+        }
+
+        let e = ParsedTarget::parse("http://a%2fb/").unwrap().to_ascii().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidIdn);
+        assert_eq!(e.offset(), 7);
+        assert_eq!(e.byte(), Some(b'a'));
+    }
+}
@@ -0,0 +1,205 @@
+//! Best-effort classification of slightly malformed targets.
+
+use core::ops::Range;
+
+use {ErrorKind, ParseError, RequestTarget, leading_whitespace_len, trailing_whitespace_len};
+
+/// A way in which a target deviated from the strict grammar but was accepted anyway.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Deviation {
+    /// Whitespace before or after the target was ignored.
+    SurroundingWhitespace,
+    /// A path without its leading slash, such as `a/b`, was treated as origin form.
+    MissingLeadingSlash,
+    /// An HTTP scheme wasn't in lowercase, such as `HTTP://`, which producers should
+    /// avoid even though schemes are case-insensitive [RFC3986§3.1].
+    SchemeCase,
+}
+
+/// Every deviation, in order of its bit in `Deviations`.
+const ALL: [Deviation; 3] = [
+    Deviation::SurroundingWhitespace,
+    Deviation::MissingLeadingSlash,
+    Deviation::SchemeCase,
+];
+
+/// Set of deviations found in a target.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Deviations(u8);
+
+impl Deviations {
+    /// Check if the given deviation is in the set.
+    pub fn contains(&self, d: Deviation) -> bool { self.0 & bit(d) != 0 }
+
+    /// Check if the target had no deviations.
+    pub fn is_empty(&self) -> bool { self.0 == 0 }
+
+    /// Iterate over the deviations in the set.
+    pub fn iter(&self) -> impl Iterator<Item = Deviation> {
+        let set = *self;
+        ALL.iter().copied().filter(move |&d| set.contains(d))
+    }
+
+    /// Add the given deviation to the set.
+    fn insert(&mut self, d: Deviation) { self.0 |= bit(d) }
+}
+
+/// Retrieve the bit representing the given deviation.
+fn bit(d: Deviation) -> u8 { 1 << d as u8 }
+
+/// Result of classifying a target leniently.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LenientTarget {
+    /// Best-effort form of the target.
+    pub kind: RequestTarget,
+    /// Range of the input holding the target, without any ignored whitespace.
+    pub range: Range<usize>,
+    /// Deviations from the strict grammar that were tolerated.
+    pub deviations: Deviations,
+}
+
+impl RequestTarget {
+    /// Classify the given target bytes, tolerating the minor deviations in `Deviation`
+    /// that real-world clients are known to send.
+    ///
+    /// This lets proxies forward slightly malformed traffic while flagging it. Strictly
+    /// valid targets are classified the same as by `classify`, with no deviations, and
+    /// targets that can't be classified even leniently return the same error.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{RequestTarget, Deviation};
+    ///
+    /// let t = RequestTarget::classify_lenient(b" r/rust\t").unwrap();
+    /// assert_eq!(t.kind, RequestTarget::AbsPath);
+    /// assert_eq!(t.range, 1..7);
+    /// assert!(t.deviations.contains(Deviation::SurroundingWhitespace));
+    /// assert!(t.deviations.contains(Deviation::MissingLeadingSlash));
+    /// ```
+    pub fn classify_lenient(s: &[u8]) -> Result<LenientTarget, ParseError> {
+        let mut deviations = Deviations::default();
+        let range = trim(s);
+
+        if range.len() != s.len() {
+            deviations.insert(Deviation::SurroundingWhitespace);
+        }
+
+        let t = &s[range.clone()];
+
+        let kind = match RequestTarget::classify(t) {
+            Ok(kind) => kind,
+            Err(e) => match e.kind() {
+                ErrorKind::SlashInAuthority => {
+                    // Classification stopped at the slash, so check the rest as a path.
+                    let slash = e.offset();
+
+                    if let Err(e) = RequestTarget::classify(&t[slash..]) {
+                        return Err(e.shift(range.start + slash));
+                    }
+
+                    deviations.insert(Deviation::MissingLeadingSlash);
+                    RequestTarget::AbsPath
+                },
+                ErrorKind::Empty => return Err(ParseError::at(ErrorKind::Empty, s, 0)),
+                _ => return Err(e.shift(range.start)),
+            },
+        };
+
+        if kind == RequestTarget::AbsUri && has_uppercase_scheme(t) {
+            deviations.insert(Deviation::SchemeCase);
+        }
+
+        Ok(LenientTarget { kind, range, deviations })
+    }
+}
+
+/// Find the range of the given bytes without surrounding whitespace.
+pub(crate) fn trim(s: &[u8]) -> Range<usize> {
+    let mut start = 0;
+    let mut end = s.len();
+
+    loop {
+        match leading_whitespace_len(&s[start..end]) {
+            0 => break,
+            len => start += len,
+        }
+    }
+
+    loop {
+        match trailing_whitespace_len(&s[start..end]) {
+            0 => break,
+            len => end -= len,
+        }
+    }
+
+    start..end
+}
+
+/// Check if the scheme before the first `:` of the given target has any uppercase
+/// letters.
+fn has_uppercase_scheme(s: &[u8]) -> bool {
+    s.iter().take_while(|&&b| b != b':').any(u8::is_ascii_uppercase)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify_lenient() {
+        use RequestTarget::*;
+
+        let t = RequestTarget::classify_lenient(b"/a?b").unwrap();
+        assert_eq!((t.kind, t.range), (AbsPath, 0..4));
+        assert!(t.deviations.is_empty());
+        assert_eq!(t.deviations.iter().count(), 0);
+
+        let t = RequestTarget::classify_lenient(b"/a  ").unwrap();
+        assert_eq!((t.kind, t.range), (AbsPath, 0..2));
+        assert_eq!(t.deviations.iter().collect::<Vec<_>>(),
+            vec![Deviation::SurroundingWhitespace]);
+
+        let t = RequestTarget::classify_lenient("\u{3000}\t*\r\n".as_bytes()).unwrap();
+        assert_eq!((t.kind, t.range), (ServerOptions, 4..5));
+
+        let t = RequestTarget::classify_lenient(b"a/b").unwrap();
+        assert_eq!((t.kind, t.range), (AbsPath, 0..3));
+        assert_eq!(t.deviations.iter().collect::<Vec<_>>(),
+            vec![Deviation::MissingLeadingSlash]);
+
+        let t = RequestTarget::classify_lenient(b" HTTPS://a/b").unwrap();
+        assert_eq!((t.kind, t.range), (AbsUri, 1..12));
+        assert_eq!(t.deviations.iter().collect::<Vec<_>>(),
+            vec![Deviation::SurroundingWhitespace, Deviation::SchemeCase]);
+
+        let t = RequestTarget::classify_lenient(b"Http://A/B").unwrap();
+        assert_eq!(t.deviations.iter().collect::<Vec<_>>(), vec![Deviation::SchemeCase]);
+
+        let t = RequestTarget::classify_lenient(b"http://A/B").unwrap();
+        assert!(t.deviations.is_empty());
+
+        let t = RequestTarget::classify_lenient(b"example.com:443").unwrap();
+        assert_eq!((t.kind, t.range), (Authority, 0..15));
+        assert!(t.deviations.is_empty());
+
+        let e = RequestTarget::classify_lenient(b"ftp://a").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::UnknownScheme, 4));
+
+        let e = RequestTarget::classify_lenient(b"  /a\0b").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::NulByte, 4));
+
+        let e = RequestTarget::classify_lenient(b"a/b\r\nX: y").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::IllegalCharacter, 3));
+
+        let e = RequestTarget::classify_lenient(b"a/b\0").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::NulByte, 3));
+
+        let e = RequestTarget::classify_lenient(b" ab/c/d\0").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::NulByte, 7));
+
+        let e = RequestTarget::classify_lenient(b" \t ").unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::Empty, 0));
+    }
+}
@@ -13,12 +13,180 @@
 //! assert_eq!("example.com".parse(), Ok(RequestTarget::Authority));
 //! assert_eq!("*".parse(), Ok(RequestTarget::ServerOptions));
 //! ```
+//!
+//! Targets can also be classified and split into their components in one call:
+//!
+//! ```rust
+//! use uhttp_request_target::ParsedTarget;
+//!
+//! match ParsedTarget::parse("/r/rust?sort=new") {
+//!     Ok(ParsedTarget::AbsPath(form)) => {
+//!         assert_eq!(form.path(), "/r/rust");
+//!         assert_eq!(form.query(), Some("sort=new"));
+//!     },
+//!     _ => panic!(),
+//! }
+//! ```
+//!
+//! ## Features
+//!
+//! Classification, the borrowed parse types, and the `core::error::Error` impl for
+//! `ParseError` only depend on `core`, so the crate is `no_std` by default. Further APIs
+//! are enabled in tiers:
+//!
+//! - `alloc`: owned target types, such as `RequestTargetBuf`.
+//! - `std`: conveniences that need the standard library, such as resolving
+//!   authority-form targets to socket addresses. This implies `alloc`.
+//! - `defmt`: `defmt::Format` impls for the target, parse, and error types.
+//! - `ufmt`: `ufmt::uDisplay` and `ufmt::uDebug` impls for the same types.
+//! - `heapless`: fixed-capacity owned target types, such as `HeaplessTarget`, for targets
+//!   without a heap.
+//! - `idna`: conversion of internationalized hosts to their ASCII form, such as
+//!   `host_to_ascii`. This implies `alloc`.
+//! - `serde`: deserialization of queries into typed parameters, such as `from_query`.
+//!   This implies `alloc`.
+//! - `unicode`: Unicode normalization of decoded components, such as
+//!   `percent_decode_nfc`. This implies `alloc`.
+//!
+//! ## Guarantees
+//!
+//! Every parse entry point, including `RequestTarget::classify`,
+//! `RequestTarget::parse_prefix`, `ParsedTarget::parse`, the `parse` function of each
+//! form, and `TargetSpans::parse`, is total: it returns a result for any input without
+//! panicking, never recurses, and does work bounded by a small constant times the length
+//! of the input. This makes them suitable for running directly on untrusted input from
+//! fuzzers or firmware without a `catch_unwind` wrapper.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(not(test), deny(clippy::panic, clippy::unwrap_used, clippy::expect_used))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "heapless")]
+extern crate heapless;
+#[cfg(feature = "idna")]
+extern crate idna;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "ufmt")]
+extern crate ufmt;
+#[cfg(feature = "unicode")]
+extern crate unicode_normalization;
+
+mod acl;
+mod authority;
+mod batch;
+mod charset;
+#[cfg(feature = "serde")]
+mod de;
+mod decode;
+mod equiv;
+mod error;
+#[cfg(feature = "heapless")]
+mod fixed;
+mod form;
+#[cfg(feature = "std")]
+mod fs;
+mod glob;
+mod h2;
+#[cfg(feature = "idna")]
+mod idn;
+mod lenient;
+mod matcher;
+#[cfg(feature = "std")]
+mod net;
+mod normalize;
+#[cfg(feature = "alloc")]
+mod openapi;
+mod options;
+#[cfg(feature = "alloc")]
+mod owned;
+mod path;
+#[cfg(feature = "alloc")]
+mod prefix;
+mod query;
+#[cfg(feature = "alloc")]
+mod resolve;
+#[cfg(feature = "alloc")]
+mod rewrite;
+mod route;
+#[cfg(feature = "alloc")]
+mod router;
+mod scan;
+#[cfg(feature = "bytes")]
+mod shared;
+mod span;
+mod stream;
+mod target;
+#[cfg(feature = "ufmt")]
+mod ufmt_impl;
+#[cfg(feature = "unicode")]
+mod unicode;
+
+pub use acl::{Acl, Action, Decision, Rule};
+pub use authority::HostKind;
+pub use batch::{classify_all, ClassifyAll};
+#[cfg(feature = "serde")]
+pub use de::{from_query, QueryDeError};
+pub use decode::{percent_decode_into, InvalidEscapePolicy};
+#[cfg(feature = "alloc")]
+pub use decode::{percent_decode, percent_decode_str};
+pub use equiv::{targets_equivalent, CompareOptions};
+pub use error::{ParseError, ErrorKind};
+#[cfg(feature = "heapless")]
+pub use fixed::HeaplessTarget;
+pub use form::{ParsedTarget, OriginForm, AbsoluteForm, AuthorityForm};
+pub use glob::Glob;
+#[cfg(feature = "idna")]
+pub use idn::host_to_ascii;
+pub use lenient::{Deviation, Deviations, LenientTarget};
+pub use matcher::TargetMatcher;
+#[cfg(feature = "alloc")]
+pub use normalize::{collapse_slashes, decode_unreserved, remove_dot_segments,
+                    uppercase_escapes};
+pub use normalize::{collapse_slashes_into, decode_unreserved_into,
+                    remove_dot_segments_into, uppercase_escapes_into, NormalizeOptions};
+#[cfg(feature = "alloc")]
+pub use openapi::{PathTemplate, PathTemplates, TemplateMatch};
+pub use options::{BackslashPolicy, NonAsciiPolicy, ParseOptions, Scheme};
+pub use path::{path_segments, split_matrix, MatrixParams, MatrixSegments, Segments};
+#[cfg(feature = "alloc")]
+pub use path::DecodedSegments;
+#[cfg(feature = "alloc")]
+pub use prefix::{PrefixMatch, PrefixTable};
+pub use scan::{find_double_encoding, find_forbidden_octet, find_invalid_escape,
+               find_line_break, find_suspicious_whitespace, find_traversal,
+               SuspiciousWhitespace};
+#[cfg(feature = "bytes")]
+pub use shared::BytesTarget;
+pub use span::TargetSpans;
+pub use stream::{StreamClassifier, Progress};
+#[cfg(feature = "alloc")]
+pub use owned::RequestTargetBuf;
+pub use route::{CaptureNames, RoutePattern};
+#[cfg(feature = "alloc")]
+pub use route::Captures;
+#[cfg(feature = "alloc")]
+pub use router::{RouteMatch, Router};
+pub use query::{query_pairs, DuplicateKeyPolicy, QueryOptions, QueryPairs};
+#[cfg(feature = "alloc")]
+pub use query::{query_map, QueryMap, QueryMultiMap};
+pub use target::RequestTargetStr;
+#[cfg(feature = "unicode")]
+pub use unicode::{is_nfc, percent_decode_nfc};
 
 /// A request target that appears in every HTTP request start line.
 ///
 /// This gives a hint as to how the target should be interpreted but doesn't guarantee the
 /// matched string has well-formed syntax.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum RequestTarget {
     /// General form used for direct requests targeting a resource on the origin server.
     AbsPath,
@@ -31,33 +199,432 @@ pub enum RequestTarget {
     ServerOptions,
 }
 
-impl std::str::FromStr for RequestTarget {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl RequestTarget {
+    /// Classify the given target bytes with the same rules as the `FromStr` impl.
+    ///
+    /// The bytes aren't required to be valid UTF-8, but surrounding whitespace is
+    /// detected according to its UTF-8 encoding.
+    ///
+    /// The target is classified in a single forward pass, which also rejects the CR, LF,
+    /// and NUL octets that can never appear within a request line, returning as soon as
+    /// any violation is found. Leading whitespace is reported before any invalid octet,
+    /// and trailing whitespace after.
+    ///
+    /// The first and last bytes are looked up in tables to settle the common cases
+    /// immediately, and the slower whitespace and scheme checks only run when those
+    /// bytes could begin or end a whitespace character or scheme.
+    pub const fn classify(s: &[u8]) -> Result<Self, ParseError> {
         use self::RequestTarget::*;
 
-        // Surrounding whitespace and empty string are invalid [RFC7230§3.1.1,
-        // RFC7230§5.3].
-        if s != s.trim() || s.is_empty() {
-            return Err(());
-        }
+        let (first, last) = match (s.first(), s.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            // The empty string is invalid [RFC7230§5.3].
+            _ => return Err(ParseError::at(ErrorKind::Empty, s, 0)),
+        };
 
-        if s == "*" {
+        // The absolute path form always starts with a slash [RFC7230§5.3.1], and
+        // anything else is provisionally in authority form until proven otherwise.
+        let mut kind = match LEADING[first as usize] {
+            // Surrounding whitespace is invalid [RFC7230§3.1.1].
+            Class::Space => {
+                return Err(ParseError::at(ErrorKind::SurroundingWhitespace, s, 0))
+            },
+            Class::MaybeSpace if starts_with_whitespace(s) => {
+                return Err(ParseError::at(ErrorKind::SurroundingWhitespace, s, 0))
+            },
             // The OPTIONS form contains only an asterisk [RFC7230§5.3.4].
-            Ok(ServerOptions)
-        } else if s.starts_with('/') {
-            // The absolute path form always starts with a slash [RFC7230§5.3.1].
-            Ok(AbsPath)
-        } else if s.starts_with("http://") || s.starts_with("https://") {
-            // The URI form starts with one of the two HTTP schemes [RFC7230§5.3.2].
-            Ok(AbsUri)
-        } else if !s.contains('/') {
-            // The authority form contains no slashes [RFC7230§5.3.3].
-            Ok(Authority)
+            Class::Star if s.len() == 1 => return Ok(ServerOptions),
+            Class::Slash => AbsPath,
+            _ => Authority,
+        };
+
+        // The URI form starts with one of the two HTTP schemes [RFC7230§5.3.2].
+        let scheme = matches!(LEADING[first as usize], Class::Scheme);
+        let mut idx = 0;
+
+        while idx < s.len() {
+            match s[idx] {
+                // These octets delimit or terminate the request line [RFC7230§3.1.1].
+                b'\r' | b'\n' => {
+                    return Err(ParseError::at(ErrorKind::IllegalCharacter, s, idx))
+                },
+                // NUL is a common mismatch between frontends and backends.
+                b'\0' => return Err(ParseError::at(ErrorKind::NulByte, s, idx)),
+                b':' if scheme && matches!(kind, Authority) && is_http_scheme(s, idx) => {
+                    kind = AbsUri
+                },
+                // The authority form contains no slashes [RFC7230§5.3.3].
+                b'/' if matches!(kind, Authority) => return Err(slash_error(s, idx)),
+                _ => {},
+            }
+
+            idx += 1;
+        }
+
+        let space = match TRAILING[last as usize] {
+            Class::Space => 1,
+            Class::MaybeSpace => trailing_whitespace_len(s),
+            _ => 0,
+        };
+
+        if space > 0 {
+            Err(ParseError::at(ErrorKind::SurroundingWhitespace, s, s.len() - space))
         } else {
-            Err(())
+            Ok(kind)
+        }
+    }
+
+    /// Classify the given target string at compile time or run time.
+    ///
+    /// This has the same rules as the `FromStr` impl but can be used in constant
+    /// expressions, such as when building static route tables.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{RequestTarget, ParseError};
+    ///
+    /// const INDEX: Result<RequestTarget, ParseError> =
+    ///     RequestTarget::classify_str("/index.html");
+    /// assert_eq!(INDEX, Ok(RequestTarget::AbsPath));
+    /// ```
+    pub const fn classify_str(s: &str) -> Result<Self, ParseError> {
+        Self::classify(s.as_bytes())
+    }
+
+    /// Classify the target at the start of the given buffer, such as the remainder of a
+    /// request line after the method.
+    ///
+    /// The target ends at the first SP, CR, or LF, or at the end of the buffer if there's
+    /// no such delimiter. On success, return the classification along with the length of
+    /// the target, not including the delimiter.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RequestTarget;
+    ///
+    /// let line = b"/r/rust HTTP/1.1\r\n";
+    /// assert_eq!(RequestTarget::parse_prefix(line), Ok((RequestTarget::AbsPath, 7)));
+    /// ```
+    pub fn parse_prefix(s: &[u8]) -> Result<(Self, usize), ParseError> {
+        let len = prefix_len(s);
+        Self::classify(&s[..len]).map(|kind| (kind, len))
+    }
+
+    /// Classify the given target bytes, rejecting targets longer than the given maximum
+    /// length with `ErrorKind::TooLong`.
+    ///
+    /// The length is checked before any bytes are scanned.
+    pub fn classify_bounded(s: &[u8], max_len: usize) -> Result<Self, ParseError> {
+        if s.len() > max_len {
+            Err(ParseError::at(ErrorKind::TooLong, s, max_len))
+        } else {
+            Self::classify(s)
+        }
+    }
+
+    /// Classify the target at the start of the given buffer like `parse_prefix`,
+    /// rejecting targets longer than the given maximum length with
+    /// `ErrorKind::TooLong`.
+    ///
+    /// No more than `max_len + 1` bytes of the buffer are scanned for the delimiter, so
+    /// this is bounded even when the buffer holds arbitrarily long attacker input.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{RequestTarget, ErrorKind};
+    ///
+    /// let line = b"/aaaaaaaaaaaaaaaa HTTP/1.1\r\n";
+    /// let err = RequestTarget::parse_prefix_bounded(line, 8).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::TooLong);
+    /// assert_eq!(err.offset(), 8);
+    /// ```
+    pub fn parse_prefix_bounded(s: &[u8], max_len: usize)
+        -> Result<(Self, usize), ParseError>
+    {
+        let window = &s[..s.len().min(max_len.saturating_add(1))];
+        let len = prefix_len(window);
+
+        if len > max_len {
+            Err(ParseError::at(ErrorKind::TooLong, window, max_len))
+        } else {
+            Ok((Self::classify(&window[..len])?, len))
+        }
+    }
+
+    /// Check if a target of this form is allowed in an HTTP/1.1 request line with the
+    /// given method.
+    ///
+    /// Authority form is required for CONNECT and not allowed for any other method
+    /// [RFC7230§5.3.3], and asterisk form is only allowed for OPTIONS [RFC7230§5.3.4].
+    /// Origin form and absolute form are allowed for every other method. The method is
+    /// compared case-sensitively [RFC7231§4.1], so `connect` is treated as an extension
+    /// method. For HTTP/2 and HTTP/3, use `valid_for_h2` instead.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RequestTarget;
+    ///
+    /// assert!(RequestTarget::Authority.valid_for_method("CONNECT"));
+    /// assert!(!RequestTarget::AbsPath.valid_for_method("CONNECT"));
+    /// assert!(!RequestTarget::ServerOptions.valid_for_method("GET"));
+    /// ```
+    pub fn valid_for_method(&self, method: &str) -> bool {
+        use self::RequestTarget::*;
+
+        match (method, *self) {
+            ("CONNECT", t) => t == Authority,
+            (_, Authority) => false,
+            ("OPTIONS", ServerOptions) => true,
+            (_, ServerOptions) => false,
+            (_, AbsPath) | (_, AbsUri) => true,
+        }
+    }
+
+    /// Determine the form of the given target without validating it.
+    ///
+    /// This skips the whitespace and octet checks done by `classify` and only looks at
+    /// the first byte and whether a slash is present, for use when the target has
+    /// already been validated by an earlier stage, such as a request framing layer.
+    ///
+    /// # Safety
+    ///
+    /// The target must be one that `classify` accepts. Otherwise, the returned form is
+    /// unspecified, and code relying on it to slice the target may misbehave.
+    pub const unsafe fn classify_unchecked(s: &[u8]) -> Self {
+        Self::discriminate(s)
+    }
+
+    /// Determine the form of the given target, which must already be known to be valid.
+    pub(crate) const fn discriminate(s: &[u8]) -> Self {
+        use self::RequestTarget::*;
+
+        match s {
+            b"*" => ServerOptions,
+            [b'/', ..] => AbsPath,
+            _ => {
+                let mut idx = 0;
+
+                // Only the URI form may contain a slash after a non-slash first byte.
+                while idx < s.len() {
+                    if s[idx] == b'/' {
+                        return AbsUri;
+                    }
+
+                    idx += 1;
+                }
+
+                Authority
+            },
+        }
+    }
+}
+
+impl core::str::FromStr for RequestTarget {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RequestTarget::classify(s.as_bytes())
+    }
+}
+
+/// Classification of the first or last byte of a target.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Class {
+    /// ASCII whitespace.
+    Space,
+    /// Part of the UTF-8 encoding of a possible non-ASCII whitespace character.
+    MaybeSpace,
+    /// Asterisk, which may be the entire OPTIONS form.
+    Star,
+    /// Slash, which begins the absolute path form.
+    Slash,
+    /// First letter of the HTTP schemes.
+    Scheme,
+    /// Any other byte.
+    Other,
+}
+
+/// Classes of bytes that begin a target.
+const LEADING: [Class; 256] = {
+    let mut t = [Class::Other; 256];
+
+    t[b'*' as usize] = Class::Star;
+    t[b'/' as usize] = Class::Slash;
+    t[b'h' as usize] = Class::Scheme;
+    t[b'H' as usize] = Class::Scheme;
+
+    // These begin the encodings of U+0085, U+00A0, U+1680, U+2000 through U+205F, and
+    // U+3000.
+    t[0xC2] = Class::MaybeSpace;
+    t[0xE1] = Class::MaybeSpace;
+    t[0xE2] = Class::MaybeSpace;
+    t[0xE3] = Class::MaybeSpace;
+
+    ascii_space(t)
+};
+
+/// Classes of bytes that end a target.
+const TRAILING: [Class; 256] = {
+    let mut t = [Class::Other; 256];
+    let mut b = 0x80;
+
+    // Every non-ASCII whitespace character ends with a continuation byte.
+    while b < 0xC0 {
+        t[b] = Class::MaybeSpace;
+        b += 1;
+    }
+
+    ascii_space(t)
+};
+
+/// Mark the ASCII whitespace bytes in the given table.
+const fn ascii_space(mut t: [Class; 256]) -> [Class; 256] {
+    let mut b = 0x09;
+
+    // U+0009 through U+000D are whitespace along with the space character.
+    while b <= 0x0D {
+        t[b] = Class::Space;
+        b += 1;
+    }
+
+    t[b' ' as usize] = Class::Space;
+    t
+}
+
+/// Find the length of the target at the start of the given buffer, up to the first SP,
+/// CR, or LF.
+fn prefix_len(s: &[u8]) -> usize {
+    s.iter().position(|&b| b == b' ' || b == b'\r' || b == b'\n').unwrap_or(s.len())
+}
+
+/// Check if the given colon position ends one of the two HTTP schemes followed by the
+/// `//` that introduces an authority.
+const fn is_http_scheme(s: &[u8], colon: usize) -> bool {
+    let (scheme, rest) = s.split_at(colon);
+
+    is_http(scheme) && matches!(rest, [b':', b'/', b'/', ..])
+}
+
+/// Check if the given scheme is one of the two HTTP schemes, which are matched
+/// case-insensitively [RFC3986§3.1].
+pub(crate) const fn is_http(scheme: &[u8]) -> bool {
+    eq_ignore_case(scheme, b"http") || eq_ignore_case(scheme, b"https")
+}
+
+/// Check if the given bytes are equal, ignoring ASCII case.
+pub(crate) const fn eq_ignore_case(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && starts_with_ignore_case(a, b)
+}
+
+/// Check if the given bytes start with the given prefix, ignoring ASCII case.
+pub(crate) const fn starts_with_ignore_case(s: &[u8], prefix: &[u8]) -> bool {
+    if s.len() < prefix.len() {
+        return false;
+    }
+
+    let mut idx = 0;
+
+    while idx < prefix.len() {
+        if !s[idx].eq_ignore_ascii_case(&prefix[idx]) {
+            return false;
+        }
+
+        idx += 1;
+    }
+
+    true
+}
+
+/// Determine the error for a slash at the given position in a target that would
+/// otherwise be in authority form.
+const fn slash_error(s: &[u8], idx: usize) -> ParseError {
+    match s.split_at(idx).0 {
+        [scheme @ .., b':'] if is_scheme(scheme) && !is_http(scheme) => {
+            ParseError::at(ErrorKind::UnknownScheme, s, idx)
+        },
+        _ => ParseError::at(ErrorKind::SlashInAuthority, s, idx),
+    }
+}
+
+/// Check if the given bytes are a syntactically valid scheme [RFC3986§3.1].
+pub(crate) const fn is_scheme(s: &[u8]) -> bool {
+    let mut idx = 0;
+
+    while idx < s.len() {
+        if !is_scheme_byte(s[idx], idx == 0) {
+            return false;
         }
+
+        idx += 1;
+    }
+
+    !s.is_empty()
+}
+
+/// Check if the given byte may appear in a scheme, either as the first byte or after.
+pub(crate) const fn is_scheme_byte(b: u8, first: bool) -> bool {
+    match b {
+        b'a'..=b'z' | b'A'..=b'Z' => true,
+        b'0'..=b'9' | b'+' | b'-' | b'.' => !first,
+        _ => false,
+    }
+}
+
+/// Check if the given bytes begin with a UTF-8 encoded whitespace character.
+pub(crate) const fn starts_with_whitespace(s: &[u8]) -> bool {
+    leading_whitespace_len(s) > 0
+}
+
+/// Find the encoded length of the whitespace character that begins the given bytes, or
+/// 0 if they don't begin with whitespace.
+pub(crate) const fn leading_whitespace_len(s: &[u8]) -> usize {
+    match decode_char(s) {
+        Some((c, len)) if c.is_whitespace() => len,
+        _ => 0,
+    }
+}
+
+/// Find the encoded length of the whitespace character that ends the given bytes, or 0
+/// if they don't end with whitespace.
+pub(crate) const fn trailing_whitespace_len(s: &[u8]) -> usize {
+    if s.is_empty() {
+        return 0;
+    }
+
+    // Back up over at most 3 continuation bytes to the start of the last character.
+    let mut start = s.len() - 1;
+
+    while start > 0 && s.len() - start < 4 && s[start] & 0xC0 == 0x80 {
+        start -= 1;
+    }
+
+    match decode_char(s.split_at(start).1) {
+        Some((c, len)) if len == s.len() - start && c.is_whitespace() => len,
+        _ => 0,
+    }
+}
+
+/// Decode the UTF-8 character at the start of the given bytes, returning it along with
+/// its encoded length.
+const fn decode_char(s: &[u8]) -> Option<(char, usize)> {
+    let (len, mut c) = match s.first() {
+        Some(&b @ 0x00..=0x7F) => (1, b as u32),
+        Some(&b @ 0xC0..=0xDF) => (2, (b & 0x1F) as u32),
+        Some(&b @ 0xE0..=0xEF) => (3, (b & 0x0F) as u32),
+        Some(&b @ 0xF0..=0xF7) => (4, (b & 0x07) as u32),
+        _ => return None,
+    };
+
+    if s.len() < len || core::str::from_utf8(s.split_at(len).0).is_err() {
+        return None;
+    }
+
+    let mut idx = 1;
+
+    while idx < len {
+        c = c << 6 | (s[idx] & 0x3F) as u32;
+        idx += 1;
+    }
+
+    match core::char::from_u32(c) {
+        Some(c) => Some((c, len)),
+        None => None,
     }
 }
 
@@ -65,39 +632,299 @@ impl std::str::FromStr for RequestTarget {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_valid_for_method() {
+        use self::RequestTarget::*;
+
+        assert!(AbsPath.valid_for_method("GET"));
+        assert!(AbsPath.valid_for_method("OPTIONS"));
+        assert!(AbsUri.valid_for_method("POST"));
+        assert!(AbsUri.valid_for_method("OPTIONS"));
+        assert!(Authority.valid_for_method("CONNECT"));
+        assert!(ServerOptions.valid_for_method("OPTIONS"));
+        assert!(AbsPath.valid_for_method("connect"));
+        assert!(!AbsPath.valid_for_method("CONNECT"));
+        assert!(!AbsUri.valid_for_method("CONNECT"));
+        assert!(!ServerOptions.valid_for_method("CONNECT"));
+        assert!(!Authority.valid_for_method("GET"));
+        assert!(!Authority.valid_for_method("OPTIONS"));
+        assert!(!ServerOptions.valid_for_method("GET"));
+        assert!(!ServerOptions.valid_for_method("options"));
+    }
+
     #[test]
     fn test_request_target() {
         use self::RequestTarget::*;
 
-        assert_eq!("".parse::<RequestTarget>(), Err(()));
-        assert_eq!("  ".parse::<RequestTarget>(), Err(()));
-        assert_eq!("\t\n\r\u{2008}\u{00A0}\u{205F}".parse::<RequestTarget>(), Err(()));
-        assert_eq!("".parse::<RequestTarget>(), Err(()));
+        assert_eq!("".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+        assert_eq!("  ".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!(
+            "\t\n\r\u{2008}\u{00A0}\u{205F}".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!("".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
 
         assert_eq!("*".parse::<RequestTarget>(), Ok(ServerOptions));
-        assert_eq!(" *".parse::<RequestTarget>(), Err(()));
-        assert_eq!("* ".parse::<RequestTarget>(), Err(()));
-        assert_eq!("   *  ".parse::<RequestTarget>(), Err(()));
+        assert_eq!(" *".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!("* ".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!("   *  ".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
 
         assert_eq!("/path/sub/42".parse::<RequestTarget>(), Ok(AbsPath));
         assert_eq!("/path/sub/42?key=value".parse::<RequestTarget>(), Ok(AbsPath));
         assert_eq!("/where?q=now".parse::<RequestTarget>(), Ok(AbsPath));
-        assert_eq!(" /path/sub/42".parse::<RequestTarget>(), Err(()));
+        assert_eq!(" /path/sub/42".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
         assert_eq!("/path/sub boop/42".parse::<RequestTarget>(), Ok(AbsPath));
 
         assert_eq!("www.example.com:80".parse::<RequestTarget>(), Ok(Authority));
         assert_eq!("www.example.com".parse::<RequestTarget>(), Ok(Authority));
         assert_eq!("example.com".parse::<RequestTarget>(), Ok(Authority));
         assert_eq!("user@example.com".parse::<RequestTarget>(), Ok(Authority));
-        assert_eq!("user@example.com/".parse::<RequestTarget>(), Err(()));
+        assert_eq!("user@example.com/".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
         assert_eq!("user name@example.com".parse::<RequestTarget>(), Ok(Authority));
 
         assert_eq!("http://zombo.com".parse::<RequestTarget>(), Ok(AbsUri));
         assert_eq!("http://picard.ytmnd.com/".parse::<RequestTarget>(), Ok(AbsUri));
         assert_eq!("https://rust-lang.org".parse::<RequestTarget>(), Ok(AbsUri));
         assert_eq!("https://rust-lang.org/a path".parse::<RequestTarget>(), Ok(AbsUri));
-        assert_eq!("http:/zombo.com".parse::<RequestTarget>(), Err(()));
-        assert_eq!("file:/rust-lang.org".parse::<RequestTarget>(), Err(()));
-        assert_eq!("ftp://rust-lang.org".parse::<RequestTarget>(), Err(()));
+        assert_eq!("http:/zombo.com".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!("file:/rust-lang.org".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+        assert_eq!("ftp://rust-lang.org".parse::<RequestTarget>().map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+    }
+
+    #[test]
+    fn test_classify() {
+        use self::RequestTarget::*;
+
+        assert_eq!(RequestTarget::classify(b"").map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+        assert_eq!(RequestTarget::classify(b"  ").map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!(RequestTarget::classify("\u{2008}/a".as_bytes()).map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!(RequestTarget::classify("/a\u{00A0}".as_bytes()).map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!(RequestTarget::classify(b"\t*").map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+        assert_eq!(RequestTarget::classify(b"*\r").map_err(|e| e.kind()),
+            Err(ErrorKind::IllegalCharacter));
+
+        assert_eq!(RequestTarget::classify(b"*"), Ok(ServerOptions));
+        assert_eq!(RequestTarget::classify(b"/a/b?c"), Ok(AbsPath));
+        assert_eq!(RequestTarget::classify(b"/\xff\xfe"), Ok(AbsPath));
+        assert_eq!(RequestTarget::classify(b"/\xc2"), Ok(AbsPath));
+        assert_eq!(RequestTarget::classify(b"\xa0/").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::classify(b"\xa0"), Ok(Authority));
+        assert_eq!(RequestTarget::classify(b"example.com:80"), Ok(Authority));
+        assert_eq!(RequestTarget::classify(b"http://example.com"), Ok(AbsUri));
+        assert_eq!(RequestTarget::classify(b"https://example.com/"), Ok(AbsUri));
+        assert_eq!(RequestTarget::classify(b"HTTP://example.com"), Ok(AbsUri));
+        assert_eq!(RequestTarget::classify(b"hTtPs://example.com/"), Ok(AbsUri));
+        assert_eq!(RequestTarget::classify(b"HTTPX://a").map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+        assert_eq!(RequestTarget::classify(b"ftp://example.com/").map_err(|e| e.kind()),
+            Err(ErrorKind::UnknownScheme));
+        assert_eq!(RequestTarget::classify(b"http:/example.com").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::classify(b"http:"), Ok(Authority));
+        assert_eq!(RequestTarget::classify(b"http:80"), Ok(Authority));
+        assert_eq!(RequestTarget::classify(b"*a"), Ok(Authority));
+        assert_eq!(RequestTarget::classify(b"a:http://").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::classify(b"http:/").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::classify(b"https:"), Ok(Authority));
+
+        assert_eq!(RequestTarget::classify(b"/a\rb").map_err(|e| e.kind()),
+            Err(ErrorKind::IllegalCharacter));
+        assert_eq!(RequestTarget::classify(b"/a\nb").map_err(|e| e.kind()),
+            Err(ErrorKind::IllegalCharacter));
+        assert_eq!(RequestTarget::classify(b"/a\0b").map_err(|e| e.kind()),
+            Err(ErrorKind::NulByte));
+        assert_eq!(RequestTarget::classify(b"http://a\r\n/").map_err(|e| e.kind()),
+            Err(ErrorKind::IllegalCharacter));
+        assert_eq!(RequestTarget::classify(b"a\0b").map_err(|e| e.kind()),
+            Err(ErrorKind::NulByte));
+        assert_eq!(RequestTarget::classify(b"/a\tb"), Ok(AbsPath));
+
+        for c in (0..0x3100).filter_map(std::char::from_u32) {
+            let invalid = c.is_whitespace() || c == '\0';
+
+            let s = format!("{}a", c);
+            assert_eq!(RequestTarget::classify(s.as_bytes()).is_err(), invalid);
+            assert_eq!(starts_with_whitespace(s.as_bytes()), c.is_whitespace());
+
+            let s = format!("/a{}", c);
+            assert_eq!(RequestTarget::classify(s.as_bytes()).is_err(), invalid);
+            assert_eq!(trailing_whitespace_len(s.as_bytes()) > 0, c.is_whitespace());
+        }
+
+        assert_eq!(trailing_whitespace_len(b""), 0);
+        assert_eq!(trailing_whitespace_len(b"\x80\x80\x80\x80"), 0);
+        assert_eq!(trailing_whitespace_len(b"\xc2"), 0);
+        assert_eq!(trailing_whitespace_len(b"\xa0"), 0);
+        assert_eq!(trailing_whitespace_len(b"\xc2\xa0"), 2);
+        assert_eq!(trailing_whitespace_len(b"\xc2\xa0\xa0"), 0);
+        assert!(!starts_with_whitespace(b"\xc2"));
+        assert!(!starts_with_whitespace(b"\xe3\x80"));
+        assert!(starts_with_whitespace(b"\xe3\x80\x80"));
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        use self::RequestTarget::*;
+
+        assert_eq!(RequestTarget::parse_prefix(b"* HTTP/1.1\r\n"), Ok((ServerOptions, 1)));
+        assert_eq!(RequestTarget::parse_prefix(b"/a?b HTTP/1.1"), Ok((AbsPath, 4)));
+        assert_eq!(RequestTarget::parse_prefix(b"/a\r\n"), Ok((AbsPath, 2)));
+        assert_eq!(RequestTarget::parse_prefix(b"/a\n"), Ok((AbsPath, 2)));
+        assert_eq!(RequestTarget::parse_prefix(b"/a"), Ok((AbsPath, 2)));
+        assert_eq!(RequestTarget::parse_prefix(b"http://a/b c"), Ok((AbsUri, 10)));
+        assert_eq!(RequestTarget::parse_prefix(b"a.com:443 HTTP/1.1"), Ok((Authority, 9)));
+        assert_eq!(RequestTarget::parse_prefix(b"/a\tb c"), Ok((AbsPath, 4)));
+
+        assert_eq!(RequestTarget::parse_prefix(b"").map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+        assert_eq!(RequestTarget::parse_prefix(b" /a").map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+        assert_eq!(RequestTarget::parse_prefix(b"\r\n").map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+        assert_eq!(RequestTarget::parse_prefix(b"a/b HTTP/1.1").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::parse_prefix(b"/a\t HTTP/1.1").map_err(|e| e.kind()),
+            Err(ErrorKind::SurroundingWhitespace));
+    }
+
+    #[test]
+    fn test_bounded() {
+        use self::RequestTarget::*;
+
+        assert_eq!(RequestTarget::classify_bounded(b"/abc", 4), Ok(AbsPath));
+        assert_eq!(RequestTarget::classify_bounded(b"/abcd", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(RequestTarget::classify_bounded(b"a/b", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(RequestTarget::classify_bounded(b"/a b", 2).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(RequestTarget::classify_bounded(b"", 0).map_err(|e| e.kind()),
+            Err(ErrorKind::Empty));
+
+        assert_eq!(RequestTarget::parse_prefix_bounded(b"/abc HTTP/1.1", 4),
+            Ok((AbsPath, 4)));
+        assert_eq!(RequestTarget::parse_prefix_bounded(b"/abc", 4), Ok((AbsPath, 4)));
+        assert_eq!(
+            RequestTarget::parse_prefix_bounded(b"/abcd HTTP/1.1", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(RequestTarget::parse_prefix_bounded(b"/abcd", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(
+            RequestTarget::parse_prefix_bounded(b"a/b HTTP/1.1", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        // The invalid part is beyond the limit, so the length is reported first.
+        assert_eq!(
+            RequestTarget::parse_prefix_bounded(b"abcd/ HTTP/1.1", 4).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(RequestTarget::parse_prefix_bounded(b"/a", usize::MAX),
+            Ok((AbsPath, 2)));
+    }
+
+    #[test]
+    fn test_classify_unchecked() {
+        for &s in &["*", "/", "/a?b", "//a", "http://a", "https://a/b", "a", "a:80", "*a"] {
+            assert_eq!(unsafe { RequestTarget::classify_unchecked(s.as_bytes()) },
+                s.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_total() {
+        const ALPHABET: &[u8] = b"/*:?@[]#%.0ahps \r\n\0\xc2\xa0\xff";
+
+        // Run every entry point over each short string of interesting bytes behind each
+        // interesting prefix, which panics on failure.
+        for prefix in &[&b""[..], b"/", b"*", b"a", b"http://", b"https://", b"http:"] {
+            for len in 0..4 {
+                for n in 0..ALPHABET.len().pow(len as u32) {
+                    let mut s = prefix.to_vec();
+                    let mut n = n;
+
+                    for _ in 0..len {
+                        s.push(ALPHABET[n % ALPHABET.len()]);
+                        n /= ALPHABET.len();
+                    }
+
+                    let kind = RequestTarget::classify(&s);
+                    let _ = RequestTarget::parse_prefix(&s);
+
+                    if let Ok(kind) = kind {
+                        assert_eq!(RequestTarget::discriminate(&s), kind);
+                    }
+
+                    if let Ok(s) = std::str::from_utf8(&s) {
+                        assert_eq!(s.parse(), kind);
+
+                        if let Ok(t) = ParsedTarget::parse(s) {
+                            assert_eq!(Ok(t.kind()), kind);
+                            assert_eq!(t.as_str(), s);
+                            assert_eq!(TargetSpans::parse(s), Ok(t.spans()));
+                        }
+
+                        let _ = OriginForm::parse(s);
+                        let _ = AbsoluteForm::parse(s);
+                        let _ = AuthorityForm::parse(s);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_offset() {
+        fn err(s: &[u8]) -> (ErrorKind, usize, Option<u8>) {
+            let e = RequestTarget::classify(s).unwrap_err();
+            (e.kind(), e.offset(), e.byte())
+        }
+
+        assert_eq!(err(b""), (ErrorKind::Empty, 0, None));
+        assert_eq!(err(b"\t/a"), (ErrorKind::SurroundingWhitespace, 0, Some(b'\t')));
+        assert_eq!(err(b"/a "), (ErrorKind::SurroundingWhitespace, 2, Some(b' ')));
+        assert_eq!(err("/a\u{3000}".as_bytes()),
+            (ErrorKind::SurroundingWhitespace, 2, Some(0xE3)));
+        assert_eq!(err(b"/abc\0d"), (ErrorKind::NulByte, 4, Some(0)));
+        assert_eq!(err(b"/abc\rd"), (ErrorKind::IllegalCharacter, 4, Some(b'\r')));
+        assert_eq!(err(b"example.com/a"), (ErrorKind::SlashInAuthority, 11, Some(b'/')));
+        assert_eq!(err(b"ftp://a"), (ErrorKind::UnknownScheme, 4, Some(b'/')));
+        // The first violation is reported, even if others follow.
+        assert_eq!(err(b"a\0/\r"), (ErrorKind::NulByte, 1, Some(0)));
+        assert_eq!(err(b"a\r/\0"), (ErrorKind::IllegalCharacter, 1, Some(b'\r')));
+
+        let e = RequestTarget::classify_bounded(b"/abcdef", 4).unwrap_err();
+        assert_eq!((e.kind(), e.offset(), e.byte()), (ErrorKind::TooLong, 4, Some(b'd')));
+    }
+
+    #[test]
+    fn test_classify_str() {
+        use self::RequestTarget::*;
+
+        const TARGETS: [Result<RequestTarget, ParseError>; 5] = [
+            RequestTarget::classify_str("*"),
+            RequestTarget::classify_str("/a"),
+            RequestTarget::classify_str("http://a"),
+            RequestTarget::classify_str("a:80"),
+            RequestTarget::classify_str(" /a"),
+        ];
+
+        assert_eq!(TARGETS, [Ok(ServerOptions), Ok(AbsPath), Ok(AbsUri), Ok(Authority),
+            Err(ParseError::at(ErrorKind::SurroundingWhitespace, b" /a", 0))]);
     }
 }
@@ -0,0 +1,173 @@
+//! Common interface to the matchers of classified targets.
+
+use {ParsedTarget, RequestTarget};
+use acl::{Acl, Rule};
+use glob::Glob;
+use route::RoutePattern;
+
+#[cfg(feature = "alloc")]
+use openapi::{PathTemplate, PathTemplates};
+#[cfg(feature = "alloc")]
+use prefix::PrefixTable;
+#[cfg(feature = "alloc")]
+use router::Router;
+
+/// Matcher that decides whether a classified target is of interest, such as a path
+/// pattern of a route or a condition of an access rule.
+///
+/// The trait is object-safe, so matchers of different types can be stored together as
+/// `&dyn TargetMatcher` and plugged into an access rule with `Rule::matcher`. It's
+/// implemented for every closure taking a `&ParsedTarget`, so an application can supply
+/// its own conditions without defining a type.
+///
+/// The built-in path matchers match origin-form and absolute-form targets by their path,
+/// where an empty absolute-form path is treated like `/`, and never match authority-form
+/// or asterisk-form targets, which have no path.
+///
+/// ```rust
+/// use uhttp_request_target::{Glob, ParsedTarget, RequestTarget, RoutePattern,
+///                            TargetMatcher};
+///
+/// let glob = Glob::parse("/static/**").unwrap();
+/// let route = RoutePattern::parse("/users/{id}").unwrap();
+/// let connect = |t: &ParsedTarget| t.kind() == RequestTarget::Authority;
+///
+/// let matchers: [&dyn TargetMatcher; 3] = [&glob, &route, &connect];
+/// let t = ParsedTarget::parse("/users/42?full=1").unwrap();
+///
+/// assert_eq!(matchers.iter().position(|m| m.matches(&t)), Some(1));
+/// ```
+pub trait TargetMatcher {
+    /// Check if the given target matches.
+    fn matches(&self, target: &ParsedTarget) -> bool;
+}
+
+/// Retrieve the path matched by the path matchers, if the given target has one.
+fn target_path<'a>(target: &ParsedTarget<'a>) -> Option<&'a str> {
+    match *target {
+        ParsedTarget::AbsPath(ref form) => Some(form.path()),
+        ParsedTarget::AbsUri(ref form) => Some(form.path_or_root()),
+        ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => None,
+    }
+}
+
+impl<F: Fn(&ParsedTarget) -> bool> TargetMatcher for F {
+    fn matches(&self, target: &ParsedTarget) -> bool { self(target) }
+}
+
+/// Matches targets of the form.
+impl TargetMatcher for RequestTarget {
+    fn matches(&self, target: &ParsedTarget) -> bool { target.kind() == *self }
+}
+
+impl<'p> TargetMatcher for Glob<'p> {
+    fn matches(&self, target: &ParsedTarget) -> bool {
+        target_path(target).is_some_and(|path| self.is_match(path))
+    }
+}
+
+impl<'p> TargetMatcher for RoutePattern<'p> {
+    fn matches(&self, target: &ParsedTarget) -> bool {
+        target_path(target).is_some_and(|path| self.is_match(path))
+    }
+}
+
+/// Matches targets that meet all the conditions of the rule, whatever its action.
+impl<'p> TargetMatcher for Rule<'p> {
+    fn matches(&self, target: &ParsedTarget) -> bool { Rule::matches(self, target) }
+}
+
+/// Matches targets allowed by the list.
+impl<'r, 'p> TargetMatcher for Acl<'r, 'p> {
+    fn matches(&self, target: &ParsedTarget) -> bool {
+        self.evaluate(target).is_allowed()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'t> TargetMatcher for PathTemplate<'t> {
+    fn matches(&self, target: &ParsedTarget) -> bool {
+        target_path(target).is_some_and(|path| self.is_match(path))
+    }
+}
+
+/// Matches targets with a path that a template matches, even if its variables don't
+/// decode.
+#[cfg(feature = "alloc")]
+impl<T> TargetMatcher for PathTemplates<T> {
+    fn matches(&self, target: &ParsedTarget) -> bool { self.route(target).is_some() }
+}
+
+/// Matches targets with a path that a route matches, even if its captures don't decode.
+#[cfg(feature = "alloc")]
+impl<T> TargetMatcher for Router<T> {
+    fn matches(&self, target: &ParsedTarget) -> bool { self.route(target).is_some() }
+}
+
+/// Matches targets with a path that a prefix matches.
+#[cfg(feature = "alloc")]
+impl<T> TargetMatcher for PrefixTable<T> {
+    fn matches(&self, target: &ParsedTarget) -> bool { self.route(target).is_some() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use acl::Action;
+
+    fn parse(s: &str) -> ParsedTarget<'_> { ParsedTarget::parse(s).unwrap() }
+
+    #[test]
+    fn test_matchers() {
+        let glob = Glob::parse("/a/*").unwrap();
+        let route = RoutePattern::parse("/a/{b}").unwrap();
+        let rule = Rule::deny().form(RequestTarget::AbsPath);
+        let rules = [Rule::allow().host("x")];
+        let acl = Acl::new(&rules, Action::Deny);
+        let closure = |t: &ParsedTarget| t.kind() == RequestTarget::ServerOptions;
+
+        let matchers: [&dyn TargetMatcher; 6] =
+            [&glob, &route, &rule, &RequestTarget::AbsUri, &acl, &closure];
+        let matching = |s| {
+            let t = parse(s);
+            let mut out = [false; 6];
+
+            for (o, m) in out.iter_mut().zip(matchers.iter()) {
+                *o = m.matches(&t);
+            }
+
+            out
+        };
+
+        assert_eq!(matching("/a/b?c"), [true, true, true, false, false, false]);
+        assert_eq!(matching("http://x/a/b"), [true, true, false, true, true, false]);
+        assert_eq!(matching("http://y"), [false, false, false, true, false, false]);
+        assert_eq!(matching("x:443"), [false, false, false, false, true, false]);
+        assert_eq!(matching("*"), [false, false, false, false, false, true]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_alloc_matchers() {
+        let template = PathTemplate::parse("/a/{b}").unwrap();
+        let mut templates = PathTemplates::new();
+        templates.insert("/a/{b}", ()).unwrap();
+        let mut router = Router::new();
+        router.insert("/a/{b}", ()).unwrap();
+        let mut table = PrefixTable::new();
+        table.insert("/a", ()).unwrap();
+
+        let matchers: [&dyn TargetMatcher; 4] = [&template, &templates, &router, &table];
+        let matching = |s| {
+            let t = parse(s);
+            matchers.iter().map(|m| m.matches(&t)).collect::<alloc::vec::Vec<_>>()
+        };
+
+        assert_eq!(matching("/a/b?c"), [true, true, true, true]);
+        assert_eq!(matching("http://x/a/b"), [true, true, true, true]);
+        assert_eq!(matching("/a/%FF"), [false, true, true, true]);
+        assert_eq!(matching("/a/b/c"), [false, false, false, true]);
+        assert_eq!(matching("/b"), [false, false, false, false]);
+        assert_eq!(matching("x:443"), [false, false, false, false]);
+    }
+}
@@ -0,0 +1,55 @@
+//! Socket conveniences, available with the `std` feature.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::vec;
+
+use AuthorityForm;
+
+impl<'a> AuthorityForm<'a> {
+    /// Retrieve the host in the form expected by `std::net` resolution, with any IP
+    /// literal brackets removed.
+    pub fn host_unbracketed(&self) -> &'a str {
+        let host = self.host();
+
+        if host.starts_with('[') && host.ends_with(']') {
+            &host[1..host.len() - 1]
+        } else {
+            host
+        }
+    }
+}
+
+/// Resolve the host and port of a CONNECT target, such as `example.com:443`.
+///
+/// Targets without a port can't be resolved and produce an `InvalidInput` error.
+impl<'a> ToSocketAddrs for AuthorityForm<'a> {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        match self.port() {
+            Some(port) => (self.host_unbracketed(), port).to_socket_addrs(),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "target has no port")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_socket_addrs() {
+        let f = AuthorityForm::parse("127.0.0.1:8080").unwrap();
+        assert_eq!(f.to_socket_addrs().unwrap().collect::<Vec<_>>(),
+            vec!["127.0.0.1:8080".parse().unwrap()]);
+
+        let f = AuthorityForm::parse("[::1]:443").unwrap();
+        assert_eq!(f.host_unbracketed(), "::1");
+        assert_eq!(f.to_socket_addrs().unwrap().collect::<Vec<_>>(),
+            vec!["[::1]:443".parse().unwrap()]);
+
+        let f = AuthorityForm::parse("127.0.0.1").unwrap();
+        assert_eq!(f.to_socket_addrs().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,1147 @@
+//! Normalization of targets and their components [RFC3986§6].
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+use {ErrorKind, ParseError, ParsedTarget, Scheme};
+use charset::is_unreserved;
+use decode::hex_value;
+use form::offset;
+
+/// Options controlling how `NormalizeOptions::normalize_into` rewrites a target.
+///
+/// No normalization is done by default, so each step can be enabled individually for
+/// the equivalence a deployment needs.
+///
+/// ```rust
+/// use uhttp_request_target::{NormalizeOptions, ParsedTarget};
+///
+/// let opts = NormalizeOptions::new().lowercase(true);
+/// let t = ParsedTarget::parse("HTTP://EXAMPLE.com/A").unwrap();
+/// let mut buf = [0; 32];
+///
+/// let len = opts.normalize_into(&t, &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"http://example.com/A");
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct NormalizeOptions<'s> {
+    lowercase: bool,
+    uppercase_escapes: bool,
+    decode_unreserved: bool,
+    remove_dot_segments: bool,
+    collapse_slashes: bool,
+    sort_query: bool,
+    strip_params: &'s [&'s str],
+    keep_params: Option<&'s [&'s str]>,
+    default_ports: u8,
+    root_empty_path: bool,
+    strip_trailing_dot: bool,
+}
+
+impl<'s> NormalizeOptions<'s> {
+    /// Create options that copy targets unchanged.
+    pub const fn new() -> Self {
+        NormalizeOptions {
+            lowercase: false,
+            uppercase_escapes: false,
+            decode_unreserved: false,
+            remove_dot_segments: false,
+            collapse_slashes: false,
+            sort_query: false,
+            strip_params: &[],
+            keep_params: None,
+            default_ports: 0,
+            root_empty_path: false,
+            strip_trailing_dot: false,
+        }
+    }
+
+    /// Create options that produce a canonical target suitable as a cache key.
+    ///
+    /// This enables the normalizations that preserve URI equivalence [RFC3986§6.2.2,
+    /// RFC3986§6.2.3]: case normalization, percent-encoding normalization, dot-segment
+    /// removal, default port elision for both HTTP schemes, and rooting an empty path.
+    /// Slash collapsing and trailing-dot stripping change which resource may be
+    /// identified, so they're left to be enabled explicitly.
+    pub const fn cache_key() -> Self {
+        NormalizeOptions {
+            lowercase: true,
+            uppercase_escapes: true,
+            decode_unreserved: true,
+            remove_dot_segments: true,
+            collapse_slashes: false,
+            sort_query: false,
+            strip_params: &[],
+            keep_params: None,
+            default_ports: Scheme::Http.bit() | Scheme::Https.bit(),
+            root_empty_path: true,
+            strip_trailing_dot: false,
+        }
+    }
+
+    /// Set whether to lowercase the scheme and host of absolute-form and authority-form
+    /// targets, which are case-insensitive [RFC3986§6.2.2.1].
+    ///
+    /// Percent-encoded octets in the host are left unchanged, and so are the userinfo,
+    /// path, and query, which are case-sensitive.
+    pub const fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Set whether to uppercase the hex digits of percent-encoded octets in the path,
+    /// query, and fragment, as with `uppercase_escapes_into`.
+    pub const fn uppercase_escapes(mut self, uppercase: bool) -> Self {
+        self.uppercase_escapes = uppercase;
+        self
+    }
+
+    /// Set whether to decode percent-encoded unreserved characters in the path, query,
+    /// and fragment, as with `decode_unreserved_into`.
+    pub const fn decode_unreserved(mut self, decode: bool) -> Self {
+        self.decode_unreserved = decode;
+        self
+    }
+
+    /// Set whether to remove `.` and `..` segments from the path, as with
+    /// `remove_dot_segments_into`.
+    ///
+    /// This is done after any percent-encoding normalization, so `%2E%2E` segments are
+    /// removed too when unreserved characters are decoded.
+    pub const fn remove_dot_segments(mut self, remove: bool) -> Self {
+        self.remove_dot_segments = remove;
+        self
+    }
+
+    /// Set whether to collapse runs of `/` in the path, as with `collapse_slashes_into`.
+    ///
+    /// This is done before dot-segment removal, so `/a//../b` becomes `/b`.
+    pub const fn collapse_slashes(mut self, collapse: bool) -> Self {
+        self.collapse_slashes = collapse;
+        self
+    }
+
+    /// Set whether to sort the `&`-separated query parameters by key, the part before any
+    /// `=`, so targets with reordered parameters normalize the same.
+    ///
+    /// Keys are compared bytewise after any percent-encoding normalization, and the sort
+    /// is stable, so the values of duplicate keys keep their order. Parameter order may be
+    /// significant to the origin, so this isn't part of URI equivalence.
+    pub const fn sort_query(mut self, sort: bool) -> Self {
+        self.sort_query = sort;
+        self
+    }
+
+    /// Set the query parameters to remove, such as `&["utm_*", "fbclid"]`, so tracking
+    /// parameters don't fragment cache keys.
+    ///
+    /// Each pattern matches a parameter with exactly that key, or with a key starting
+    /// with the part before a trailing `*`. Keys are compared bytewise after any
+    /// percent-encoding normalization. The `?` is removed too if no parameters remain.
+    /// The patterns are borrowed, so they can come from runtime configuration.
+    pub const fn strip_params(mut self, patterns: &'s [&'s str]) -> Self {
+        self.strip_params = patterns;
+        self
+    }
+
+    /// Set the only query parameters to keep, matched like `strip_params`, removing all
+    /// others.
+    ///
+    /// Parameters matching `strip_params` are still removed.
+    pub const fn keep_params(mut self, patterns: &'s [&'s str]) -> Self {
+        self.keep_params = Some(patterns);
+        self
+    }
+
+    /// Set whether to remove the port from absolute-form targets with the given scheme
+    /// when it's the default port of the scheme, such as `:80` for `http`.
+    ///
+    /// The default port is equivalent to omitting it [RFC3986§6.2.3], so this keeps
+    /// cache keys and origin comparisons from being fragmented by explicit ports. An
+    /// empty port, such as in `http://example.com:/`, is removed too.
+    pub const fn elide_default_port(mut self, scheme: Scheme, elide: bool) -> Self {
+        if elide {
+            self.default_ports |= scheme.bit();
+        } else {
+            self.default_ports &= !scheme.bit();
+        }
+
+        self
+    }
+
+    /// Set whether to write an empty path of an absolute-form target as `/`, as with
+    /// `AbsoluteForm::path_or_root`, so `http://example.com` and `http://example.com/`
+    /// normalize the same.
+    pub const fn root_empty_path(mut self, root: bool) -> Self {
+        self.root_empty_path = root;
+        self
+    }
+
+    /// Set whether to remove a single trailing dot from the host of absolute-form and
+    /// authority-form targets, such as `example.com.`.
+    ///
+    /// A fully qualified name with a trailing dot usually resolves the same as the name
+    /// without it, but the two aren't equivalent URIs, so CDNs and caches that want
+    /// consistent keys normalize it away. A host consisting only of a dot is left alone.
+    pub const fn strip_trailing_dot(mut self, strip: bool) -> Self {
+        self.strip_trailing_dot = strip;
+        self
+    }
+
+    /// Write the given target into the given buffer with the normalizations enabled in
+    /// these options applied, returning the number of bytes written.
+    ///
+    /// A buffer one byte longer than the target is always large enough, and
+    /// `ErrorKind::TooLong` is otherwise returned at the offset of the target byte that
+    /// didn't fit.
+    pub fn normalize_into(&self, target: &ParsedTarget, dst: &mut [u8])
+        -> Result<usize, ParseError>
+    {
+        let s = target.as_str();
+        let mut out = Out::new(s.as_bytes(), dst);
+
+        match *target {
+            ParsedTarget::AbsPath(ref form) => {
+                self.push_path(&mut out, s, form.path(), false)?
+            },
+            ParsedTarget::ServerOptions => out.push_str(s, 0)?,
+            ParsedTarget::AbsUri(ref form) => {
+                self.push_lower(&mut out, s, form.scheme())?;
+                out.push(b"://", form.scheme().len())?;
+                let port = match Scheme::from_name(form.scheme()) {
+                    Some(scheme) if self.default_ports & scheme.bit() != 0 &&
+                                    form.port().is_none_or(|p| p == scheme.default_port()) =>
+                    {
+                        None
+                    },
+                    _ => form.port_str(),
+                };
+
+                self.push_authority(&mut out, s, form.userinfo(), form.host(), port)?;
+
+                let root = self.root_empty_path && form.path().is_empty();
+                self.push_path(&mut out, s, form.path(), root)?;
+            },
+            ParsedTarget::Authority(ref form) => {
+                self.push_authority(&mut out, s, form.userinfo(), form.host(),
+                                    form.port_str())?;
+            },
+        }
+
+        Ok(out.len)
+    }
+
+    /// Write the given path of the given target and everything after it, preceded by a
+    /// `/` if `root` is set.
+    fn push_path(&self, out: &mut Out, s: &str, path: &str, root: bool)
+        -> Result<(), ParseError>
+    {
+        // The path and query end at the first `#`, even if it comes before a `?`.
+        let start = offset(s, path);
+        let frag = s[start..].find('#').map_or(s.len(), |idx| start + idx);
+        let end = frag.min(start + path.len());
+        let path = &s[start..end];
+
+        let start = out.len;
+
+        if root {
+            out.push(b"/", end)?;
+        }
+
+        self.push_escaped(out, s, path, self.collapse_slashes)?;
+
+        if self.remove_dot_segments {
+            out.len = start + remove_dot_segments_in_place(&mut out.dst[start..out.len]);
+        }
+
+        // This includes the `?` before any query.
+        let query = &s[end..frag];
+        let start = out.len;
+
+        self.push_escaped(out, s, query, false)?;
+
+        if out.len > start && (!self.strip_params.is_empty() || self.keep_params.is_some()) {
+            let len = self.filter_params_in_place(&mut out.dst[start + 1..out.len]);
+
+            out.len = match len {
+                0 if out.len > start + 1 => start,
+                len => start + 1 + len,
+            };
+        }
+
+        if self.sort_query && out.len > start {
+            sort_params_in_place(&mut out.dst[start + 1..out.len]);
+        }
+
+        self.push_escaped(out, s, &s[frag..], false)
+    }
+
+    /// Remove the parameters from the given query that aren't kept by these options,
+    /// returning the length of the result at the start of the buffer.
+    fn filter_params_in_place(&self, buf: &mut [u8]) -> usize {
+        let matches = |patterns: &[&str], key: &[u8]| {
+            patterns.iter().any(|p| match p.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix.as_bytes()),
+                None => key == p.as_bytes(),
+            })
+        };
+
+        let mut r = 0;
+        let mut w = 0;
+        let mut first = true;
+
+        while r <= buf.len() {
+            let end = buf[r..].iter().position(|&b| b == b'&').map_or(buf.len(), |i| r + i);
+            let key = param_key(&buf[r..end]);
+
+            let keep = !matches(self.strip_params, key) &&
+                       self.keep_params.is_none_or(|keep| matches(keep, key));
+
+            if keep {
+                if !first {
+                    buf[w] = b'&';
+                    w += 1;
+                }
+
+                first = false;
+
+                buf.copy_within(r..end, w);
+                w += end - r;
+            }
+
+            r = end + 1;
+        }
+
+        w
+    }
+
+    /// Write the given component of the given target, normalizing percent-encoded
+    /// octets as enabled, and collapsing runs of `/` if `collapse` is set.
+    fn push_escaped(&self, out: &mut Out, s: &str, part: &str, collapse: bool)
+        -> Result<(), ParseError>
+    {
+        let start = offset(s, part);
+        let bytes = part.as_bytes();
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            let len = match bytes[idx..] {
+                [b'%', hi, lo, ..] => match (hex_value(hi), hex_value(lo)) {
+                    (Some(hi), Some(lo)) if self.decode_unreserved &&
+                                            is_unreserved(hi << 4 | lo) =>
+                    {
+                        out.push(&[hi << 4 | lo], start + idx)?;
+                        0
+                    },
+                    (Some(_), Some(_)) if self.uppercase_escapes => {
+                        let escape = [b'%', hi.to_ascii_uppercase(), lo.to_ascii_uppercase()];
+                        out.push(&escape, start + idx)?;
+                        0
+                    },
+                    (Some(_), Some(_)) => 3,
+                    _ => 1,
+                },
+                // Decoding never produces a `/`, so runs can be found in the source.
+                [b'/', ..] if collapse && idx > 0 && bytes[idx - 1] == b'/' => {
+                    idx += 1;
+                    continue;
+                },
+                _ => 1,
+            };
+
+            match len {
+                0 => idx += 3,
+                len => {
+                    out.push(&bytes[idx..idx + len], start + idx)?;
+                    idx += len;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the given authority components of the given target.
+    fn push_authority(&self, out: &mut Out, s: &str, userinfo: Option<&str>, host: &str,
+                      port: Option<&str>)
+        -> Result<(), ParseError>
+    {
+        if let Some(userinfo) = userinfo {
+            let idx = offset(s, userinfo);
+            out.push_str(userinfo, idx)?;
+            out.push(b"@", idx + userinfo.len())?;
+        }
+
+        let host = match host.strip_suffix('.') {
+            Some(stripped) if self.strip_trailing_dot && !stripped.is_empty() => stripped,
+            _ => host,
+        };
+
+        self.push_lower(out, s, host)?;
+
+        if let Some(port) = port {
+            let idx = offset(s, port);
+            out.push(b":", idx - 1)?;
+            out.push_str(port, idx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the given case-insensitive component of the given target, lowercasing it
+    /// if enabled but leaving any percent-encoded octets unchanged.
+    fn push_lower(&self, out: &mut Out, s: &str, part: &str) -> Result<(), ParseError> {
+        let start = offset(s, part);
+
+        if !self.lowercase {
+            return out.push_str(part, start);
+        }
+
+        let bytes = part.as_bytes();
+        let mut escape = 0;
+
+        for (idx, &b) in bytes.iter().enumerate() {
+            let b = match b {
+                b'%' => {
+                    escape = 2;
+                    b
+                },
+                _ if escape > 0 => {
+                    escape -= 1;
+                    b
+                },
+                _ => b.to_ascii_lowercase(),
+            };
+
+            out.push(&[b], start + idx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'s> NormalizeOptions<'s> {
+    /// Normalize the given target like `normalize_into`, available with the `alloc`
+    /// feature.
+    ///
+    /// The target string itself is borrowed if it's already normal, and a target shorter
+    /// than 512 bytes is checked without allocating.
+    ///
+    /// ```rust
+    /// use std::borrow::Cow;
+    /// use uhttp_request_target::{NormalizeOptions, ParsedTarget};
+    ///
+    /// let opts = NormalizeOptions::cache_key();
+    /// let t = ParsedTarget::parse("/a/b?c").unwrap();
+    /// assert!(matches!(opts.normalize(&t), Cow::Borrowed("/a/b?c")));
+    ///
+    /// let t = ParsedTarget::parse("/a/../b?c").unwrap();
+    /// assert!(matches!(opts.normalize(&t), Cow::Owned(ref s) if s == "/b?c"));
+    /// ```
+    pub fn normalize<'a>(&self, target: &ParsedTarget<'a>) -> Cow<'a, str> {
+        to_cow(target.as_str(), |dst| self.normalize_into(target, dst))
+    }
+}
+
+/// Length of the stack buffer used to check if a string is already normal without
+/// allocating.
+#[cfg(feature = "alloc")]
+const STACK_LEN: usize = 512;
+
+/// Apply the given normalization to the given string, borrowing it if unchanged.
+///
+/// The normalization must succeed with a buffer one byte longer than the source.
+#[cfg(feature = "alloc")]
+fn to_cow<F>(src: &str, normalize: F) -> Cow<'_, str>
+    where F: Fn(&mut [u8]) -> Result<usize, ParseError>
+{
+    let mut stack = [0; STACK_LEN];
+    let mut heap;
+
+    let out = match normalize(&mut stack) {
+        Ok(len) => &stack[..len],
+        Err(_) => {
+            heap = vec![0; src.len() + 1];
+            let len = normalize(&mut heap).unwrap_or(0);
+            &heap[..len]
+        },
+    };
+
+    if out == src.as_bytes() {
+        return Cow::Borrowed(src);
+    }
+
+    // Normalization only removes, copies, or decodes to ASCII, so the result is still
+    // UTF-8.
+    Cow::Owned(String::from_utf8_lossy(out).into_owned())
+}
+
+/// Remove the `.` and `..` segments from the given path like `remove_dot_segments_into`,
+/// borrowing it if it has none, available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn remove_dot_segments(path: &str) -> Cow<'_, str> {
+    to_cow(path, |dst| remove_dot_segments_into(path.as_bytes(), dst))
+}
+
+/// Collapse runs of `/` in the given path like `collapse_slashes_into`, borrowing it if it
+/// has none, available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn collapse_slashes(path: &str) -> Cow<'_, str> {
+    to_cow(path, |dst| collapse_slashes_into(path.as_bytes(), dst))
+}
+
+/// Uppercase the escapes in the given target like `uppercase_escapes_into`, borrowing it
+/// if they're already uppercase, available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn uppercase_escapes(s: &str) -> Cow<'_, str> {
+    to_cow(s, |dst| uppercase_escapes_into(s.as_bytes(), dst))
+}
+
+/// Decode the escapes of unreserved characters in the given target like
+/// `decode_unreserved_into`, borrowing it if it has none, available with the `alloc`
+/// feature.
+#[cfg(feature = "alloc")]
+pub fn decode_unreserved(s: &str) -> Cow<'_, str> {
+    to_cow(s, |dst| decode_unreserved_into(s.as_bytes(), dst))
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Write the canonical form of the target, as normalized by
+    /// `NormalizeOptions::cache_key`, into the given buffer, returning the number of bytes
+    /// written.
+    ///
+    /// A buffer one byte longer than the target is always large enough.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::ParsedTarget;
+    ///
+    /// let t = ParsedTarget::parse("HTTP://Example.COM:80/a/./b/../%7ec%2f").unwrap();
+    /// let mut buf = [0; 64];
+    /// let len = t.canonicalize_into(&mut buf).unwrap();
+    /// assert_eq!(&buf[..len], b"http://example.com/a/~c%2F");
+    /// ```
+    pub fn canonicalize_into(&self, dst: &mut [u8]) -> Result<usize, ParseError> {
+        NormalizeOptions::cache_key().normalize_into(self, dst)
+    }
+
+    /// Canonicalize the target like `canonicalize_into`, borrowing the target string if
+    /// it's already canonical, available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn canonicalize(&self) -> Cow<'a, str> {
+        NormalizeOptions::cache_key().normalize(self)
+    }
+}
+
+impl<'s> Default for NormalizeOptions<'s> {
+    fn default() -> Self { NormalizeOptions::new() }
+}
+
+/// Output buffer that reports overflow at the source offset being written.
+struct Out<'s, 'd> {
+    src: &'s [u8],
+    dst: &'d mut [u8],
+    len: usize,
+}
+
+impl<'s, 'd> Out<'s, 'd> {
+    fn new(src: &'s [u8], dst: &'d mut [u8]) -> Self {
+        Out { src, dst, len: 0 }
+    }
+
+    /// Append the given bytes, which originate at the given source offset.
+    fn push(&mut self, bytes: &[u8], idx: usize) -> Result<(), ParseError> {
+        match self.dst.get_mut(self.len..self.len + bytes.len()) {
+            Some(slots) => slots.copy_from_slice(bytes),
+            None => return Err(ParseError::at(ErrorKind::TooLong, self.src, idx)),
+        }
+
+        self.len += bytes.len();
+
+        Ok(())
+    }
+
+    /// Append the given string, which originates at the given source offset.
+    fn push_str(&mut self, s: &str, idx: usize) -> Result<(), ParseError> {
+        self.push(s.as_bytes(), idx)
+    }
+
+    /// Remove the last segment and its preceding `/`, if any, from the output.
+    fn pop_segment(&mut self) {
+        self.len = self.dst[..self.len].iter().rposition(|&b| b == b'/').unwrap_or(0);
+    }
+}
+
+/// Remove the `.` and `..` segments from the given path into the given buffer, returning
+/// the number of bytes written [RFC3986§5.2.4].
+///
+/// This resolves a path like `/a/./b/../c` to `/a/c`, and a `..` segment never climbs
+/// above the root, so the result can be safely mapped onto a directory or matched
+/// against route prefixes. Only literal dots are recognized, so percent-encoded ones
+/// should be decoded first. Since removal never lengthens the path, a buffer the size of
+/// the source is always large enough, and `ErrorKind::TooLong` is otherwise returned at
+/// the offset of the source byte that didn't fit.
+///
+/// ```rust
+/// use uhttp_request_target::remove_dot_segments_into;
+///
+/// let mut buf = [0; 16];
+/// let len = remove_dot_segments_into(b"/a/./b/../c", &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"/a/c");
+/// ```
+pub fn remove_dot_segments_into(src: &[u8], dst: &mut [u8]) -> Result<usize, ParseError> {
+    let mut out = Out::new(src, dst);
+    let mut idx = 0;
+
+    while idx < src.len() {
+        match &src[idx..] {
+            [b'.', b'.', b'/', ..] => idx += 3,
+            [b'.', b'/', ..] => idx += 2,
+            // These leave the final `/` as the start of the remaining input.
+            [b'/', b'.', b'/', ..] => idx += 2,
+            [b'/', b'.', b'.', b'/', ..] => {
+                out.pop_segment();
+                idx += 3;
+            },
+            b"/." => {
+                out.push(b"/", idx)?;
+                idx += 2;
+            },
+            b"/.." => {
+                out.pop_segment();
+                out.push(b"/", idx)?;
+                idx += 3;
+            },
+            b"." | b".." => idx = src.len(),
+            rest => {
+                let end = rest.iter().skip(1).position(|&b| b == b'/')
+                    .map_or(rest.len(), |i| i + 1);
+                out.push(&rest[..end], idx)?;
+                idx += end;
+            },
+        }
+    }
+
+    Ok(out.len)
+}
+
+/// Remove the `.` and `..` segments from the path in the given buffer like
+/// `remove_dot_segments_into`, returning the length of the result at the start of the
+/// buffer.
+///
+/// The result is never longer than the input consumed so far, so it can be written over
+/// the input as it's read.
+pub(crate) fn remove_dot_segments_in_place(buf: &mut [u8]) -> usize {
+    let pop = |buf: &[u8], w: usize| buf[..w].iter().rposition(|&b| b == b'/').unwrap_or(0);
+    let mut r = 0;
+    let mut w = 0;
+
+    while r < buf.len() {
+        match buf[r..] {
+            [b'.', b'.', b'/', ..] => r += 3,
+            [b'.', b'/', ..] => r += 2,
+            [b'/', b'.', b'/', ..] => r += 2,
+            [b'/', b'.', b'.', b'/', ..] => {
+                w = pop(buf, w);
+                r += 3;
+            },
+            [b'/', b'.'] => {
+                buf[w] = b'/';
+                w += 1;
+                r += 2;
+            },
+            [b'/', b'.', b'.'] => {
+                w = pop(buf, w);
+                buf[w] = b'/';
+                w += 1;
+                r += 3;
+            },
+            [b'.'] | [b'.', b'.'] => r = buf.len(),
+            _ => {
+                let len = buf[r + 1..].iter().position(|&b| b == b'/')
+                    .map_or(buf.len() - r, |i| i + 1);
+                buf.copy_within(r..r + len, w);
+                w += len;
+                r += len;
+            },
+        }
+    }
+
+    w
+}
+
+/// Collapse runs of `/` in the path in the given buffer like `collapse_slashes_into`,
+/// returning the length of the result at the start of the buffer.
+pub(crate) fn collapse_slashes_in_place(buf: &mut [u8]) -> usize {
+    let mut w = 0;
+
+    for r in 0..buf.len() {
+        if buf[r] == b'/' && w > 0 && buf[w - 1] == b'/' {
+            continue;
+        }
+
+        buf[w] = buf[r];
+        w += 1;
+    }
+
+    w
+}
+
+/// Stably sort the `&`-separated parameters in the given query by key.
+///
+/// This is an insertion sort that rotates each parameter into place, so it needs no
+/// allocation and is fast for the handful of parameters in a typical query.
+fn sort_params_in_place(buf: &mut [u8]) {
+    let param_end = |buf: &[u8], from: usize, to: usize| {
+        buf[from..to].iter().position(|&b| b == b'&').map_or(to, |i| from + i)
+    };
+
+    let mut start = 0;
+
+    while start < buf.len() {
+        let end = param_end(buf, start, buf.len());
+
+        // Find the first earlier parameter with a greater key.
+        let mut pos = 0;
+
+        while pos < start {
+            let next = param_end(buf, pos, start);
+
+            if param_key(&buf[pos..next]) > param_key(&buf[start..end]) {
+                break;
+            }
+
+            pos = next + 1;
+        }
+
+        if pos < start {
+            // Move the parameter and the `&` before it to the front, then swap them.
+            buf[pos..end].rotate_right(end - start + 1);
+            buf[pos..pos + end - start + 1].rotate_left(1);
+        }
+
+        start = end + 1;
+    }
+}
+
+/// Retrieve the key of the given query parameter, the part before any `=`.
+fn param_key(param: &[u8]) -> &[u8] {
+    &param[..param.iter().position(|&b| b == b'=').unwrap_or(param.len())]
+}
+
+/// Collapse each run of consecutive `/` in the given path into a single one, writing the
+/// result into the given buffer and returning the number of bytes written.
+///
+/// Empty segments are significant to URI equivalence, so `/a//b` and `/a/b` are different
+/// resources in general, but many routers want to treat them the same. A buffer the size
+/// of the source is always large enough, and `ErrorKind::TooLong` is otherwise returned
+/// at the offset of the source byte that didn't fit. Encoded slashes, `%2F`, are left
+/// alone.
+///
+/// ```rust
+/// use uhttp_request_target::collapse_slashes_into;
+///
+/// let mut buf = [0; 16];
+/// let len = collapse_slashes_into(b"//a///b/", &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"/a/b/");
+/// ```
+pub fn collapse_slashes_into(src: &[u8], dst: &mut [u8]) -> Result<usize, ParseError> {
+    let mut out = Out::new(src, dst);
+
+    for (idx, &b) in src.iter().enumerate() {
+        if b == b'/' && idx > 0 && src[idx - 1] == b'/' {
+            continue;
+        }
+
+        out.push(&[b], idx)?;
+    }
+
+    Ok(out.len)
+}
+
+/// Uppercase the hex digits of each percent-encoded octet in the given target, writing
+/// the result into the given buffer and returning the number of bytes written.
+///
+/// Escapes are equivalent regardless of case, but uppercase is the normal form
+/// [RFC3986§6.2.2.1], so `/a%2fb` and `/a%2Fb` become the same after this. Other bytes,
+/// including a `%` not followed by two hex digits, are copied unchanged. A buffer the size
+/// of the source is always large enough, and `ErrorKind::TooLong` is otherwise returned
+/// at the offset of the source byte that didn't fit.
+///
+/// ```rust
+/// use uhttp_request_target::uppercase_escapes_into;
+///
+/// let mut buf = [0; 16];
+/// let len = uppercase_escapes_into(b"/a%2fb?c=%e2%9C", &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"/a%2Fb?c=%E2%9C");
+/// ```
+pub fn uppercase_escapes_into(src: &[u8], dst: &mut [u8]) -> Result<usize, ParseError> {
+    let mut out = Out::new(src, dst);
+    let mut idx = 0;
+
+    while idx < src.len() {
+        match src[idx..] {
+            [b'%', hi, lo, ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                out.push(&[b'%', hi.to_ascii_uppercase(), lo.to_ascii_uppercase()], idx)?;
+                idx += 3;
+            },
+            _ => {
+                out.push(&src[idx..idx + 1], idx)?;
+                idx += 1;
+            },
+        }
+    }
+
+    Ok(out.len)
+}
+
+/// Decode each percent-encoded octet of an unreserved character in the given target,
+/// writing the result into the given buffer and returning the number of bytes written.
+///
+/// Escapes of unreserved characters, such as `%7E` for `~`, are equivalent to the
+/// characters themselves and should be decoded for comparison [RFC3986§6.2.2.2]. Escapes
+/// of reserved characters, such as `%2F` for `/`, change the meaning of the target and
+/// are left encoded, along with everything else. A buffer the size of the source is
+/// always large enough, and `ErrorKind::TooLong` is otherwise returned at the offset of
+/// the source byte that didn't fit.
+///
+/// ```rust
+/// use uhttp_request_target::decode_unreserved_into;
+///
+/// let mut buf = [0; 16];
+/// let len = decode_unreserved_into(b"/%7Euser/a%2Fb", &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"/~user/a%2Fb");
+/// ```
+pub fn decode_unreserved_into(src: &[u8], dst: &mut [u8]) -> Result<usize, ParseError> {
+    let mut out = Out::new(src, dst);
+    let mut idx = 0;
+
+    while idx < src.len() {
+        let len = match src[idx..] {
+            [b'%', hi, lo, ..] => match (hex_value(hi), hex_value(lo)) {
+                (Some(hi), Some(lo)) if is_unreserved(hi << 4 | lo) => {
+                    out.push(&[hi << 4 | lo], idx)?;
+                    idx += 3;
+                    continue;
+                },
+                (Some(_), Some(_)) => 3,
+                _ => 1,
+            },
+            _ => 1,
+        };
+
+        out.push(&src[idx..idx + len], idx)?;
+        idx += len;
+    }
+
+    Ok(out.len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dots(s: &str) -> String {
+        let mut buf = [0; 64];
+        let len = remove_dot_segments_into(s.as_bytes(), &mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    fn collapse(s: &str) -> String {
+        let mut buf = [0; 64];
+        let len = collapse_slashes_into(s.as_bytes(), &mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    fn normalize(s: &str, opts: NormalizeOptions) -> String {
+        let mut buf = [0; 64];
+        let t = ParsedTarget::parse(s).unwrap();
+        let len = opts.normalize_into(&t, &mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_into() {
+        assert_eq!(NormalizeOptions::default(), NormalizeOptions::new());
+
+        let opts = NormalizeOptions::new();
+        assert_eq!(normalize("/A/b?C", opts), "/A/b?C");
+        assert_eq!(normalize("HTTP://A:80/B", opts), "HTTP://A:80/B");
+        assert_eq!(normalize("*", opts), "*");
+
+        let opts = opts.lowercase(true);
+        assert_eq!(normalize("/A/b?C", opts), "/A/b?C");
+        assert_eq!(normalize("HTTP://EXAMPLE.com/", opts), "http://example.com/");
+        assert_eq!(normalize("http://example.com/", opts), "http://example.com/");
+        assert_eq!(normalize("hTTps://U:P@Ex.COM:8443/P?Q#F", opts),
+            "https://U:P@ex.com:8443/P?Q#F");
+        assert_eq!(normalize("http://A%C3%A9B?Q", opts), "http://a%C3%A9b?Q");
+        assert_eq!(normalize("http://[::FFFF:A]:80", opts), "http://[::ffff:a]:80");
+        assert_eq!(normalize("EXAMPLE.COM:443", opts), "example.com:443");
+        assert_eq!(normalize("U@EXAMPLE.COM:", opts), "U@example.com:");
+
+        assert_eq!(Scheme::Ws.default_port(), 80);
+        assert_eq!(Scheme::Wss.default_port(), 443);
+
+        let opts = NormalizeOptions::new().elide_default_port(Scheme::Http, true)
+            .elide_default_port(Scheme::Https, true);
+        assert_eq!(normalize("http://a:80/b", opts), "http://a/b");
+        assert_eq!(normalize("HTTP://a:080?b", opts), "HTTP://a?b");
+        assert_eq!(normalize("http://a:/b", opts), "http://a/b");
+        assert_eq!(normalize("http://u@a:80", opts), "http://u@a");
+        assert_eq!(normalize("http://a:443/b", opts), "http://a:443/b");
+        assert_eq!(normalize("https://a:443/b", opts), "https://a/b");
+        assert_eq!(normalize("https://[::1]:443/b", opts), "https://[::1]/b");
+        assert_eq!(normalize("https://a:80/b", opts), "https://a:80/b");
+        assert_eq!(normalize("a:443", opts), "a:443");
+        let opts = opts.elide_default_port(Scheme::Https, false);
+        assert_eq!(normalize("http://a:80/b", opts), "http://a/b");
+        assert_eq!(normalize("https://a:443/b", opts), "https://a:443/b");
+
+        let opts = NormalizeOptions::new().root_empty_path(true);
+        assert_eq!(normalize("http://a", opts), "http://a/");
+        assert_eq!(normalize("http://a/", opts), "http://a/");
+        assert_eq!(normalize("http://a?b", opts), "http://a/?b");
+        assert_eq!(normalize("http://a/b", opts), "http://a/b");
+        assert_eq!(normalize("a:80", opts), "a:80");
+        assert_eq!(normalize("/?a", opts), "/?a");
+
+        let opts = NormalizeOptions::new().strip_trailing_dot(true);
+        assert_eq!(normalize("http://example.com./a", opts), "http://example.com/a");
+        assert_eq!(normalize("http://example.com.:80", opts), "http://example.com:80");
+        assert_eq!(normalize("http://example.com../", opts), "http://example.com./");
+        assert_eq!(normalize("http://example.com/a.", opts), "http://example.com/a.");
+        assert_eq!(normalize("http://./", opts), "http://./");
+        assert_eq!(normalize("example.com.:443", opts), "example.com:443");
+        assert_eq!(normalize("u.@a.", opts), "u.@a");
+
+        let opts = NormalizeOptions::new().uppercase_escapes(true);
+        assert_eq!(normalize("/a%2fb%7e?c=%e9#%aa", opts), "/a%2Fb%7E?c=%E9#%AA");
+        assert_eq!(normalize("http://%aa/%aa", opts), "http://%aa/%AA");
+        let opts = NormalizeOptions::new().decode_unreserved(true);
+        assert_eq!(normalize("/%7e%2f?%41=%3d", opts), "/~%2f?A=%3d");
+        let opts = opts.uppercase_escapes(true);
+        assert_eq!(normalize("/%7e%2f?%41=%3d", opts), "/~%2F?A=%3D");
+
+        let opts = NormalizeOptions::new().remove_dot_segments(true);
+        assert_eq!(normalize("/a/./b/../c?d=/../e", opts), "/a/c?d=/../e");
+        assert_eq!(normalize("/a/b/..", opts), "/a/");
+        assert_eq!(normalize("/../../a/.", opts), "/a/");
+        assert_eq!(normalize("/a/%2e%2E/b", opts), "/a/%2e%2E/b");
+        assert_eq!(normalize("http://a/b/../c", opts), "http://a/c");
+        assert_eq!(normalize("http://a/..", opts), "http://a/");
+        assert_eq!(normalize("/a/%2e%2E/b", opts.decode_unreserved(true)), "/b");
+        assert_eq!(normalize("/a/b#/../c", opts), "/a/b#/../c");
+
+        let opts = NormalizeOptions::new().collapse_slashes(true);
+        assert_eq!(normalize("//a///b/?c//d", opts), "/a/b/?c//d");
+        assert_eq!(normalize("http://a//b", opts), "http://a/b");
+        assert_eq!(normalize("/a//../b", opts), "/a/../b");
+        assert_eq!(normalize("/a//../b", opts.remove_dot_segments(true)), "/b");
+
+        let opts = NormalizeOptions::new().sort_query(true);
+        assert_eq!(normalize("/?c=1&a=2&b=3", opts), "/?a=2&b=3&c=1");
+        assert_eq!(normalize("/?b=1&a=2&b=0&a=1", opts), "/?a=2&a=1&b=1&b=0");
+        assert_eq!(normalize("/?b&a=&&c", opts), "/?&a=&b&c");
+        assert_eq!(normalize("/?ab=1&a=2&a", opts), "/?a=2&a&ab=1");
+        assert_eq!(normalize("/?z=1#b&a", opts), "/?z=1#b&a");
+        assert_eq!(normalize("/a#?b&a", opts), "/a#?b&a");
+        assert_eq!(normalize("/?", opts), "/?");
+        assert_eq!(normalize("http://x?b=%62&a", opts), "http://x?a&b=%62");
+        assert_eq!(normalize("http://x/?%62=1&a", opts.decode_unreserved(true)),
+            "http://x/?a&b=1");
+
+        let opts = NormalizeOptions::new().strip_params(&["utm_*", "fbclid"]);
+        assert_eq!(normalize("/a?utm_source=x&b=1&fbclid=2&utm_=3", opts), "/a?b=1");
+        assert_eq!(normalize("/a?b=1&fbclid", opts), "/a?b=1");
+        assert_eq!(normalize("/a?fbclid=1&utm_x#f", opts), "/a#f");
+        assert_eq!(normalize("/a?fbclidx=1&utm", opts), "/a?fbclidx=1&utm");
+        assert_eq!(normalize("/a?", opts), "/a?");
+        assert_eq!(normalize("/a?&b&", opts), "/a?&b&");
+        assert_eq!(normalize("/a", opts), "/a");
+        assert_eq!(normalize("http://x?%66bclid=1&a", opts.decode_unreserved(true)),
+            "http://x?a");
+        assert_eq!(normalize("/?c&fbclid&b&a", opts.sort_query(true)), "/?a&b&c");
+
+        let opts = NormalizeOptions::new().keep_params(&["id", "page*"]);
+        assert_eq!(normalize("/a?x=1&id=2&page_size=3&y", opts), "/a?id=2&page_size=3");
+        assert_eq!(normalize("/a?x=1", opts), "/a");
+        assert_eq!(normalize("/a?", opts), "/a?");
+        let opts = opts.strip_params(&["page_size"]);
+        assert_eq!(normalize("/a?id=2&page_size=3&page=4", opts), "/a?id=2&page=4");
+
+        let config = String::from("id,sid");
+        let names: Vec<&str> = config.split(',').collect();
+        let keep = NormalizeOptions::new().keep_params(&names);
+        assert_eq!(normalize("/a?x=1&sid=2&id=3", keep), "/a?sid=2&id=3");
+        assert_eq!(normalize("/a?x=1&sid=2", keep.strip_params(&names[1..])), "/a");
+
+        let t = ParsedTarget::parse("HTTP://A/B").unwrap();
+        let e = opts.normalize_into(&t, &mut [0; 8]).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 8));
+    }
+
+    #[test]
+    fn test_canonicalize_into() {
+        let canon = |s| {
+            let mut buf = [0; 64];
+            let t = ParsedTarget::parse(s).unwrap();
+            let len = t.canonicalize_into(&mut buf).unwrap();
+            String::from_utf8(buf[..len].to_vec()).unwrap()
+        };
+
+        assert_eq!(canon("/a/b?c=d"), "/a/b?c=d");
+        assert_eq!(canon("/a/./b/../%63%7e%2f?%41"), "/a/c~%2F?A");
+        assert_eq!(canon("HTTP://EXAMPLE.com"), "http://example.com/");
+        assert_eq!(canon("http://example.com:80/"), "http://example.com/");
+        assert_eq!(canon("https://example.com:443?a"), "https://example.com/?a");
+        assert_eq!(canon("https://example.com:80/"), "https://example.com:80/");
+        assert_eq!(canon("http://example.com.//a"), "http://example.com.//a");
+        assert_eq!(canon("Example.COM:443"), "example.com:443");
+        assert_eq!(canon("*"), "*");
+        assert_eq!(canon("http://A#F"), "http://a/#F");
+        assert_eq!(canon("http://A:80?Q#F"), "http://a/?Q#F");
+
+        for s in &["HTTP://A:80", "http://a/", "http://a:/./", "http://a/%2e"] {
+            assert_eq!(canon(s), canon("http://a"), "{}", s);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_canonicalize() {
+        let t = ParsedTarget::parse("HTTP://A:80/b/../%7e").unwrap();
+        assert_eq!(t.canonicalize(), "http://a/~");
+
+        let opts = NormalizeOptions::new().lowercase(true);
+        assert_eq!(opts.normalize(&t), "http://a:80/b/../%7e");
+
+        let t = ParsedTarget::parse("http://a/b?c").unwrap();
+        assert!(matches!(t.canonicalize(), Cow::Borrowed("http://a/b?c")));
+        let t = ParsedTarget::parse("http://a").unwrap();
+        assert!(matches!(t.canonicalize(), Cow::Owned(ref s) if s == "http://a/"));
+
+        let mut long = String::from("/");
+        for _ in 0..STACK_LEN {
+            long.push_str("a/");
+        }
+        let t = ParsedTarget::parse(&long).unwrap();
+        assert!(matches!(t.canonicalize(), Cow::Borrowed(s) if s == long));
+        long.push_str("./");
+        let t = ParsedTarget::parse(&long).unwrap();
+        assert_eq!(t.canonicalize(), &long[..long.len() - 2]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_cow_normalizers() {
+        assert!(matches!(remove_dot_segments("/a/b"), Cow::Borrowed("/a/b")));
+        assert_eq!(remove_dot_segments("/a/./b/.."), "/a/");
+        assert!(matches!(collapse_slashes("/a/b/"), Cow::Borrowed("/a/b/")));
+        assert_eq!(collapse_slashes("//a//b"), "/a/b");
+        assert!(matches!(uppercase_escapes("/%2F%zz"), Cow::Borrowed("/%2F%zz")));
+        assert_eq!(uppercase_escapes("/%2f"), "/%2F");
+        assert!(matches!(decode_unreserved("/%2F~"), Cow::Borrowed("/%2F~")));
+        assert_eq!(decode_unreserved("/%7e%C3%A9"), "/~%C3%A9");
+        assert!(matches!(decode_unreserved(""), Cow::Borrowed("")));
+    }
+
+    fn upper(s: &str) -> String {
+        let mut buf = [0; 64];
+        let len = uppercase_escapes_into(s.as_bytes(), &mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_uppercase_escapes_into() {
+        assert_eq!(upper(""), "");
+        assert_eq!(upper("/abc/def"), "/abc/def");
+        assert_eq!(upper("/a%2fb%2F"), "/a%2Fb%2F");
+        assert_eq!(upper("%aa%Bb%cC"), "%AA%BB%CC");
+        assert_eq!(upper("/%zz%a%"), "/%zz%a%");
+        assert_eq!(upper("%%ab"), "%%AB");
+        assert_eq!(upper("/ab?c=%ef"), "/ab?c=%EF");
+
+        let mut buf = [0; 4];
+        let e = uppercase_escapes_into(b"/a%2f", &mut buf).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 2));
+    }
+
+    fn unreserved(s: &str) -> String {
+        let mut buf = [0; 64];
+        let len = decode_unreserved_into(s.as_bytes(), &mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_decode_unreserved_into() {
+        assert_eq!(unreserved(""), "");
+        assert_eq!(unreserved("/a/b"), "/a/b");
+        assert_eq!(unreserved("/%7e%7E"), "/~~");
+        assert_eq!(unreserved("/%41%62%30%2D%2e%5F"), "/Ab0-._");
+        assert_eq!(unreserved("/%2F%2f%3F%25%20"), "/%2F%2f%3F%25%20");
+        assert_eq!(unreserved("/%C3%A9"), "/%C3%A9");
+        assert_eq!(unreserved("/%2541"), "/%2541");
+        assert_eq!(unreserved("/%zz%4"), "/%zz%4");
+        assert_eq!(unreserved("/a?b=%61"), "/a?b=a");
+
+        let mut buf = [0; 2];
+        assert_eq!(decode_unreserved_into(b"/%41", &mut buf), Ok(2));
+        let e = decode_unreserved_into(b"/%2F", &mut buf).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 1));
+    }
+
+    #[test]
+    fn test_collapse_slashes_into() {
+        assert_eq!(collapse(""), "");
+        assert_eq!(collapse("/"), "/");
+        assert_eq!(collapse("//"), "/");
+        assert_eq!(collapse("/a/b/"), "/a/b/");
+        assert_eq!(collapse("/a//b"), "/a/b");
+        assert_eq!(collapse("///a////b//"), "/a/b/");
+        assert_eq!(collapse("/a/%2F/b"), "/a/%2F/b");
+        assert_eq!(collapse("/a/%2F%2F/b"), "/a/%2F%2F/b");
+
+        let mut buf = [0; 3];
+        assert_eq!(collapse_slashes_into(b"//a//", &mut buf), Ok(3));
+        let e = collapse_slashes_into(b"/a/b", &mut buf).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 3));
+    }
+
+    #[test]
+    fn test_remove_dot_segments_into() {
+        assert_eq!(dots(""), "");
+        assert_eq!(dots("/"), "/");
+        assert_eq!(dots("/a/b/c"), "/a/b/c");
+        assert_eq!(dots("/a/./b/../c"), "/a/c");
+        assert_eq!(dots("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(dots("mid/content=5/../6"), "mid/6");
+        assert_eq!(dots("/.."), "/");
+        assert_eq!(dots("/../../a"), "/a");
+        assert_eq!(dots("/a/.."), "/");
+        assert_eq!(dots("/a/b/.."), "/a/");
+        assert_eq!(dots("/a/."), "/a/");
+        assert_eq!(dots("/a/./"), "/a/");
+        assert_eq!(dots("/./a"), "/a");
+        assert_eq!(dots("/a//../b"), "/a/b");
+        assert_eq!(dots("/a/.../b"), "/a/.../b");
+        assert_eq!(dots("/a/..b/.c"), "/a/..b/.c");
+        assert_eq!(dots("/a/%2e%2e/b"), "/a/%2e%2e/b");
+        assert_eq!(dots("../a"), "a");
+        assert_eq!(dots("./a"), "a");
+        assert_eq!(dots("."), "");
+        assert_eq!(dots(".."), "");
+
+        let mut buf = [0; 4];
+        assert_eq!(remove_dot_segments_into(b"/a/../bcde", &mut buf).map_err(|e| e.kind()),
+            Err(ErrorKind::TooLong));
+        assert_eq!(remove_dot_segments_into(b"/a/../bcd", &mut buf), Ok(4));
+        assert_eq!(&buf, b"/bcd");
+        let e = remove_dot_segments_into(b"/ab/cd", &mut buf).unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::TooLong, 3));
+    }
+}
@@ -0,0 +1,557 @@
+//! Matching of paths against OpenAPI path templates, available with the `alloc` feature.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use {ErrorKind, ParsedTarget, ParseError};
+use decode::{decodes_to, percent_decode_str, InvalidEscapePolicy};
+use form::offset;
+use path::path_segments;
+use route::Captures;
+
+/// Path template of an OpenAPI document, such as `/pets/{petId}` or
+/// `/reports/{year}-{month}.csv`.
+///
+/// Unlike a `RoutePattern`, a segment may mix literal text with any number of `{name}`
+/// variables, as in the path templating of the OpenAPI specification. Each variable
+/// matches a non-empty run of characters within the decoded segment, up to the first
+/// occurrence of the literal text that follows it, so a variable never spans a `/`.
+/// Literal text is compared to the decoded segment, the path must have exactly as many
+/// segments as the template, and, as with `path_segments`, the path ends at any `#`
+/// fragment.
+///
+/// ```rust
+/// use uhttp_request_target::PathTemplate;
+///
+/// let t = PathTemplate::parse("/reports/{year}-{month}.{format}").unwrap();
+/// let caps = t.captures("/reports/2024-06.csv").unwrap().unwrap();
+///
+/// assert_eq!(caps.get("year"), Some("2024"));
+/// assert_eq!(caps.get("month"), Some("06"));
+/// assert_eq!(caps.get("format"), Some("csv"));
+/// assert!(!t.is_match("/reports/2024.csv"));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PathTemplate<'t> {
+    template: &'t str,
+}
+
+impl<'t> PathTemplate<'t> {
+    /// Parse the given path template.
+    ///
+    /// The template must begin with `/` and may not contain a `?` or `#`. Each `{` must
+    /// open a variable with a non-empty name that's closed by `}` and distinct from every
+    /// other variable in the template, and two variables may not be adjacent, since the
+    /// split between them would be ambiguous. Errors are `ErrorKind::InvalidPattern` at
+    /// the offending offset in the template.
+    pub fn parse(template: &'t str) -> Result<Self, ParseError> {
+        validate(template)?;
+        Ok(PathTemplate { template })
+    }
+
+    /// Retrieve the template string.
+    pub fn as_str(&self) -> &'t str { self.template }
+
+    /// Check if the given path matches the template with variables that decode to
+    /// UTF-8.
+    pub fn is_match(&self, path: &str) -> bool {
+        matches!(self.captures(path), Some(Ok(_)))
+    }
+
+    /// Match the given path against the template, returning the decoded value of each
+    /// variable in template order.
+    ///
+    /// This returns `None` if the path doesn't match. A segment with variables that
+    /// doesn't decode to UTF-8 gives an error at its offset in the path.
+    pub fn captures<'a>(&self, path: &'a str)
+        -> Option<Result<Captures<'t, 'a>, ParseError>>
+    {
+        match_template(self.template, path)
+    }
+}
+
+/// Set of OpenAPI path templates mapped to values, such as operations.
+///
+/// A lookup tries the templates with the path's number of segments in order of
+/// specificity, so, as the OpenAPI specification requires, concrete paths are matched
+/// before templated ones: with `/pets/mine` and `/pets/{petId}` both inserted,
+/// `/pets/mine` matches the first. Templates are compared segment by segment from the
+/// left, where a segment without variables is more specific than one with them, and
+/// templates that are equally specific are tried in order of insertion.
+///
+/// ```rust
+/// use uhttp_request_target::{ParsedTarget, PathTemplates};
+///
+/// let mut paths = PathTemplates::new();
+/// paths.insert("/pets/{petId}", "getPetById").unwrap();
+/// paths.insert("/pets/mine", "getMyPets").unwrap();
+///
+/// let m = paths.lookup("/pets/42").unwrap().unwrap();
+/// assert_eq!(*m.value(), "getPetById");
+/// assert_eq!(m.captures().get("petId"), Some("42"));
+///
+/// let t = ParsedTarget::parse("http://example.com/pets/mine?limit=10").unwrap();
+/// assert_eq!(*paths.route(&t).unwrap().unwrap().value(), "getMyPets");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathTemplates<T> {
+    /// Templates by number of segments, each in order of specificity.
+    by_len: BTreeMap<usize, Vec<Entry<T>>>,
+    len: usize,
+}
+
+/// Template in a set.
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    template: String,
+    value: T,
+}
+
+impl<T> PathTemplates<T> {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        PathTemplates {
+            by_len: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Retrieve the number of templates.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Check if there are no templates.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Insert the given template with the given value.
+    ///
+    /// The template is parsed like `PathTemplate::parse`, and errors are returned in the
+    /// same way. A template that differs from an existing one only in the names of its
+    /// variables, which OpenAPI considers identical, replaces it and returns its value.
+    pub fn insert(&mut self, template: &str, value: T) -> Result<Option<T>, ParseError> {
+        validate(template)?;
+
+        let entries = self.by_len.entry(path_segments(template).count()).or_default();
+        let entry = Entry { template: template.to_string(), value };
+
+        if let Some(prev) = entries.iter_mut().find(|e| same_shape(&e.template, template)) {
+            return Ok(Some(core::mem::replace(prev, entry).value));
+        }
+
+        let idx = entries.iter()
+            .position(|e| specificity(template).lt(specificity(&e.template)))
+            .unwrap_or(entries.len());
+
+        entries.insert(idx, entry);
+        self.len += 1;
+
+        Ok(None)
+    }
+
+    /// Match the given path against the templates like `PathTemplate::captures`,
+    /// returning the most specific match.
+    pub fn lookup<'r, 'a>(&'r self, path: &'a str)
+        -> Option<Result<TemplateMatch<'r, 'a, T>, ParseError>>
+    {
+        let entries = self.by_len.get(&path_segments(path).count())?;
+
+        entries.iter().find_map(|e| {
+            match_template(&e.template, path).map(|caps| caps.map(|captures| TemplateMatch {
+                template: &e.template,
+                value: &e.value,
+                captures,
+            }))
+        })
+    }
+
+    /// Match the path of the given target like `lookup`.
+    ///
+    /// An empty absolute-form path is treated like `/`, and authority-form and
+    /// asterisk-form targets, which have no path, never match.
+    pub fn route<'r, 'a>(&'r self, target: &ParsedTarget<'a>)
+        -> Option<Result<TemplateMatch<'r, 'a, T>, ParseError>>
+    {
+        match *target {
+            ParsedTarget::AbsPath(ref form) => self.lookup(form.path()),
+            ParsedTarget::AbsUri(ref form) => self.lookup(form.path_or_root()),
+            ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => None,
+        }
+    }
+}
+
+impl<T> Default for PathTemplates<T> {
+    fn default() -> Self { PathTemplates::new() }
+}
+
+/// Template matched by `PathTemplates::lookup`.
+#[derive(Clone, Debug)]
+pub struct TemplateMatch<'r, 'a, T> {
+    template: &'r str,
+    value: &'r T,
+    captures: Captures<'r, 'a>,
+}
+
+impl<'r, 'a, T> TemplateMatch<'r, 'a, T> {
+    /// Retrieve the matched template, as it was inserted.
+    pub fn template(&self) -> &'r str { self.template }
+
+    /// Retrieve the value of the matched template.
+    pub fn value(&self) -> &'r T { self.value }
+
+    /// Retrieve the decoded values of the variables, named by the matched template.
+    pub fn captures(&self) -> &Captures<'r, 'a> { &self.captures }
+
+    /// Consume the match, returning the decoded values of the variables.
+    pub fn into_captures(self) -> Captures<'r, 'a> { self.captures }
+}
+
+/// Part of a template segment.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Piece<'t> {
+    Literal(&'t str),
+    Variable(&'t str),
+}
+
+/// Iterator over the parts of a segment of a valid template.
+#[derive(Copy, Clone)]
+struct Pieces<'t>(&'t str);
+
+impl<'t> Iterator for Pieces<'t> {
+    type Item = Piece<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let (piece, rest) = match self.0.strip_prefix('{') {
+            Some(var) => {
+                let end = var.find('}').unwrap_or(var.len());
+                (Piece::Variable(&var[..end]), var.get(end + 1..).unwrap_or(""))
+            },
+            None => {
+                let end = self.0.find('{').unwrap_or(self.0.len());
+                (Piece::Literal(&self.0[..end]), &self.0[end..])
+            },
+        };
+
+        self.0 = rest;
+        Some(piece)
+    }
+}
+
+/// Check the given template for syntax errors.
+fn validate(template: &str) -> Result<(), ParseError> {
+    let invalid = |idx| {
+        ParseError::at(ErrorKind::InvalidPattern, template.as_bytes(), idx)
+    };
+
+    if !template.starts_with('/') {
+        return Err(invalid(0));
+    }
+
+    if let Some(idx) = template.find(['?', '#']) {
+        return Err(invalid(idx));
+    }
+
+    let mut names = Vec::new();
+
+    for seg in path_segments(template) {
+        let start = offset(template, seg);
+        let mut after_var = false;
+        let mut idx = 0;
+
+        while idx < seg.len() {
+            match seg.as_bytes()[idx] {
+                b'{' if after_var => return Err(invalid(start + idx)),
+                b'{' => {
+                    let name = &seg[idx + 1..];
+                    let end = match name.find(['{', '}']) {
+                        Some(end) if end > 0 && name.as_bytes()[end] == b'}' => end,
+                        Some(end) => return Err(invalid(start + idx + 1 + end)),
+                        None => return Err(invalid(start + seg.len())),
+                    };
+
+                    if names.contains(&&name[..end]) {
+                        return Err(invalid(start + idx));
+                    }
+
+                    names.push(&name[..end]);
+                    after_var = true;
+                    idx += end + 2;
+                },
+                b'}' => return Err(invalid(start + idx)),
+                _ => {
+                    after_var = false;
+                    idx += 1;
+                },
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Match the given path against the given valid template.
+fn match_template<'t, 'a>(template: &'t str, path: &'a str)
+    -> Option<Result<Captures<'t, 'a>, ParseError>>
+{
+    let pairs = || path_segments(template).zip(path_segments(path));
+
+    if path_segments(template).count() != path_segments(path).count() {
+        return None;
+    }
+
+    // Check the literal segments first, so decoding errors are only reported for paths
+    // that could otherwise match.
+    if !pairs().all(|(t, seg)| t.contains('{') || decodes_to(seg, t, false)) {
+        return None;
+    }
+
+    let mut captures = Vec::new();
+    let mut error = None;
+
+    for (t, seg) in pairs().filter(|&(t, _)| t.contains('{')) {
+        let decoded = match percent_decode_str(seg, InvalidEscapePolicy::Error) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                error = error.or(Some(e.shift(offset(path, seg))));
+                continue;
+            },
+        };
+
+        let mut ranges = Vec::new();
+
+        if !match_segment(t, &decoded, &mut ranges) {
+            return None;
+        }
+
+        captures.extend(ranges.into_iter().map(|(name, range)| {
+            let value = match decoded {
+                Cow::Borrowed(s) => Cow::Borrowed(&s[range]),
+                Cow::Owned(ref s) => Cow::Owned(s[range].to_string()),
+            };
+
+            (name, value)
+        }));
+    }
+
+    Some(match error {
+        Some(e) => Err(e),
+        None => Ok(Captures(captures)),
+    })
+}
+
+/// Match the given decoded segment against the given template segment, appending the
+/// name and range of each variable to the given list.
+fn match_segment<'t>(template: &'t str, seg: &str, out: &mut Vec<(&'t str, Range<usize>)>)
+    -> bool
+{
+    let mut pieces = Pieces(template).peekable();
+    let mut pos = 0;
+
+    while let Some(piece) = pieces.next() {
+        match piece {
+            Piece::Literal(lit) => match seg[pos..].strip_prefix(lit) {
+                Some(_) => pos += lit.len(),
+                None => return false,
+            },
+            Piece::Variable(name) => {
+                // Values are non-empty, so the search starts after the first character.
+                let first = match seg[pos..].chars().next() {
+                    Some(c) => pos + c.len_utf8(),
+                    None => return false,
+                };
+
+                let end = match pieces.peek() {
+                    Some(&Piece::Literal(lit)) => match seg[first..].find(lit) {
+                        Some(idx) => first + idx,
+                        None => return false,
+                    },
+                    _ => seg.len(),
+                };
+
+                out.push((name, pos..end));
+                pos = end;
+            },
+        }
+    }
+
+    pos == seg.len()
+}
+
+/// Check if the given valid templates differ only in the names of their variables.
+fn same_shape(a: &str, b: &str) -> bool {
+    let shape = |t| {
+        path_segments(t)
+            .flat_map(|seg| Pieces(seg).chain(Some(Piece::Literal("/"))))
+            .map(|piece| match piece {
+                Piece::Literal(lit) => lit,
+                Piece::Variable(_) => "{}",
+            })
+    };
+
+    shape(a).eq(shape(b))
+}
+
+/// Iterate over whether each segment of the given template has variables, which orders
+/// templates from most to least specific.
+fn specificity(template: &str) -> impl Iterator<Item = bool> + '_ {
+    path_segments(template).map(|seg| seg.contains('{'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn caps(template: &str, path: &str) -> Option<Vec<(String, String)>> {
+        PathTemplate::parse(template).unwrap().captures(path).map(|caps| {
+            caps.unwrap().iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+        })
+    }
+
+    fn pairs(v: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(v.iter().map(|&(n, v)| (n.to_string(), v.to_string())).collect())
+    }
+
+    #[test]
+    fn test_parse() {
+        for &t in &["/", "/pets", "/pets/{petId}", "/{a}.{b}", "/a{b}c/{d}", "/{\u{e9}}"] {
+            assert_eq!(PathTemplate::parse(t).unwrap().as_str(), t);
+        }
+
+        for &(t, idx) in &[
+            ("", 0),
+            ("pets", 0),
+            ("/a?b", 2),
+            ("/a#b", 2),
+            ("/{}", 2),
+            ("/{a", 3),
+            ("/{a/b}", 3),
+            ("/{a{b}", 3),
+            ("/a}", 2),
+            ("/{a}{b}", 4),
+            ("/{a}/{a}", 5),
+            ("/{a}-{a}", 5),
+        ] {
+            let e = PathTemplate::parse(t).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidPattern);
+            assert_eq!(e.offset(), idx, "{}", t);
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        assert_eq!(caps("/pets/{petId}", "/pets/42"), pairs(&[("petId", "42")]));
+        assert_eq!(caps("/pets/{petId}", "/pets/a%20b"), pairs(&[("petId", "a b")]));
+        assert_eq!(caps("/pets/{petId}", "/pets/a%2Fb"), pairs(&[("petId", "a/b")]));
+        assert_eq!(caps("/pets/{petId}", "/%70ets/1#a/b"), pairs(&[("petId", "1")]));
+        assert_eq!(caps("/pets/{petId}", "/pets/"), None);
+        assert_eq!(caps("/pets/{petId}", "/pets/1/"), None);
+        assert_eq!(caps("/pets/{petId}", "/pets"), None);
+        assert_eq!(caps("/pets/{petId}", "/cats/1"), None);
+
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/2024-06.csv"),
+                   pairs(&[("y", "2024"), ("m", "06")]));
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/2024-06-01.csv"),
+                   pairs(&[("y", "2024"), ("m", "06-01")]));
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/-06.csv"), None);
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/2024-.csv"), None);
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/2024-06.csvx"), None);
+        assert_eq!(caps("/r/{y}-{m}.csv", "/r/2024%2D06.csv"),
+                   pairs(&[("y", "2024"), ("m", "06")]));
+        assert_eq!(caps("/f/{name}.{ext}", "/f/a.b.c"),
+                   pairs(&[("name", "a"), ("ext", "b.c")]));
+        assert_eq!(caps("/f/{name}.{ext}", "/f/.b"), None);
+        assert_eq!(caps("/f/x{n}", "/f/x1"), pairs(&[("n", "1")]));
+        assert_eq!(caps("/f/x{n}", "/f/x"), None);
+        assert_eq!(caps("/f/x{n}", "/f/y1"), None);
+        assert_eq!(caps("/f/{a}/{b}", "/f/\u{e9}/%C3%A9"),
+                   pairs(&[("a", "\u{e9}"), ("b", "\u{e9}")]));
+        assert_eq!(caps("/f/{a}x", "/f/\u{e9}x"), pairs(&[("a", "\u{e9}")]));
+
+        let t = PathTemplate::parse("/a/{b}/c/{d}").unwrap();
+        assert!(t.is_match("/a/1/c/2"));
+        assert!(!t.is_match("/a/1/c/%FF"));
+        assert!(t.captures("/a/%FF/d/1").is_none());
+
+        let e = t.captures("/a/1/c/%FF").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 7);
+        let e = t.captures("/a/%zz/c/%FF").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 3);
+
+        let caps = t.captures("/a/1/c/%41").unwrap().unwrap().into_vec();
+        assert!(matches!(caps[0].1, Cow::Borrowed("1")));
+        assert!(matches!(caps[1].1, Cow::Owned(ref s) if s == "A"));
+    }
+
+    #[test]
+    fn test_path_templates() {
+        let mut paths = PathTemplates::new();
+        assert!(paths.is_empty());
+
+        for (i, &t) in [
+            "/pets/{petId}",
+            "/pets/mine",
+            "/pets",
+            "/{kind}/mine",
+            "/pets/{petId}/photos/{photoId}",
+            "/pets/{petId}/photos/latest",
+            "/{kind}/{id}/photos/latest",
+        ].iter().enumerate() {
+            assert_eq!(paths.insert(t, i), Ok(None));
+        }
+
+        assert_eq!(paths.len(), 7);
+
+        let value = |path| paths.lookup(path).map(|m| *m.unwrap().value());
+
+        assert_eq!(value("/pets"), Some(2));
+        assert_eq!(value("/pets/mine"), Some(1));
+        assert_eq!(value("/pets/42"), Some(0));
+        assert_eq!(value("/cats/mine"), Some(3));
+        assert_eq!(value("/pets/1/photos/latest"), Some(5));
+        assert_eq!(value("/pets/1/photos/2"), Some(4));
+        assert_eq!(value("/cats/1/photos/latest"), Some(6));
+        assert_eq!(value("/cats/1/photos/2"), None);
+        assert_eq!(value("/cats"), None);
+        assert_eq!(value("/"), None);
+
+        let m = paths.lookup("/pets/1/photos/2").unwrap().unwrap();
+        assert_eq!(m.template(), "/pets/{petId}/photos/{photoId}");
+        assert_eq!(m.captures().get("photoId"), Some("2"));
+        assert_eq!(m.into_captures().get("petId"), Some("1"));
+
+        assert_eq!(paths.insert("/pets/{id}", 7), Ok(Some(0)));
+        assert_eq!(paths.len(), 7);
+        let m = paths.lookup("/pets/42").unwrap().unwrap();
+        assert_eq!((m.template(), m.captures().get("id")), ("/pets/{id}", Some("42")));
+
+        let e = paths.insert("/pets/{a", 8).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPattern);
+        assert_eq!(paths.len(), 7);
+
+        let route = |s| {
+            paths.route(&ParsedTarget::parse(s).unwrap()).map(|m| *m.unwrap().value())
+        };
+        assert_eq!(route("/pets/mine?a"), Some(1));
+        assert_eq!(route("http://x/pets/mine?a"), Some(1));
+        assert_eq!(route("x:443"), None);
+        assert_eq!(route("*"), None);
+        let e = paths.lookup("/pets/%FF").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+    }
+
+    #[test]
+    fn test_same_shape() {
+        assert!(same_shape("/a/{b}", "/a/{c}"));
+        assert!(same_shape("/{a}-{b}", "/{x}-{y}"));
+        assert!(!same_shape("/a/{b}", "/a/b"));
+        assert!(!same_shape("/a/{b}", "/a/{b}c"));
+        assert!(!same_shape("/a{b}", "/a/{b}"));
+    }
+}
@@ -0,0 +1,971 @@
+//! Configurable parsing of request targets.
+
+use {ErrorKind, ParseError, ParsedTarget, RequestTarget};
+use decode::percent_encode_non_ascii_into;
+use form::offset;
+use lenient::trim;
+use scan::{find_invalid_escape, find_line_break};
+
+/// Scheme of an absolute-form target.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Scheme {
+    /// The `http` scheme [RFC7230§2.7.1].
+    Http,
+    /// The `https` scheme [RFC7230§2.7.2].
+    Https,
+    /// The WebSocket `ws` scheme [RFC6455§3].
+    Ws,
+    /// The secure WebSocket `wss` scheme [RFC6455§3].
+    Wss,
+}
+
+impl Scheme {
+    /// Look up the given scheme name, ignoring ASCII case.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        [Scheme::Http, Scheme::Https, Scheme::Ws, Scheme::Wss].iter()
+            .find(|s| s.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    /// Retrieve the lowercase name of the scheme.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+            Scheme::Ws => "ws",
+            Scheme::Wss => "wss",
+        }
+    }
+
+    /// Retrieve the port used when a target with the scheme doesn't specify one.
+    pub const fn default_port(self) -> u16 {
+        match self {
+            Scheme::Http | Scheme::Ws => 80,
+            Scheme::Https | Scheme::Wss => 443,
+        }
+    }
+
+    /// Retrieve the bit representing the scheme in a set of schemes.
+    pub(crate) const fn bit(self) -> u8 {
+        match self {
+            Scheme::Http => 1 << 0,
+            Scheme::Https => 1 << 1,
+            Scheme::Ws => 1 << 2,
+            Scheme::Wss => 1 << 3,
+        }
+    }
+}
+
+/// Policy for bytes above 0x7F, such as raw UTF-8 sent by legacy clients.
+///
+/// These aren't allowed in a URI [RFC3986§2], but some clients send them anyway.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NonAsciiPolicy {
+    /// Reject the target with `ErrorKind::NonAscii`.
+    Reject,
+    /// Accept the bytes unchanged, as long as the target is valid UTF-8.
+    Accept,
+    /// Percent-encode each of the bytes before parsing, which requires a buffer to hold
+    /// the encoded target.
+    PercentEncode,
+}
+
+/// Policy for backslashes, which some clients send where `/` is meant.
+///
+/// These aren't allowed unencoded in a URI [RFC3986§2], but some backends treat them as
+/// path separators, so a target like `/a\..\b` may bypass prefix-based access checks.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BackslashPolicy {
+    /// Reject the target with `ErrorKind::Backslash`.
+    Reject,
+    /// Accept backslashes as literal bytes, distinct from `/`.
+    Literal,
+    /// Replace each backslash with `/` before parsing, which requires a buffer to hold
+    /// the replaced target.
+    Normalize,
+}
+
+/// Options controlling which targets are accepted by `ParseOptions::parse`.
+///
+/// The default options accept the same targets as `ParsedTarget::parse`. Each stricter
+/// check can be enabled individually, or all at once with `ParseOptions::strict`, so
+/// different deployments can tune what they accept.
+///
+/// ```rust
+/// use uhttp_request_target::{ParseOptions, ErrorKind, Scheme};
+///
+/// let opts = ParseOptions::new()
+///     .reject_controls(true)
+///     .allow_scheme(Scheme::Http, false)
+///     .max_len(32);
+///
+/// assert!(ParseOptions::new().parse("/a\tb").is_ok());
+/// assert_eq!(opts.parse("/a\tb").unwrap_err().kind(), ErrorKind::ControlCharacter);
+/// assert_eq!(opts.parse("http://a").unwrap_err().kind(), ErrorKind::UnknownScheme);
+/// assert!(opts.parse("https://a").is_ok());
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ParseOptions<'s> {
+    schemes: u8,
+    any_scheme: bool,
+    custom_schemes: &'s [&'s str],
+    max_len: Option<usize>,
+    max_path_len: Option<usize>,
+    max_query_len: Option<usize>,
+    max_host_len: Option<usize>,
+    max_segments: Option<usize>,
+    max_params: Option<usize>,
+    trim_whitespace: bool,
+    non_ascii: NonAsciiPolicy,
+    backslash: BackslashPolicy,
+    reject_controls: bool,
+    reject_fragment: bool,
+    reject_encoded_line_breaks: bool,
+    check_escapes: bool,
+    reject_userinfo: bool,
+    check_chars: bool,
+}
+
+impl<'s> ParseOptions<'s> {
+    /// Create options that accept the same targets as `ParsedTarget::parse`.
+    pub const fn new() -> Self {
+        ParseOptions {
+            schemes: Scheme::Http.bit() | Scheme::Https.bit(),
+            any_scheme: false,
+            custom_schemes: &[],
+            max_len: None,
+            max_path_len: None,
+            max_query_len: None,
+            max_host_len: None,
+            max_segments: None,
+            max_params: None,
+            trim_whitespace: false,
+            non_ascii: NonAsciiPolicy::Accept,
+            backslash: BackslashPolicy::Literal,
+            reject_controls: false,
+            reject_fragment: false,
+            reject_encoded_line_breaks: false,
+            check_escapes: false,
+            reject_userinfo: false,
+            check_chars: false,
+        }
+    }
+
+    /// Create options with every stricter check enabled.
+    ///
+    /// Both HTTP schemes are still accepted, and there's no length limit.
+    pub const fn strict() -> Self {
+        ParseOptions {
+            schemes: Scheme::Http.bit() | Scheme::Https.bit(),
+            any_scheme: false,
+            custom_schemes: &[],
+            max_len: None,
+            max_path_len: None,
+            max_query_len: None,
+            max_host_len: None,
+            max_segments: None,
+            max_params: None,
+            trim_whitespace: false,
+            non_ascii: NonAsciiPolicy::Reject,
+            backslash: BackslashPolicy::Reject,
+            reject_controls: true,
+            reject_fragment: true,
+            reject_encoded_line_breaks: true,
+            check_escapes: true,
+            reject_userinfo: true,
+            check_chars: true,
+        }
+    }
+
+    /// Set whether to accept absolute-form targets with the given scheme, which are
+    /// otherwise rejected with `ErrorKind::UnknownScheme`.
+    ///
+    /// Both `http` and `https` are accepted by default. The WebSocket schemes aren't, but
+    /// can be allowed so WebSocket-aware proxies don't have to rewrite targets before
+    /// parsing.
+    pub const fn allow_scheme(mut self, scheme: Scheme, allow: bool) -> Self {
+        if allow {
+            self.schemes |= scheme.bit();
+        } else {
+            self.schemes &= !scheme.bit();
+        }
+
+        self
+    }
+
+    /// Set whether to accept absolute-form targets with any syntactically valid scheme
+    /// followed by `://`, such as `ftp://example.com/a` [RFC3986§3.1].
+    ///
+    /// Absolute form allows any absolute URI [RFC7230§5.3.2], which proxies may receive,
+    /// but only the HTTP schemes are accepted by default. Those remain governed by
+    /// `allow_scheme` even with this set. URIs without an authority, such as `urn:a/b`,
+    /// still can't be told apart from other forms and are rejected as before.
+    pub const fn any_scheme(mut self, any: bool) -> Self {
+        self.any_scheme = any;
+        self
+    }
+
+    /// Set additional schemes to accept in absolute-form targets with `://`, such as
+    /// `coap` or `rtsp`, which are matched case-insensitively.
+    ///
+    /// This lets gateways for other protocols reuse the same classification. Names that
+    /// aren't syntactically valid schemes [RFC3986§3.1] never match. The HTTP schemes
+    /// remain governed by `allow_scheme` even if they're listed.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{ParseOptions, ErrorKind};
+    ///
+    /// let opts = ParseOptions::new().custom_schemes(&["coap", "rtsp"]);
+    ///
+    /// assert!(opts.parse("coap://sensor/temp").is_ok());
+    /// assert!(opts.parse("RTSP://cam:554/live").is_ok());
+    /// assert_eq!(opts.parse("ftp://a/").unwrap_err().kind(), ErrorKind::UnknownScheme);
+    /// ```
+    ///
+    /// The names are borrowed, so they can come from runtime configuration:
+    ///
+    /// ```rust
+    /// use uhttp_request_target::ParseOptions;
+    ///
+    /// let config = String::from("coap rtsp");
+    /// let names: Vec<&str> = config.split(' ').collect();
+    /// let opts = ParseOptions::new().custom_schemes(&names);
+    ///
+    /// assert!(opts.parse("rtsp://cam/live").is_ok());
+    /// ```
+    pub const fn custom_schemes(mut self, schemes: &'s [&'s str]) -> Self {
+        self.custom_schemes = schemes;
+        self
+    }
+
+    /// Set the maximum length of the target in bytes, as with
+    /// `RequestTarget::classify_bounded`.
+    ///
+    /// Longer targets are rejected with `ErrorKind::TooLong` at the given length before
+    /// any other checks, which can be answered with `414 URI Too Long`.
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Set the maximum length of the path in bytes, which includes any fragment of an
+    /// origin-form or absolute-form target without a query.
+    ///
+    /// Longer paths are rejected with `ErrorKind::PathTooLong` at the given length past
+    /// the start of the path, so they can be answered differently from other errors.
+    pub const fn max_path_len(mut self, max_len: usize) -> Self {
+        self.max_path_len = Some(max_len);
+        self
+    }
+
+    /// Set the maximum length of the query in bytes, not including the `?`.
+    ///
+    /// Longer queries are rejected with `ErrorKind::QueryTooLong` at the given length past
+    /// the start of the query.
+    pub const fn max_query_len(mut self, max_len: usize) -> Self {
+        self.max_query_len = Some(max_len);
+        self
+    }
+
+    /// Set the maximum length of the host in bytes of an absolute-form or
+    /// authority-form target, including any brackets.
+    ///
+    /// Longer hosts are rejected with `ErrorKind::HostTooLong` at the given length past
+    /// the start of the host.
+    pub const fn max_host_len(mut self, max_len: usize) -> Self {
+        self.max_host_len = Some(max_len);
+        self
+    }
+
+    /// Set the maximum number of segments in the path, where each `/` before any fragment
+    /// starts a segment [RFC3986§3.3].
+    ///
+    /// Paths with more segments are rejected with `ErrorKind::TooManySegments` at the `/`
+    /// starting the first segment over the limit, which protects routers that walk the
+    /// segments from pathologically deep paths.
+    pub const fn max_segments(mut self, max: usize) -> Self {
+        self.max_segments = Some(max);
+        self
+    }
+
+    /// Set the maximum number of `&`-separated parameters in the query before any
+    /// fragment, where an empty query has none.
+    ///
+    /// Queries with more parameters are rejected with `ErrorKind::TooManyParams` at the
+    /// separator before the first parameter over the limit, which protects query parsers
+    /// from targets like `/?&&&&`.
+    pub const fn max_params(mut self, max: usize) -> Self {
+        self.max_params = Some(max);
+        self
+    }
+
+    /// Set whether to trim surrounding whitespace before parsing, rather than rejecting
+    /// it with `ErrorKind::SurroundingWhitespace`.
+    ///
+    /// The parsed target then borrows only the trimmed part of the input, but error
+    /// offsets are still relative to the start of the input.
+    pub const fn trim_whitespace(mut self, trim: bool) -> Self {
+        self.trim_whitespace = trim;
+        self
+    }
+
+    /// Set the policy for bytes above 0x7F, which are accepted by default.
+    ///
+    /// Only `NonAsciiPolicy::Reject` applies to `parse`, since encoding needs the buffer
+    /// passed to `parse_bytes`.
+    pub const fn non_ascii(mut self, policy: NonAsciiPolicy) -> Self {
+        self.non_ascii = policy;
+        self
+    }
+
+    /// Set the policy for literal backslashes, which are accepted by default.
+    ///
+    /// Only `BackslashPolicy::Reject` applies to `parse`, since replacement needs the
+    /// buffer passed to `parse_bytes`. Encoded backslashes, `%5C`, are always accepted.
+    pub const fn backslash(mut self, policy: BackslashPolicy) -> Self {
+        self.backslash = policy;
+        self
+    }
+
+    /// Set whether to reject targets containing SP, HTAB, or any other control byte,
+    /// including DEL.
+    ///
+    /// Classification only rejects the CR, LF, and NUL octets that can never appear in a
+    /// request line, but the others are common request smuggling vectors when targets
+    /// are passed on to other parsers. These are rejected with
+    /// `ErrorKind::ControlCharacter`.
+    pub const fn reject_controls(mut self, reject: bool) -> Self {
+        self.reject_controls = reject;
+        self
+    }
+
+    /// Set whether to reject targets containing a percent-encoded CR or LF, such as
+    /// `%0D%0A`, as detected by `find_line_break`, with `ErrorKind::EncodedLineBreak`
+    /// at the offset of the `%`.
+    ///
+    /// Raw CR and LF are always rejected by classification, but encoded ones are a
+    /// common vector for response splitting and log injection when a target is decoded
+    /// and echoed.
+    pub const fn reject_encoded_line_breaks(mut self, reject: bool) -> Self {
+        self.reject_encoded_line_breaks = reject;
+        self
+    }
+
+    /// Set whether to reject targets containing a `%` that isn't followed by two hex
+    /// digits, as detected by `find_invalid_escape`, with
+    /// `ErrorKind::InvalidPercentEncoding` at the offset of the `%`.
+    ///
+    /// This applies to the whole target, unlike `check_chars`, which otherwise only
+    /// reports such an escape after any invalid byte before it.
+    pub const fn check_escapes(mut self, check: bool) -> Self {
+        self.check_escapes = check;
+        self
+    }
+
+    /// Set whether to reject targets containing a fragment, as detected by
+    /// `ParsedTarget::fragment`, with `ErrorKind::Fragment` at the offset of the `#`.
+    pub const fn reject_fragment(mut self, reject: bool) -> Self {
+        self.reject_fragment = reject;
+        self
+    }
+
+    /// Set whether to reject absolute-form and authority-form targets containing
+    /// userinfo, as detected by `AbsoluteForm::userinfo` and `AuthorityForm::userinfo`,
+    /// with `ErrorKind::Userinfo` at the start of the userinfo.
+    pub const fn reject_userinfo(mut self, reject: bool) -> Self {
+        self.reject_userinfo = reject;
+        self
+    }
+
+    /// Set whether to check the target against the strict grammar of its form, as with
+    /// `ParsedTarget::validate`.
+    pub const fn check_chars(mut self, check: bool) -> Self {
+        self.check_chars = check;
+        self
+    }
+
+    /// Classify the given target and split it into its components like
+    /// `ParsedTarget::parse`, then apply the checks enabled in these options.
+    pub fn parse<'a>(&self, s: &'a str) -> Result<ParsedTarget<'a>, ParseError> {
+        if let Some(max_len) = self.max_len {
+            if s.len() > max_len {
+                return Err(ParseError::at(ErrorKind::TooLong, s.as_bytes(), max_len));
+            }
+        }
+
+        if !self.trim_whitespace {
+            return self.parse_trimmed(s);
+        }
+
+        let range = trim(s.as_bytes());
+
+        if range.is_empty() {
+            return Err(ParseError::at(ErrorKind::Empty, s.as_bytes(), 0));
+        }
+
+        self.parse_trimmed(&s[range.clone()]).map_err(|e| e.shift(range.start))
+    }
+
+    /// Parse the given raw target bytes like `parse`, applying the policies for bytes above
+    /// 0x7F and backslashes.
+    ///
+    /// With `NonAsciiPolicy::PercentEncode`, a target containing such bytes is encoded
+    /// into the given buffer and parsed from there, so error offsets are then relative to
+    /// the encoded target. With `BackslashPolicy::Normalize`, a target containing
+    /// backslashes is likewise copied into the buffer with them replaced. A buffer three
+    /// times the size of the target is always large enough, and it's otherwise left
+    /// untouched. Targets that aren't valid UTF-8 are
+    /// rejected with `ErrorKind::InvalidUtf8`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{ParseOptions, NonAsciiPolicy};
+    ///
+    /// let opts = ParseOptions::new().non_ascii(NonAsciiPolicy::PercentEncode);
+    /// let mut buf = [0; 64];
+    ///
+    /// let t = opts.parse_bytes(b"/caf\xc3\xa9", &mut buf).unwrap();
+    /// assert_eq!(t.as_str(), "/caf%C3%A9");
+    /// ```
+    pub fn parse_bytes<'a>(&self, s: &'a [u8], buf: &'a mut [u8])
+        -> Result<ParsedTarget<'a>, ParseError>
+    {
+        let mut len = match self.non_ascii {
+            NonAsciiPolicy::PercentEncode if !s.is_ascii() => {
+                Some(percent_encode_non_ascii_into(s, buf)?)
+            },
+            _ => None,
+        };
+
+        if self.backslash == BackslashPolicy::Normalize && s.contains(&b'\\') {
+            let n = match len {
+                Some(n) => n,
+                None => match buf.get_mut(..s.len()) {
+                    Some(slots) => {
+                        slots.copy_from_slice(s);
+                        s.len()
+                    },
+                    None => return Err(ParseError::at(ErrorKind::TooLong, s, buf.len())),
+                },
+            };
+
+            for b in buf[..n].iter_mut().filter(|b| **b == b'\\') {
+                *b = b'/';
+            }
+
+            len = Some(n);
+        }
+
+        let buf: &'a [u8] = buf;
+
+        let s = match len {
+            Some(len) => &buf[..len],
+            None => s,
+        };
+
+        if self.non_ascii == NonAsciiPolicy::Reject {
+            if let Some(idx) = s.iter().position(|&b| b > 0x7F) {
+                return Err(ParseError::at(ErrorKind::NonAscii, s, idx));
+            }
+        }
+
+        match core::str::from_utf8(s) {
+            Ok(s) => self.parse(s),
+            Err(e) => Err(ParseError::at(ErrorKind::InvalidUtf8, s, e.valid_up_to())),
+        }
+    }
+
+    /// Check if the given scheme name is accepted.
+    fn accepts(&self, name: &str) -> bool {
+        match Scheme::from_name(name) {
+            Some(scheme @ Scheme::Http) | Some(scheme @ Scheme::Https) => {
+                self.schemes & scheme.bit() != 0
+            },
+            Some(scheme) if self.schemes & scheme.bit() != 0 => true,
+            _ => {
+                self.any_scheme ||
+                self.custom_schemes.iter().any(|s| s.eq_ignore_ascii_case(name))
+            },
+        }
+    }
+
+    /// Parse the given target, which has already had any whitespace trimmed.
+    fn parse_trimmed<'a>(&self, s: &'a str) -> Result<ParsedTarget<'a>, ParseError> {
+        // Check for a fragment before the authority is checked, so it's reported even if
+        // the port is also invalid.
+        if self.reject_fragment {
+            if let Ok(RequestTarget::AbsPath) | Ok(RequestTarget::AbsUri) =
+                RequestTarget::classify(s.as_bytes())
+            {
+                if let Some(idx) = s.find('#') {
+                    return Err(ParseError::at(ErrorKind::Fragment, s.as_bytes(), idx));
+                }
+            }
+        }
+
+        let parsed = match ParsedTarget::parse(s) {
+            // The scheme ends in the `:` before the slash where it was rejected.
+            Err(e) if e.kind() == ErrorKind::UnknownScheme &&
+                      self.accepts(&s[..e.offset() - 1]) =>
+            {
+                parse_any_scheme(s, e.offset())?
+            },
+            result => result?,
+        };
+
+        if let ParsedTarget::AbsUri(ref form) = parsed {
+            if !self.accepts(form.scheme()) {
+                return Err(ParseError::at(ErrorKind::UnknownScheme, s.as_bytes(), 0));
+            }
+        }
+
+        let (path, query, host) = match parsed {
+            ParsedTarget::AbsPath(ref form) => (Some(form.path()), form.query(), None),
+            ParsedTarget::AbsUri(ref form) => {
+                (Some(form.path()), form.query(), Some(form.host()))
+            },
+            ParsedTarget::Authority(ref form) => (None, None, Some(form.host())),
+            ParsedTarget::ServerOptions => (None, None, None),
+        };
+
+        check_len(s, path, self.max_path_len, ErrorKind::PathTooLong)?;
+        check_len(s, query, self.max_query_len, ErrorKind::QueryTooLong)?;
+        check_len(s, host, self.max_host_len, ErrorKind::HostTooLong)?;
+        check_count(s, path, b'/', 0, self.max_segments, ErrorKind::TooManySegments)?;
+        check_count(s, query, b'&', 1, self.max_params, ErrorKind::TooManyParams)?;
+
+        if self.reject_encoded_line_breaks {
+            if let Some(idx) = find_line_break(s.as_bytes()) {
+                return Err(ParseError::at(ErrorKind::EncodedLineBreak, s.as_bytes(), idx));
+            }
+        }
+
+        if self.check_escapes {
+            if let Some(idx) = find_invalid_escape(s.as_bytes()) {
+                let kind = ErrorKind::InvalidPercentEncoding;
+                return Err(ParseError::at(kind, s.as_bytes(), idx));
+            }
+        }
+
+        if self.reject_fragment {
+            if let Some(fragment) = parsed.fragment() {
+                let idx = s.len() - fragment.len() - 1;
+                return Err(ParseError::at(ErrorKind::Fragment, s.as_bytes(), idx));
+            }
+        }
+
+        if self.reject_userinfo {
+            let userinfo = match parsed {
+                ParsedTarget::AbsUri(ref form) => form.userinfo(),
+                ParsedTarget::Authority(ref form) => form.userinfo(),
+                ParsedTarget::AbsPath(_) | ParsedTarget::ServerOptions => None,
+            };
+
+            if let Some(userinfo) = userinfo {
+                let idx = offset(s, userinfo);
+                return Err(ParseError::at(ErrorKind::Userinfo, s.as_bytes(), idx));
+            }
+        }
+
+        if self.backslash == BackslashPolicy::Reject {
+            if let Some(idx) = s.bytes().position(|b| b == b'\\') {
+                return Err(ParseError::at(ErrorKind::Backslash, s.as_bytes(), idx));
+            }
+        }
+
+        if self.non_ascii == NonAsciiPolicy::Reject {
+            if let Some(idx) = s.bytes().position(|b| b > 0x7F) {
+                return Err(ParseError::at(ErrorKind::NonAscii, s.as_bytes(), idx));
+            }
+        }
+
+        if self.reject_controls {
+            if let Some(idx) = s.bytes().position(is_control) {
+                return Err(ParseError::at(ErrorKind::ControlCharacter, s.as_bytes(), idx));
+            }
+        }
+
+        if self.check_chars {
+            parsed.validate()?;
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl<'s> Default for ParseOptions<'s> {
+    fn default() -> Self { ParseOptions::new() }
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Classify the given target and split it into its components like `parse`, then
+    /// apply the checks enabled in the given options, as with `ParseOptions::parse`.
+    pub fn parse_with(s: &'a str, opts: &ParseOptions) -> Result<Self, ParseError> {
+        opts.parse(s)
+    }
+}
+
+/// Parse the given target as absolute form with a non-HTTP scheme, which classification
+/// rejected at the slash at the given offset after the scheme.
+fn parse_any_scheme(s: &str, slash: usize) -> Result<ParsedTarget<'_>, ParseError> {
+    if !s[slash..].starts_with("//") {
+        return Err(ParseError::at(ErrorKind::UnknownScheme, s.as_bytes(), slash));
+    }
+
+    // The remainder is classified as origin form to check its bytes like the rest.
+    RequestTarget::classify(&s.as_bytes()[slash..]).map_err(|e| e.shift(slash))?;
+
+    let parsed = ParsedTarget::split(s, RequestTarget::AbsUri);
+    parsed.check_structure()?;
+    Ok(parsed)
+}
+
+/// Check that the given component of the given target, if any, is within the given
+/// limit, if any.
+fn check_len(s: &str, part: Option<&str>, max_len: Option<usize>, kind: ErrorKind)
+    -> Result<(), ParseError>
+{
+    match (part, max_len) {
+        (Some(part), Some(max_len)) if part.len() > max_len => {
+            Err(ParseError::at(kind, s.as_bytes(), offset(s, part) + max_len))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Check that the given component of the given target, if any, has no more than the
+/// given limit, if any, of items started by the given separator, in addition to the given
+/// number of items implied by a nonempty component.
+///
+/// Only the part of the component before any fragment of the target is counted.
+fn check_count(s: &str, part: Option<&str>, sep: u8, implied: usize, max: Option<usize>,
+               kind: ErrorKind)
+    -> Result<(), ParseError>
+{
+    let (part, max) = match (part, max) {
+        (Some(part), Some(max)) => (part, max),
+        _ => return Ok(()),
+    };
+
+    let start = offset(s, part);
+    let end = s.find('#').unwrap_or(s.len()).clamp(start, start + part.len());
+    let bytes = &s.as_bytes()[start..end];
+
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    let mut count = implied;
+
+    if count > max {
+        return Err(ParseError::at(kind, s.as_bytes(), start));
+    }
+
+    for (idx, &b) in bytes.iter().enumerate() {
+        if b != sep {
+            continue;
+        }
+
+        count += 1;
+
+        if count > max {
+            return Err(ParseError::at(kind, s.as_bytes(), start + idx));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check if the given byte is SP or a control byte.
+fn is_control(b: u8) -> bool {
+    b <= b' ' || b == 0x7F
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn err(s: &str, opts: &ParseOptions) -> (ErrorKind, usize) {
+        let e = opts.parse(s).unwrap_err();
+        (e.kind(), e.offset())
+    }
+
+    fn bytes(s: &[u8], opts: &ParseOptions) -> Result<String, (ErrorKind, usize)> {
+        let mut buf = [0; 16];
+        opts.parse_bytes(s, &mut buf).map(|t| t.as_str().to_string())
+            .map_err(|e| (e.kind(), e.offset()))
+    }
+
+    #[test]
+    fn test_default() {
+        assert_eq!(ParseOptions::default(), ParseOptions::new());
+    }
+
+    #[test]
+    fn test_reject_controls() {
+        let opts = ParseOptions::new().reject_controls(true);
+
+        for &s in &["/a\tb", "/a b", "a\x0b:80", "http://a/\x7f", "/a?\x01"] {
+            assert!(ParsedTarget::parse_with(s, &ParseOptions::new()).is_ok());
+            assert_eq!(err(s, &opts).0, ErrorKind::ControlCharacter);
+        }
+
+        assert_eq!(err("/ab\tc", &opts), (ErrorKind::ControlCharacter, 3));
+        assert_eq!(err("/a\r", &opts), (ErrorKind::IllegalCharacter, 2));
+        assert_eq!(err(" /a", &opts), (ErrorKind::SurroundingWhitespace, 0));
+        assert!(ParsedTarget::parse_with("/a\u{e9}", &opts).is_ok());
+    }
+
+    #[test]
+    fn test_check_chars() {
+        let opts = ParseOptions::new().check_chars(true);
+        assert_eq!(err("/a\u{e9}", &opts), (ErrorKind::InvalidPath, 2));
+        assert_eq!(err("/a b", &opts), (ErrorKind::InvalidPath, 2));
+    }
+
+    #[test]
+    fn test_reject_fragment() {
+        let opts = ParseOptions::new().reject_fragment(true);
+        assert_eq!(err("/a?b#c", &opts), (ErrorKind::Fragment, 4));
+        assert_eq!(err("http://a#", &opts), (ErrorKind::Fragment, 8));
+        assert_eq!(err("http://a#f", &opts), (ErrorKind::Fragment, 8));
+        assert_eq!(err("http://a:80#f", &opts), (ErrorKind::Fragment, 11));
+        assert_eq!(err("http://a?q#f", &opts), (ErrorKind::Fragment, 10));
+        assert_eq!(err("http://a:x#f", &opts), (ErrorKind::Fragment, 10));
+        assert_eq!(err("http://a:x#f", &ParseOptions::new()), (ErrorKind::InvalidPort, 9));
+
+        for &s in &["http://a#f", "http://a:80#f", "http://a?q#f"] {
+            assert!(ParseOptions::new().parse(s).is_ok(), "{}", s);
+        }
+        assert!(ParsedTarget::parse_with("/a?b", &opts).is_ok());
+        assert!(ParsedTarget::parse_with("/a#b", &ParseOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn test_reject_userinfo() {
+        let opts = ParseOptions::new().reject_userinfo(true);
+        assert_eq!(err("http://u:p@a/", &opts), (ErrorKind::Userinfo, 7));
+        assert_eq!(err("https://@a", &opts), (ErrorKind::Userinfo, 8));
+        assert!(ParsedTarget::parse_with("http://a/b@c?d@e", &opts).is_ok());
+        assert!(ParsedTarget::parse_with("/a@b", &opts).is_ok());
+        assert_eq!(err("u@a:80", &opts), (ErrorKind::Userinfo, 0));
+        assert!(ParsedTarget::parse_with("u@a:80", &ParseOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn test_allow_scheme() {
+        let opts = ParseOptions::new().allow_scheme(Scheme::Https, false);
+        assert_eq!(err("https://a/", &opts), (ErrorKind::UnknownScheme, 0));
+        assert!(opts.parse("http://a/").is_ok());
+        assert!(opts.parse("HTTP://a/").is_ok());
+        assert_eq!(err("HTTPS://a/", &opts), (ErrorKind::UnknownScheme, 0));
+        assert!(opts.parse("/https://a/").is_ok());
+        assert_eq!(err("ftp://a/", &opts), (ErrorKind::UnknownScheme, 4));
+
+        let opts = opts.allow_scheme(Scheme::Http, false).allow_scheme(Scheme::Https, true);
+        assert_eq!(err("http://a/", &opts), (ErrorKind::UnknownScheme, 0));
+        assert!(opts.parse("https://a/").is_ok());
+    }
+
+    #[test]
+    fn test_any_scheme() {
+        let opts = ParseOptions::new().any_scheme(true);
+        let t = opts.parse("ftp://u@a:21/b?c").unwrap();
+        assert_eq!(t.kind(), RequestTarget::AbsUri);
+
+        match t {
+            ParsedTarget::AbsUri(form) => {
+                assert_eq!(form.scheme(), "ftp");
+                assert_eq!(form.host(), "a");
+                assert_eq!(form.port(), Some(21));
+                assert_eq!(form.path(), "/b");
+                assert_eq!(form.query(), Some("c"));
+            },
+            _ => panic!(),
+        }
+
+        assert!(opts.parse("WS+x.1-y://a").is_ok());
+        assert!(opts.parse("http://a").is_ok());
+        assert_eq!(err("ftp:/a", &opts), (ErrorKind::UnknownScheme, 4));
+        assert_eq!(err("1ftp://a", &opts), (ErrorKind::SlashInAuthority, 5));
+        assert_eq!(err("ftp://a/\r", &opts), (ErrorKind::IllegalCharacter, 8));
+        assert_eq!(err("ftp://a/ ", &opts), (ErrorKind::SurroundingWhitespace, 8));
+        assert_eq!(err("ftp://a:b/", &opts), (ErrorKind::InvalidPort, 8));
+        assert_eq!(err("ftp://u@a/", &opts.reject_userinfo(true)), (ErrorKind::Userinfo, 6));
+        assert_eq!(err("http://a", &opts.allow_scheme(Scheme::Http, false)),
+            (ErrorKind::UnknownScheme, 0));
+        assert!(ParseOptions::strict().any_scheme(true).parse("ws://a/b").is_ok());
+    }
+
+    #[test]
+    fn test_ws_schemes() {
+        let opts = ParseOptions::new().allow_scheme(Scheme::Ws, true);
+        assert_eq!(opts.parse("ws://a/b").unwrap().kind(), RequestTarget::AbsUri);
+        assert!(opts.parse("WS://a/b").is_ok());
+        assert_eq!(err("wss://a/b", &opts), (ErrorKind::UnknownScheme, 4));
+        assert_eq!(err("ftp://a/b", &opts), (ErrorKind::UnknownScheme, 4));
+        assert_eq!(err("ws://a:x", &opts), (ErrorKind::InvalidPort, 7));
+        assert!(opts.allow_scheme(Scheme::Wss, true).parse("wss://a:443/b").is_ok());
+        assert_eq!(err("ws://a/b", &ParseOptions::new()), (ErrorKind::UnknownScheme, 3));
+        assert_eq!(Scheme::Wss.name(), "wss");
+    }
+
+    #[test]
+    fn test_custom_schemes() {
+        let opts = ParseOptions::new().custom_schemes(&["coap", "ws", "http", "a/b"]);
+        assert!(opts.parse("coap://a/b").is_ok());
+        assert!(opts.parse("CoAP://a/b").is_ok());
+        assert!(opts.parse("ws://a/b").is_ok());
+        assert_eq!(err("coaps://a/b", &opts), (ErrorKind::UnknownScheme, 6));
+        assert_eq!(err("coap:/a/b", &opts), (ErrorKind::UnknownScheme, 5));
+        assert_eq!(err("coap://a/\0", &opts), (ErrorKind::NulByte, 9));
+        assert_eq!(err("http://a", &opts.allow_scheme(Scheme::Http, false)),
+            (ErrorKind::UnknownScheme, 0));
+
+        let config = String::from("rtsp");
+        let names = [config.as_str()];
+        let opts = ParseOptions::new().custom_schemes(&names);
+        assert!(opts.parse("rtsp://a/b").is_ok());
+        assert_eq!(err("coap://a/b", &opts), (ErrorKind::UnknownScheme, 5));
+    }
+
+    #[test]
+    fn test_max_len() {
+        let opts = ParseOptions::new().max_len(4);
+        assert!(opts.parse("/abc").is_ok());
+        assert_eq!(err("/abcd", &opts), (ErrorKind::TooLong, 4));
+        assert_eq!(err("/ab\r", &opts), (ErrorKind::IllegalCharacter, 3));
+    }
+
+    #[test]
+    fn test_component_lens() {
+        let opts = ParseOptions::new().max_path_len(3).max_query_len(2).max_host_len(4);
+        assert!(opts.parse("/ab?cd").is_ok());
+        assert!(opts.parse("http://abcd/ab?cd").is_ok());
+        assert!(opts.parse("[::]:80").is_ok());
+        assert!(opts.parse("*").is_ok());
+        assert_eq!(err("/abc?cd", &opts), (ErrorKind::PathTooLong, 3));
+        assert_eq!(err("/ab?cde", &opts), (ErrorKind::QueryTooLong, 6));
+        assert_eq!(err("http://a/abcd", &opts), (ErrorKind::PathTooLong, 11));
+        assert_eq!(err("http://a/?abc", &opts), (ErrorKind::QueryTooLong, 12));
+        assert_eq!(err("http://u@abcde:80/", &opts), (ErrorKind::HostTooLong, 13));
+        assert_eq!(err("[::1]:80", &opts), (ErrorKind::HostTooLong, 4));
+        assert_eq!(err("abcd/", &opts.max_len(4)), (ErrorKind::TooLong, 4));
+    }
+
+    #[test]
+    fn test_check_escapes() {
+        let opts = ParseOptions::new().check_escapes(true);
+        assert!(opts.parse("/a%20b?c=%2F").is_ok());
+        assert!(ParseOptions::new().parse("/a%zz").is_ok());
+        assert_eq!(err("/a%zz", &opts), (ErrorKind::InvalidPercentEncoding, 2));
+        assert_eq!(err("/a?b=%2", &opts), (ErrorKind::InvalidPercentEncoding, 5));
+        assert_eq!(err("http://a%/", &opts), (ErrorKind::InvalidPercentEncoding, 8));
+        assert_eq!(err("/a%0", &ParseOptions::strict()),
+            (ErrorKind::InvalidPercentEncoding, 2));
+    }
+
+    #[test]
+    fn test_max_segments_params() {
+        let opts = ParseOptions::new().max_segments(2).max_params(2);
+        assert!(opts.parse("/a/b?c=1&d=2").is_ok());
+        assert!(opts.parse("/a/?&").is_ok());
+        assert!(opts.parse("/a/b#/c/d?&&").is_ok());
+        assert!(opts.parse("/a/b?c&d#&&").is_ok());
+        assert!(opts.parse("http://a/b/c?").is_ok());
+        assert!(opts.parse("http://a").is_ok());
+        assert!(opts.parse("a:80").is_ok());
+        assert_eq!(err("/a/b/c", &opts), (ErrorKind::TooManySegments, 4));
+        assert_eq!(err("///", &opts), (ErrorKind::TooManySegments, 2));
+        assert_eq!(err("http://a/b/c/", &opts), (ErrorKind::TooManySegments, 12));
+        assert_eq!(err("/?a&b&c", &opts), (ErrorKind::TooManyParams, 5));
+        assert_eq!(err("/?&&", &opts), (ErrorKind::TooManyParams, 3));
+        assert_eq!(err("/a", &ParseOptions::new().max_segments(0)),
+            (ErrorKind::TooManySegments, 0));
+        assert_eq!(err("/?a", &ParseOptions::new().max_params(0)),
+            (ErrorKind::TooManyParams, 2));
+        assert!(ParseOptions::new().max_params(0).parse("/?").is_ok());
+    }
+
+    #[test]
+    fn test_trim_whitespace() {
+        let opts = ParseOptions::new().trim_whitespace(true);
+        assert_eq!(opts.parse(" \t/a?b ").unwrap().as_str(), "/a?b");
+        assert_eq!(opts.parse("a:80\u{3000}").unwrap().as_str(), "a:80");
+        assert_eq!(err("  ", &opts), (ErrorKind::Empty, 0));
+        assert_eq!(err(" a/b ", &opts), (ErrorKind::SlashInAuthority, 2));
+        assert_eq!(err(" a:80 ", &ParseOptions::new()),
+            (ErrorKind::SurroundingWhitespace, 0));
+
+        let opts = opts.reject_fragment(true).max_len(6);
+        assert_eq!(err("  /a#b", &opts), (ErrorKind::Fragment, 4));
+        assert_eq!(err("  /a#bc", &opts), (ErrorKind::TooLong, 6));
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let opts = ParseOptions::new().non_ascii(NonAsciiPolicy::Reject);
+        assert_eq!(err("/a\u{e9}", &opts), (ErrorKind::NonAscii, 2));
+        assert!(opts.parse("/a%C3%A9").is_ok());
+
+        assert_eq!(bytes(b"/a\xc3\xa9", &opts), Err((ErrorKind::NonAscii, 2)));
+        assert_eq!(bytes(b"/a\xff", &opts), Err((ErrorKind::NonAscii, 2)));
+        assert_eq!(bytes(b"/a", &opts), Ok("/a".to_string()));
+
+        let opts = ParseOptions::new();
+        assert_eq!(bytes(b"/a\xc3\xa9", &opts), Ok("/a\u{e9}".to_string()));
+        assert_eq!(bytes(b"/a\xff", &opts), Err((ErrorKind::InvalidUtf8, 2)));
+
+        let opts = ParseOptions::new().non_ascii(NonAsciiPolicy::PercentEncode);
+        assert!(opts.parse("/a\u{e9}").is_ok());
+        assert_eq!(bytes(b"/a\xff?\x80", &opts), Ok("/a%FF?%80".to_string()));
+        assert_eq!(bytes(b"/abc", &opts), Ok("/abc".to_string()));
+        assert_eq!(bytes(b"/\xff\r", &opts), Err((ErrorKind::IllegalCharacter, 4)));
+        assert_eq!(bytes(b"/aaaaaaaaaaaaa\xff", &opts), Err((ErrorKind::TooLong, 14)));
+        assert_eq!(bytes(b"/\xff", &opts.check_chars(true)), Ok("/%FF".to_string()));
+    }
+
+    #[test]
+    fn test_backslash() {
+        let opts = ParseOptions::new().backslash(BackslashPolicy::Reject);
+        assert!(ParseOptions::new().parse("/a\\b").is_ok());
+        assert!(opts.parse("/a%5Cb").is_ok());
+        assert_eq!(err("/a\\b", &opts), (ErrorKind::Backslash, 2));
+        assert_eq!(err("/a?\\", &ParseOptions::strict()), (ErrorKind::Backslash, 3));
+
+        let opts = ParseOptions::new().backslash(BackslashPolicy::Normalize);
+        assert!(opts.parse("/a\\b").is_ok());
+        assert_eq!(bytes(b"/a\\..\\b", &opts), Ok("/a/../b".to_string()));
+        assert_eq!(bytes(b"/a/b", &opts), Ok("/a/b".to_string()));
+        assert_eq!(bytes(b"/aaaaaaaaaaaaaaa\\", &opts), Err((ErrorKind::TooLong, 16)));
+        let opts = opts.non_ascii(NonAsciiPolicy::PercentEncode);
+        assert_eq!(bytes(b"/\xff\\a", &opts), Ok("/%FF/a".to_string()));
+    }
+
+    #[test]
+    fn test_reject_encoded_line_breaks() {
+        let opts = ParseOptions::new().reject_encoded_line_breaks(true);
+        assert_eq!(err("/a%0D%0Ab", &opts), (ErrorKind::EncodedLineBreak, 2));
+        assert_eq!(err("/a?b=%0a", &opts), (ErrorKind::EncodedLineBreak, 5));
+        assert_eq!(err("/a\r\n", &opts), (ErrorKind::IllegalCharacter, 2));
+        assert!(opts.parse("/a%0B%0E%250D").is_ok());
+        assert!(ParseOptions::new().parse("/a%0D%0A").is_ok());
+    }
+
+    #[test]
+    fn test_strict() {
+        let opts = ParseOptions::strict();
+        assert_eq!(err("/%0d", &opts), (ErrorKind::EncodedLineBreak, 1));
+        assert_eq!(err("/a\u{e9}", &opts), (ErrorKind::NonAscii, 2));
+        assert_eq!(err("http://u@a/", &opts), (ErrorKind::Userinfo, 7));
+        assert_eq!(err("/a#b", &opts), (ErrorKind::Fragment, 2));
+        assert_eq!(err("/a b", &opts), (ErrorKind::ControlCharacter, 2));
+        assert_eq!(err("/a\"", &opts), (ErrorKind::InvalidPath, 2));
+        assert_eq!(err("exa mple.com:80", &opts), (ErrorKind::ControlCharacter, 3));
+        assert_eq!(err("a|b:80", &opts), (ErrorKind::InvalidHost, 1));
+        assert_eq!(err("http://a|b/", &opts), (ErrorKind::InvalidHost, 8));
+        assert!(ParsedTarget::parse_with("/a/b?c=d", &opts).is_ok());
+    }
+}
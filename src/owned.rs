@@ -0,0 +1,119 @@
+//! Owned target strings, available with the `alloc` feature.
+
+use alloc::borrow::{Borrow, ToOwned};
+use alloc::string::String;
+use core::fmt;
+use core::ops::Deref;
+
+use {ParseError, ParsedTarget, RequestTargetStr};
+
+/// Owned request target string that has already been successfully parsed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RequestTargetBuf(String);
+
+impl RequestTargetBuf {
+    /// Parse the given target, taking ownership of it if it's valid.
+    pub fn new(s: String) -> Result<Self, ParseError> {
+        RequestTargetStr::new(&s)?;
+        Ok(RequestTargetBuf(s))
+    }
+
+    /// Borrow the target.
+    pub fn as_target_str(&self) -> &RequestTargetStr {
+        RequestTargetStr::from_str_unchecked(&self.0)
+    }
+
+    /// Consume the target, returning the underlying string.
+    pub fn into_string(self) -> String { self.0 }
+}
+
+impl core::str::FromStr for RequestTargetBuf {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RequestTargetStr::new(s).map(ToOwned::to_owned)
+    }
+}
+
+impl Deref for RequestTargetBuf {
+    type Target = RequestTargetStr;
+
+    fn deref(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl Borrow<RequestTargetStr> for RequestTargetBuf {
+    fn borrow(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl Borrow<str> for RequestTargetBuf {
+    fn borrow(&self) -> &str { &self.0 }
+}
+
+impl AsRef<RequestTargetStr> for RequestTargetBuf {
+    fn as_ref(&self) -> &RequestTargetStr { self.as_target_str() }
+}
+
+impl AsRef<str> for RequestTargetBuf {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl ToOwned for RequestTargetStr {
+    type Owned = RequestTargetBuf;
+
+    fn to_owned(&self) -> RequestTargetBuf { RequestTargetBuf(self.as_str().to_owned()) }
+}
+
+impl<'a> From<&'a RequestTargetStr> for RequestTargetBuf {
+    fn from(s: &'a RequestTargetStr) -> Self { s.to_owned() }
+}
+
+impl<'a> From<ParsedTarget<'a>> for RequestTargetBuf {
+    fn from(t: ParsedTarget<'a>) -> Self { t.as_target_str().to_owned() }
+}
+
+impl From<RequestTargetBuf> for String {
+    fn from(t: RequestTargetBuf) -> Self { t.0 }
+}
+
+impl fmt::Debug for RequestTargetBuf {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt::Debug::fmt(&self.0, fmt) }
+}
+
+impl fmt::Display for RequestTargetBuf {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, fmt)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RequestTargetBuf {
+    fn format(&self, fmt: defmt::Formatter) { defmt::write!(fmt, "{=str}", &*self.0) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use RequestTarget;
+
+    #[test]
+    fn test_request_target_buf() {
+        use std::collections::HashMap;
+
+        let mut map = HashMap::new();
+        map.insert("/a?b".parse::<RequestTargetBuf>().unwrap(), 42);
+        map.insert(RequestTargetBuf::new("example.com:443".to_string()).unwrap(), 1337);
+
+        let t = ParsedTarget::parse("/a?b").unwrap();
+        assert_eq!(map.get(t.as_target_str()), Some(&42));
+        assert_eq!(map.get("example.com:443"), Some(&1337));
+        assert_eq!(map.get("/a"), None);
+
+        let buf = RequestTargetBuf::from(t);
+        assert_eq!(buf.kind(), RequestTarget::AbsPath);
+        assert_eq!(buf.parsed(), t);
+        assert_eq!(buf.into_string(), "/a?b");
+
+        assert!(RequestTargetBuf::new("a/b".to_string()).is_err());
+        assert!(" *".parse::<RequestTargetBuf>().is_err());
+    }
+}
@@ -0,0 +1,614 @@
+//! Splitting of paths into segments [RFC3986§3.3].
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+use core::iter::FusedIterator;
+use core::str;
+
+use {AbsoluteForm, ErrorKind, OriginForm, ParseError};
+#[cfg(feature = "alloc")]
+use decode::{percent_decode_str, InvalidEscapePolicy};
+use decode::{decodes_to, percent_decode_into, source_offset};
+use form::offset;
+
+/// Split the given path into its raw segments, the parts between each `/`.
+///
+/// Every `/` starts a segment, so `/` has a single empty segment, a trailing `/` adds an
+/// empty last segment, and `//` yields an empty segment between the slashes, while an
+/// empty path, such as that of `http://example.com`, has no segments at all
+/// [RFC3986§3.3]. A path of a relative reference, without a leading `/`, starts with the
+/// segment before its first `/`. The path ends at any `#` fragment, and segments are
+/// yielded without decoding, so an encoded `%2F` stays within its segment.
+///
+/// ```rust
+/// use uhttp_request_target::path_segments;
+///
+/// let mut segs = path_segments("/users/42/");
+///
+/// assert_eq!(segs.next(), Some("users"));
+/// assert_eq!(segs.next(), Some("42"));
+/// assert_eq!(segs.next(), Some(""));
+/// assert_eq!(segs.next(), None);
+/// ```
+pub fn path_segments(path: &str) -> Segments<'_> {
+    let path = &path[..path.find('#').unwrap_or(path.len())];
+
+    Segments {
+        path,
+        rest: path.strip_prefix('/').unwrap_or(path),
+        done: path.is_empty(),
+    }
+}
+
+/// Iterator over the raw segments of a path, from either end.
+///
+/// This is created by `path_segments`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Segments<'a> {
+    path: &'a str,
+    rest: &'a str,
+    done: bool,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.rest.find('/') {
+            Some(idx) => {
+                let seg = &self.rest[..idx];
+                self.rest = &self.rest[idx + 1..];
+                Some(seg)
+            },
+            None => {
+                self.done = true;
+                Some(self.rest)
+            },
+        }
+    }
+}
+
+impl<'a> DoubleEndedIterator for Segments<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.rest.rfind('/') {
+            Some(idx) => {
+                let seg = &self.rest[idx + 1..];
+                self.rest = &self.rest[..idx];
+                Some(seg)
+            },
+            None => {
+                self.done = true;
+                Some(self.rest)
+            },
+        }
+    }
+}
+
+impl<'a> FusedIterator for Segments<'a> {}
+
+impl<'a> Segments<'a> {
+    /// Split the matrix parameters off each remaining segment like `split_matrix`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::path_segments;
+    ///
+    /// let mut segs = path_segments("/users;sort=asc/42").with_matrix();
+    ///
+    /// let (name, mut params) = segs.next().unwrap();
+    /// assert_eq!(name, "users");
+    /// assert_eq!(params.next(), Some(("sort", "asc")));
+    /// assert_eq!(params.next(), None);
+    ///
+    /// let (name, mut params) = segs.next().unwrap();
+    /// assert_eq!(name, "42");
+    /// assert_eq!(params.next(), None);
+    /// ```
+    pub fn with_matrix(self) -> MatrixSegments<'a> { MatrixSegments(self) }
+
+    /// Check if the remaining segments begin with the given decoded segments.
+    ///
+    /// Whole segments are compared, so `/api/v1x` doesn't begin with `["api", "v1"]`, and
+    /// each is percent-decoded for the comparison without allocating, so `/%61pi` begins
+    /// with `["api"]`. A `%` that doesn't start a valid escape is compared as is. An
+    /// encoded `%2F` stays within its segment, so `/a%2Fb` begins with `["a/b"]` but not
+    /// `["a", "b"]`. Every path begins with no segments.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::path_segments;
+    ///
+    /// assert!(path_segments("/api/v1/users").starts_with(&["api", "v1"]));
+    /// assert!(path_segments("/api/v1").starts_with(&["api", "v1"]));
+    /// assert!(!path_segments("/api/v1x/users").starts_with(&["api", "v1"]));
+    /// assert!(!path_segments("/api").starts_with(&["api", "v1"]));
+    /// ```
+    pub fn starts_with(self, prefix: &[&str]) -> bool {
+        let mut segs = self;
+
+        prefix.iter().all(|&expected| match segs.next() {
+            Some(seg) => decodes_to(seg, expected, false),
+            None => false,
+        })
+    }
+
+    /// Percent-decode the next segment into the given buffer, returning the decoded
+    /// segment.
+    ///
+    /// The segment is decoded like `percent_decode_into` and must be UTF-8, so routers
+    /// can match a segment like `caf%C3%A9` against `café` without allocating. An encoded
+    /// `%2F` decodes to `/` within the segment. Errors are returned at their offset in the
+    /// path, and the segment is consumed either way. A buffer as long as the path is
+    /// always large enough.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::path_segments;
+    ///
+    /// let mut segs = path_segments("/caf%C3%A9/a%2Fb");
+    /// let mut buf = [0; 16];
+    ///
+    /// assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("caf\u{e9}")));
+    /// assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("a/b")));
+    /// assert_eq!(segs.next_decoded_into(&mut buf), None);
+    /// ```
+    pub fn next_decoded_into<'b>(&mut self, dst: &'b mut [u8])
+        -> Option<Result<&'b str, ParseError>>
+    {
+        let seg = self.next()?;
+        Some(decode_segment_into(seg, dst).map_err(|e| e.shift(offset(self.path, seg))))
+    }
+
+    /// Percent-decode each remaining segment like `percent_decode_str`, available with
+    /// the `alloc` feature.
+    ///
+    /// Each segment is borrowed unless it has escapes, and errors are yielded at their
+    /// offset in the path.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::path_segments;
+    ///
+    /// let segs = path_segments("/caf%C3%A9/menu").decoded()
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(segs, ["caf\u{e9}", "menu"]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn decoded(self) -> DecodedSegments<'a> { DecodedSegments(self) }
+
+    /// Percent-decode the given segment, reporting errors at path offsets.
+    #[cfg(feature = "alloc")]
+    fn decode(&self, seg: &'a str) -> Result<Cow<'a, str>, ParseError> {
+        percent_decode_str(seg, InvalidEscapePolicy::Error)
+            .map_err(|e| e.shift(offset(self.path, seg)))
+    }
+}
+
+/// Check if the given raw segment is a `.` or `..` segment, even if encoded.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_dot_segment(seg: &str) -> bool {
+    decodes_to(seg, ".", false) || decodes_to(seg, "..", false)
+}
+
+/// Percent-decode the given segment into the given buffer, checking that the result is
+/// UTF-8.
+pub(crate) fn decode_segment_into<'b>(seg: &str, dst: &'b mut [u8])
+    -> Result<&'b str, ParseError>
+{
+    let len = percent_decode_into(seg.as_bytes(), dst)?;
+
+    str::from_utf8(&dst[..len]).map_err(|e| {
+        let idx = source_offset(seg.as_bytes(), e.valid_up_to());
+        ParseError::at(ErrorKind::InvalidUtf8, seg.as_bytes(), idx)
+    })
+}
+
+/// Iterator over the percent-decoded segments of a path, from either end, available with
+/// the `alloc` feature.
+///
+/// This is created by `Segments::decoded`.
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct DecodedSegments<'a>(Segments<'a>);
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for DecodedSegments<'a> {
+    type Item = Result<Cow<'a, str>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let seg = self.0.next()?;
+        Some(self.0.decode(seg))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> DoubleEndedIterator for DecodedSegments<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let seg = self.0.next_back()?;
+        Some(self.0.decode(seg))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> FusedIterator for DecodedSegments<'a> {}
+
+/// Split the given raw segment into its name and its matrix parameters.
+///
+/// Matrix parameters follow the name of a segment, each introduced by `;` and split at
+/// its first `=` into a name and value, as in `/users;sort=asc;limit=10`. The convention
+/// comes from early web architecture notes rather than the URI grammar, which allows
+/// `;` and `=` in segments without giving them a meaning [RFC3986§3.3], so only APIs that
+/// use it should split on it. A parameter without `=` has an empty value and empty
+/// parameters are skipped.
+///
+/// ```rust
+/// use uhttp_request_target::split_matrix;
+///
+/// let (name, mut params) = split_matrix("users;sort=asc;;all");
+///
+/// assert_eq!(name, "users");
+/// assert_eq!(params.next(), Some(("sort", "asc")));
+/// assert_eq!(params.next(), Some(("all", "")));
+/// assert_eq!(params.next(), None);
+/// ```
+pub fn split_matrix(segment: &str) -> (&str, MatrixParams<'_>) {
+    match segment.find(';') {
+        Some(idx) => (&segment[..idx], MatrixParams(&segment[idx + 1..])),
+        None => (segment, MatrixParams("")),
+    }
+}
+
+/// Iterator over the raw `(name, value)` matrix parameters of a segment.
+///
+/// This is created by `split_matrix`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct MatrixParams<'a>(&'a str);
+
+impl<'a> Iterator for MatrixParams<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.0.is_empty() {
+            let (param, rest) = match self.0.find(';') {
+                Some(idx) => (&self.0[..idx], &self.0[idx + 1..]),
+                None => (self.0, ""),
+            };
+
+            self.0 = rest;
+
+            if param.is_empty() {
+                continue;
+            }
+
+            return Some(match param.find('=') {
+                Some(idx) => (&param[..idx], &param[idx + 1..]),
+                None => (param, ""),
+            });
+        }
+
+        None
+    }
+}
+
+impl<'a> FusedIterator for MatrixParams<'a> {}
+
+/// Iterator over the raw segments of a path, each split into its name and matrix
+/// parameters.
+///
+/// This is created by `Segments::with_matrix`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct MatrixSegments<'a>(Segments<'a>);
+
+impl<'a> Iterator for MatrixSegments<'a> {
+    type Item = (&'a str, MatrixParams<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> { self.0.next().map(split_matrix) }
+}
+
+impl<'a> DoubleEndedIterator for MatrixSegments<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> { self.0.next_back().map(split_matrix) }
+}
+
+impl<'a> FusedIterator for MatrixSegments<'a> {}
+
+/// Retrieve the file name of the given path, its last segment, unless the path is empty
+/// or ends in a slash.
+///
+/// The name is raw, so it may need decoding, and a name of `.` or `..` is returned as is.
+fn file_name(path: &str) -> Option<&str> {
+    path_segments(path).next_back().filter(|name| !name.is_empty())
+}
+
+/// Retrieve the extension of the given file name, after its last `.`, unless that's its
+/// first character or the name is `..`.
+fn extension(name: &str) -> Option<&str> {
+    match name.rfind('.') {
+        _ if name == ".." => None,
+        Some(0) | None => None,
+        Some(idx) => Some(&name[idx + 1..]),
+    }
+}
+
+impl<'a> OriginForm<'a> {
+    /// Iterate over the raw segments of the path like `path_segments`.
+    ///
+    /// The path always begins with a slash, so there's at least one segment.
+    pub fn segments(&self) -> Segments<'a> { path_segments(self.path()) }
+
+    /// Check if the path begins with the given decoded segments like
+    /// `Segments::starts_with`.
+    ///
+    /// This is the segment-wise alternative to a raw string prefix check for mounting
+    /// routes, which would wrongly match `/api/v1x` against `/api/v1`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::OriginForm;
+    ///
+    /// let t = OriginForm::parse("/api/v1/users?page=2").unwrap();
+    /// assert!(t.starts_with_segments(&["api", "v1"]));
+    /// assert!(!t.starts_with_segments(&["api", "v2"]));
+    /// ```
+    pub fn starts_with_segments(&self, prefix: &[&str]) -> bool {
+        self.segments().starts_with(prefix)
+    }
+
+    /// Retrieve the raw file name at the end of the path, such as `app.min.js` in
+    /// `/static/app.min.js`.
+    ///
+    /// A path ending in a slash names a directory and has no file name. The name isn't
+    /// decoded, and dot segments aren't resolved, so the path should be normalized first
+    /// if that matters.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::OriginForm;
+    ///
+    /// let t = OriginForm::parse("/static/app.min.js?v=2").unwrap();
+    /// assert_eq!(t.file_name(), Some("app.min.js"));
+    /// assert_eq!(t.extension(), Some("js"));
+    ///
+    /// let t = OriginForm::parse("/static/").unwrap();
+    /// assert_eq!(t.file_name(), None);
+    /// ```
+    pub fn file_name(&self) -> Option<&'a str> { file_name(self.path()) }
+
+    /// Retrieve the raw extension of the file name, after its last `.`, such as `js` in
+    /// `/static/app.min.js`.
+    ///
+    /// As with `std::path::Path::extension`, a name that only starts with a `.`, such as
+    /// `.htaccess`, has no extension, and one ending in a `.` has an empty extension.
+    /// The dot segments `.` and `..` have no extension either.
+    pub fn extension(&self) -> Option<&'a str> { self.file_name().and_then(extension) }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Iterate over the raw segments of the path like `path_segments`.
+    ///
+    /// An empty path has no segments, so use `path_or_root` to treat it like `/`.
+    pub fn segments(&self) -> Segments<'a> { path_segments(self.path()) }
+
+    /// Check if the path begins with the given decoded segments like
+    /// `Segments::starts_with`.
+    pub fn starts_with_segments(&self, prefix: &[&str]) -> bool {
+        self.segments().starts_with(prefix)
+    }
+
+    /// Retrieve the raw file name at the end of the path like `OriginForm::file_name`.
+    pub fn file_name(&self) -> Option<&'a str> { file_name(self.path()) }
+
+    /// Retrieve the raw extension of the file name like `OriginForm::extension`.
+    pub fn extension(&self) -> Option<&'a str> { self.file_name().and_then(extension) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn segs(s: &str) -> Vec<&str> { path_segments(s).collect() }
+
+    fn segs_rev(s: &str) -> Vec<&str> {
+        let mut v: Vec<_> = path_segments(s).rev().collect();
+        v.reverse();
+        v
+    }
+
+    #[test]
+    fn test_path_segments() {
+        let cases: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("#a", &[]),
+            ("/", &[""]),
+            ("/a", &["a"]),
+            ("/a/", &["a", ""]),
+            ("/a/b", &["a", "b"]),
+            ("//", &["", ""]),
+            ("//a", &["", "a"]),
+            ("/a//b/", &["a", "", "b", ""]),
+            ("/a%2Fb/c", &["a%2Fb", "c"]),
+            ("/a/b#c/d", &["a", "b"]),
+            ("/.././", &["..", ".", ""]),
+            ("a/b", &["a", "b"]),
+            ("a", &["a"]),
+        ];
+
+        for &(path, expected) in cases {
+            assert_eq!(segs(path), expected, "{}", path);
+            assert_eq!(segs_rev(path), expected, "{}", path);
+        }
+
+        let mut it = path_segments("/a/b/c");
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.next_back(), Some("c"));
+        assert_eq!(it.next_back(), Some("b"));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next_back(), None);
+
+        let mut it = path_segments("/a/");
+        assert_eq!(it.next_back(), Some(""));
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_starts_with() {
+        let starts = |s, prefix: &[&str]| path_segments(s).starts_with(prefix);
+
+        assert!(starts("/api/v1/a", &["api", "v1"]));
+        assert!(starts("/api/v1", &["api", "v1"]));
+        assert!(starts("/api/v1/", &["api", "v1"]));
+        assert!(starts("/api/v1/", &["api", "v1", ""]));
+        assert!(starts("/%61pi/v%31", &["api", "v1"]));
+        assert!(starts("/caf%C3%A9", &["caf\u{e9}"]));
+        assert!(starts("/a%2Fb/c", &["a/b"]));
+        assert!(starts("/a%zz", &["a%zz"]));
+        assert!(starts("/a", &[]));
+        assert!(starts("", &[]));
+        assert!(starts("//a", &["", "a"]));
+        assert!(!starts("/api/v1x/a", &["api", "v1"]));
+        assert!(!starts("/api", &["api", "v1"]));
+        assert!(!starts("/api/", &["api", "v1"]));
+        assert!(!starts("/API/v1", &["api", "v1"]));
+        assert!(!starts("/a%2Fb/c", &["a", "b"]));
+        assert!(!starts("/a/b#/c", &["a", "b", "c"]));
+        assert!(!starts("/a+b", &["a b"]));
+        assert!(!starts("", &[""]));
+
+        let t = OriginForm::parse("/a/b").unwrap();
+        assert!(t.starts_with_segments(&["a"]));
+        assert!(!t.starts_with_segments(&["b"]));
+        let t = AbsoluteForm::parse("http://a/b/c").unwrap();
+        assert!(t.starts_with_segments(&["b", "c"]));
+        assert!(!t.starts_with_segments(&["a"]));
+    }
+
+    fn matrix(s: &str) -> (&str, Vec<(&str, &str)>) {
+        let (name, params) = split_matrix(s);
+        (name, params.collect())
+    }
+
+    #[test]
+    fn test_split_matrix() {
+        assert_eq!(matrix(""), ("", vec![]));
+        assert_eq!(matrix("a"), ("a", vec![]));
+        assert_eq!(matrix("a;"), ("a", vec![]));
+        assert_eq!(matrix(";a=b"), ("", vec![("a", "b")]));
+        assert_eq!(matrix("a;b=c;d"), ("a", vec![("b", "c"), ("d", "")]));
+        assert_eq!(matrix("a;b=c=d;;=e"), ("a", vec![("b", "c=d"), ("", "e")]));
+        assert_eq!(matrix("a=b;c%3Bd"), ("a=b", vec![("c%3Bd", "")]));
+    }
+
+    #[test]
+    fn test_with_matrix() {
+        let segs = path_segments("/users;sort=asc/42;v/;x").with_matrix()
+            .map(|(name, params)| (name, params.collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(segs, [
+            ("users", vec![("sort", "asc")]),
+            ("42", vec![("v", "")]),
+            ("", vec![("x", "")]),
+        ]);
+
+        let mut it = path_segments("/a;b/c;d").with_matrix();
+        assert_eq!(it.next_back().map(|(name, _)| name), Some("c"));
+        assert_eq!(it.next().map(|(name, _)| name), Some("a"));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_next_decoded_into() {
+        let mut segs = path_segments("/%41/b%2f/%/%FF/x");
+        let mut buf = [0; 8];
+
+        assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("A")));
+        assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("b/")));
+
+        let e = segs.next_decoded_into(&mut buf).unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 10);
+        assert_eq!(e.byte(), Some(b'%'));
+
+        let e = segs.next_decoded_into(&mut buf).unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 12);
+        assert_eq!(e.byte(), Some(b'%'));
+
+        assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("x")));
+        assert_eq!(segs.next_decoded_into(&mut buf), None);
+
+        let mut segs = path_segments("/ab/abc");
+        let mut buf = [0; 2];
+        assert_eq!(segs.next_decoded_into(&mut buf), Some(Ok("ab")));
+        let e = segs.next_decoded_into(&mut buf).unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooLong);
+        assert_eq!(e.offset(), 6);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_decoded() {
+        let mut segs = path_segments("/a/%62/caf%C3%A9/%zz/").decoded();
+
+        assert!(matches!(segs.next(), Some(Ok(Cow::Borrowed("a")))));
+        assert!(matches!(segs.next(), Some(Ok(Cow::Owned(ref s))) if s == "b"));
+        assert_eq!(segs.next_back(), Some(Ok(Cow::Borrowed(""))));
+        assert_eq!(segs.next_back().unwrap().unwrap_err().offset(), 17);
+        assert_eq!(segs.next(), Some(Ok("caf\u{e9}".into())));
+        assert_eq!(segs.next(), None);
+
+        let e = path_segments("/a/%C3").decoded().nth(1).unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 3);
+    }
+
+    #[test]
+    fn test_file_name() {
+        let cases: &[(&str, Option<&str>, Option<&str>)] = &[
+            ("/", None, None),
+            ("/static/", None, None),
+            ("/app.min.js", Some("app.min.js"), Some("js")),
+            ("/static/app.min.js", Some("app.min.js"), Some("js")),
+            ("/a/README", Some("README"), None),
+            ("/a/.htaccess", Some(".htaccess"), None),
+            ("/a/.config.toml", Some(".config.toml"), Some("toml")),
+            ("/a/b.", Some("b."), Some("")),
+            ("/a.d/b", Some("b"), None),
+            ("/a/caf%C3%A9.t%78t", Some("caf%C3%A9.t%78t"), Some("t%78t")),
+            ("/a/..", Some(".."), None),
+            ("/a/.", Some("."), None),
+            ("/a/b.js?c.css", Some("b.js"), Some("js")),
+            ("/a/b.js#c.css", Some("b.js"), Some("js")),
+        ];
+
+        for &(s, name, ext) in cases {
+            let t = OriginForm::parse(s).unwrap();
+            assert_eq!(t.file_name(), name, "{}", s);
+            assert_eq!(t.extension(), ext, "{}", s);
+        }
+
+        let t = AbsoluteForm::parse("http://a/b/c.tar.gz").unwrap();
+        assert_eq!(t.file_name(), Some("c.tar.gz"));
+        assert_eq!(t.extension(), Some("gz"));
+        let t = AbsoluteForm::parse("http://a.com").unwrap();
+        assert_eq!(t.file_name(), None);
+        assert_eq!(t.extension(), None);
+    }
+
+    #[test]
+    fn test_form_segments() {
+        let t = OriginForm::parse("/a/b?c/d").unwrap();
+        assert_eq!(t.segments().collect::<Vec<_>>(), ["a", "b"]);
+        let t = OriginForm::parse("/?a").unwrap();
+        assert_eq!(t.segments().collect::<Vec<_>>(), [""]);
+
+        let t = AbsoluteForm::parse("http://a/b/c/").unwrap();
+        assert_eq!(t.segments().collect::<Vec<_>>(), ["b", "c", ""]);
+        let t = AbsoluteForm::parse("http://a?b/c").unwrap();
+        assert_eq!(t.segments().next(), None);
+    }
+}
@@ -0,0 +1,283 @@
+//! Longest-prefix matching of paths against a table of prefixes, available with the
+//! `alloc` feature.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use {ErrorKind, ParseError, ParsedTarget};
+use decode::{percent_decode, InvalidEscapePolicy};
+use form::offset;
+use path::{is_dot_segment, path_segments};
+
+/// Table mapping path prefixes to values, such as upstream servers, that selects the
+/// longest prefix of each path.
+///
+/// Prefixes match whole decoded segments like `Segments::starts_with`, so `/api` matches
+/// `/api` and `/api/users` but not `/apiary`, and a trailing slash on a prefix is
+/// ignored. The prefix `/` matches every path, so it can hold a default. Prefixes are
+/// stored in a tree keyed on segments, so a lookup does work proportional to the length
+/// of the path rather than to the number of prefixes.
+///
+/// ```rust
+/// use uhttp_request_target::{ParsedTarget, PrefixTable};
+///
+/// let mut table = PrefixTable::new();
+/// table.insert("/", "default").unwrap();
+/// table.insert("/api", "api").unwrap();
+/// table.insert("/api/v2/", "api-v2").unwrap();
+///
+/// let t = ParsedTarget::parse("/api/v2/users?page=2").unwrap();
+/// let m = table.route(&t).unwrap();
+/// assert_eq!(*m.value(), "api-v2");
+/// assert_eq!(m.rest(), "/users");
+///
+/// assert_eq!(*table.lookup("/api/v1/users").unwrap().value(), "api");
+/// assert_eq!(*table.lookup("/apiary").unwrap().value(), "default");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrefixTable<T> {
+    root: Node<T>,
+    len: usize,
+}
+
+/// Node in the tree of prefix segments.
+#[derive(Clone, Debug)]
+struct Node<T> {
+    /// Children under each segment, sorted by segment for binary search.
+    children: Vec<(String, Node<T>)>,
+    /// Prefix ending at this node, as inserted, and its value.
+    entry: Option<(String, T)>,
+}
+
+impl<T> Node<T> {
+    /// Create a node with no children or entry.
+    fn new() -> Self {
+        Node {
+            children: Vec::new(),
+            entry: None,
+        }
+    }
+}
+
+impl<T> PrefixTable<T> {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        PrefixTable {
+            root: Node::new(),
+            len: 0,
+        }
+    }
+
+    /// Retrieve the number of prefixes.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Check if there are no prefixes.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Insert the given prefix with the given value.
+    ///
+    /// The prefix is made of literal segments compared to the decoded segments of
+    /// paths, so a `%` in it is just a literal `%`. A prefix that doesn't begin with `/`
+    /// or that contains a `?` or `#` gives `ErrorKind::InvalidPattern` at the offending
+    /// offset. A prefix that's the same as an existing one, ignoring trailing slashes,
+    /// replaces it and returns its value.
+    pub fn insert(&mut self, prefix: &str, value: T) -> Result<Option<T>, ParseError> {
+        let invalid = |idx| {
+            ParseError::at(ErrorKind::InvalidPattern, prefix.as_bytes(), idx)
+        };
+
+        if !prefix.starts_with('/') {
+            return Err(invalid(0));
+        }
+
+        if let Some(idx) = prefix.find(['?', '#']) {
+            return Err(invalid(idx));
+        }
+
+        let mut node = &mut self.root;
+
+        for seg in path_segments(prefix.trim_end_matches('/')) {
+            let idx = match node.children.binary_search_by(|(s, _)| (**s).cmp(seg)) {
+                Ok(idx) => idx,
+                Err(idx) => {
+                    node.children.insert(idx, (seg.to_string(), Node::new()));
+                    idx
+                },
+            };
+
+            node = &mut node.children[idx].1;
+        }
+
+        let prev = node.entry.replace((prefix.to_string(), value));
+
+        if prev.is_none() {
+            self.len += 1;
+        }
+
+        Ok(prev.map(|(_, value)| value))
+    }
+
+    /// Select the longest prefix of the given path, if any matches.
+    ///
+    /// As with `path_segments`, the path ends at any `#` fragment, and `route` should be
+    /// used for a whole target with a query. As with `OriginForm::strip_prefix`, a path
+    /// whose rest has a `.` or `..` segment, even encoded, matches no prefix, since the
+    /// service would resolve `/api/../admin` to `/admin` outside its mount, so dot
+    /// segments should be removed first if such paths are to be served.
+    pub fn lookup<'r, 'a>(&'r self, path: &'a str) -> Option<PrefixMatch<'r, 'a, T>> {
+        let path = &path[..path.find('#').unwrap_or(path.len())];
+        let mut node = &self.root;
+        let mut found = node.entry.as_ref().map(|entry| (entry, 0));
+
+        for seg in path_segments(path) {
+            let decoded = percent_decode(seg, InvalidEscapePolicy::PassThrough)
+                .unwrap_or(Cow::Borrowed(seg.as_bytes()));
+
+            node = match node.children.binary_search_by(|(s, _)| s.as_bytes().cmp(&decoded)) {
+                Ok(idx) => &node.children[idx].1,
+                Err(_) => break,
+            };
+
+            if let Some(ref entry) = node.entry {
+                found = Some((entry, offset(path, seg) + seg.len()));
+            }
+        }
+
+        let ((prefix, value), end) = found?;
+        let rest = &path[end..];
+
+        if path_segments(rest).any(is_dot_segment) {
+            return None;
+        }
+
+        Some(PrefixMatch { prefix, value, rest })
+    }
+
+    /// Select the longest prefix of the path of the given target like `lookup`.
+    ///
+    /// An empty absolute-form path is treated like `/`, and authority-form and
+    /// asterisk-form targets, which have no path, never match.
+    pub fn route<'r, 'a>(&'r self, target: &ParsedTarget<'a>)
+        -> Option<PrefixMatch<'r, 'a, T>>
+    {
+        match *target {
+            ParsedTarget::AbsPath(ref form) => self.lookup(form.path()),
+            ParsedTarget::AbsUri(ref form) => self.lookup(form.path_or_root()),
+            ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => None,
+        }
+    }
+}
+
+impl<T> Default for PrefixTable<T> {
+    fn default() -> Self { PrefixTable::new() }
+}
+
+/// Prefix selected by `PrefixTable::lookup`.
+#[derive(Copy, Clone, Debug)]
+pub struct PrefixMatch<'r, 'a, T> {
+    prefix: &'r str,
+    value: &'r T,
+    rest: &'a str,
+}
+
+impl<'r, 'a, T> PrefixMatch<'r, 'a, T> {
+    /// Retrieve the selected prefix, as it was inserted.
+    pub fn prefix(&self) -> &'r str { self.prefix }
+
+    /// Retrieve the value of the selected prefix.
+    pub fn value(&self) -> &'r T { self.value }
+
+    /// Retrieve the raw rest of the path after the prefix, which is empty or begins with
+    /// `/`, for forwarding to a service mounted at the prefix.
+    pub fn rest(&self) -> &'a str { self.rest }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lookup<'a>(table: &PrefixTable<u32>, path: &'a str) -> Option<(u32, &'a str)> {
+        table.lookup(path).map(|m| (*m.value(), m.rest()))
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut table = PrefixTable::new();
+        assert!(table.is_empty());
+        assert_eq!(lookup(&table, "/a"), None);
+
+        assert_eq!(table.insert("/api", 1), Ok(None));
+        assert_eq!(table.insert("/api/v1/", 2), Ok(None));
+        assert_eq!(table.insert("/api/v1/users", 3), Ok(None));
+        assert_eq!(table.insert("/caf\u{e9}", 4), Ok(None));
+        assert_eq!(table.insert("/a//b", 5), Ok(None));
+        assert_eq!(table.len(), 5);
+
+        assert_eq!(lookup(&table, "/api"), Some((1, "")));
+        assert_eq!(lookup(&table, "/api/"), Some((1, "/")));
+        assert_eq!(lookup(&table, "/api/v2/x"), Some((1, "/v2/x")));
+        assert_eq!(lookup(&table, "/api/v1"), Some((2, "")));
+        assert_eq!(lookup(&table, "/api/v1x"), Some((1, "/v1x")));
+        assert_eq!(lookup(&table, "/api/v1/users/42"), Some((3, "/42")));
+        assert_eq!(lookup(&table, "/api/v1/user"), Some((2, "/user")));
+        assert_eq!(lookup(&table, "/%61pi/v%31/x"), Some((2, "/x")));
+        assert_eq!(lookup(&table, "/api/v1#/users"), Some((2, "")));
+        assert_eq!(lookup(&table, "/caf%C3%A9/menu"), Some((4, "/menu")));
+        assert_eq!(lookup(&table, "/a//b/c"), Some((5, "/c")));
+        assert_eq!(lookup(&table, "/apiary"), None);
+        assert_eq!(lookup(&table, "/API"), None);
+        assert_eq!(lookup(&table, "/a/b"), None);
+        assert_eq!(lookup(&table, "/"), None);
+        assert_eq!(lookup(&table, ""), None);
+
+        assert_eq!(table.insert("/", 0), Ok(None));
+        assert_eq!(lookup(&table, "/"), Some((0, "/")));
+        assert_eq!(lookup(&table, ""), Some((0, "")));
+        assert_eq!(lookup(&table, "/apiary"), Some((0, "/apiary")));
+        assert_eq!(lookup(&table, "/%FF"), Some((0, "/%FF")));
+
+        assert_eq!(lookup(&table, "/api/../admin"), None);
+        assert_eq!(lookup(&table, "/api/%2e%2e/admin"), None);
+        assert_eq!(lookup(&table, "/api/v1/./users"), None);
+        assert_eq!(lookup(&table, "/./api"), None);
+        assert_eq!(lookup(&table, "/api/..x"), Some((1, "/..x")));
+        assert_eq!(lookup(&table, "/api/v1#/../x"), Some((2, "")));
+
+        let m = table.lookup("/api/v1/x").unwrap();
+        assert_eq!(m.prefix(), "/api/v1/");
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut table = PrefixTable::default();
+
+        assert_eq!(table.insert("/a", 1), Ok(None));
+        assert_eq!(table.insert("/a/", 2), Ok(Some(1)));
+        assert_eq!(table.insert("", 3).unwrap_err().offset(), 0);
+        assert_eq!(table.insert("a", 3).unwrap_err().kind(), ErrorKind::InvalidPattern);
+        assert_eq!(table.insert("/a?b", 3).unwrap_err().offset(), 2);
+        assert_eq!(table.insert("/a#b", 3).unwrap_err().offset(), 2);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.lookup("/a/b").unwrap().prefix(), "/a/");
+    }
+
+    #[test]
+    fn test_route() {
+        let mut table = PrefixTable::new();
+        table.insert("/", 0).unwrap();
+        table.insert("/a", 1).unwrap();
+
+        let route = |s| table.route(&ParsedTarget::parse(s).unwrap()).map(|m| *m.value());
+
+        assert_eq!(route("/a/b?c"), Some(1));
+        assert_eq!(route("/b?/a"), Some(0));
+        assert_eq!(route("http://x/a/b"), Some(1));
+        assert_eq!(route("http://x"), Some(0));
+        assert_eq!(route("x:443"), None);
+        assert_eq!(route("*"), None);
+
+        let t = ParsedTarget::parse("http://x?a").unwrap();
+        assert_eq!(table.route(&t).unwrap().rest(), "/");
+    }
+}
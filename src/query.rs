@@ -0,0 +1,667 @@
+//! Splitting of queries into parameters [RFC3986§3.4].
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+use {AbsoluteForm, OriginForm};
+#[cfg(feature = "alloc")]
+use ParseError;
+use decode::decodes_to;
+#[cfg(feature = "alloc")]
+use decode::{percent_decode_str, InvalidEscapePolicy};
+#[cfg(feature = "alloc")]
+use form::offset;
+
+/// Split the given query into its raw `(key, value)` parameters.
+///
+/// Parameters are separated by `&` and split at their first `=`, following the
+/// `application/x-www-form-urlencoded` convention [RFC1866§8.2.1]. A parameter without
+/// `=` has an empty value, empty parameters are skipped, and the query ends at any `#`
+/// fragment. Keys and values are yielded without decoding, so no allocation is
+/// performed.
+///
+/// ```rust
+/// use uhttp_request_target::query_pairs;
+///
+/// let mut pairs = query_pairs("page=2&sort=new&&debug");
+///
+/// assert_eq!(pairs.next(), Some(("page", "2")));
+/// assert_eq!(pairs.next(), Some(("sort", "new")));
+/// assert_eq!(pairs.next(), Some(("debug", "")));
+/// assert_eq!(pairs.next(), None);
+/// ```
+pub fn query_pairs(query: &str) -> QueryPairs<'_> {
+    QueryPairs::new(query, &QueryOptions::new())
+}
+
+/// Iterator over the raw `(key, value)` parameters of a query.
+///
+/// This is created by `query_pairs` or `QueryOptions::pairs`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct QueryPairs<'a> {
+    query: &'a str,
+    rest: &'a str,
+    semicolons: bool,
+    plus_as_space: bool,
+}
+
+impl<'a> QueryPairs<'a> {
+    /// Create an iterator over the given query with the given options.
+    fn new(query: &'a str, opts: &QueryOptions) -> Self {
+        let end = query.find('#').unwrap_or(query.len());
+
+        QueryPairs {
+            query,
+            rest: &query[..end],
+            semicolons: opts.semicolons,
+            plus_as_space: opts.plus_as_space,
+        }
+    }
+
+    /// Decode each remaining key and value, available with the `alloc` feature.
+    ///
+    /// Each is decoded like `percent_decode_str`, with `+` as a space if enabled in the
+    /// options that created this iterator, and is borrowed unless it actually has
+    /// something to decode. An invalid escape or decoded bytes that aren't UTF-8 are
+    /// yielded as an error at their offset in the query, and iteration can continue past
+    /// them.
+    ///
+    /// ```rust
+    /// use std::borrow::Cow;
+    /// use uhttp_request_target::query_pairs;
+    ///
+    /// let mut pairs = query_pairs("q=caf%C3%A9&page=2").decoded();
+    /// let q = Cow::Owned("caf\u{e9}".into());
+    ///
+    /// assert_eq!(pairs.next(), Some(Ok((Cow::Borrowed("q"), q))));
+    /// assert_eq!(pairs.next(), Some(Ok((Cow::Borrowed("page"), Cow::Borrowed("2")))));
+    /// assert_eq!(pairs.next(), None);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn decoded(self) -> DecodedPairs<'a> { DecodedPairs(self) }
+}
+
+impl<'a> Iterator for QueryPairs<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let semicolons = self.semicolons;
+
+        while !self.rest.is_empty() {
+            let end = self.rest.find(|c| c == '&' || semicolons && c == ';');
+
+            let (param, rest) = match end {
+                Some(idx) => (&self.rest[..idx], &self.rest[idx + 1..]),
+                None => (self.rest, ""),
+            };
+
+            self.rest = rest;
+
+            if param.is_empty() {
+                continue;
+            }
+
+            return Some(match param.find('=') {
+                Some(idx) => (&param[..idx], &param[idx + 1..]),
+                None => (param, ""),
+            });
+        }
+
+        None
+    }
+}
+
+impl<'a> FusedIterator for QueryPairs<'a> {}
+
+/// Iterator over the decoded `(key, value)` parameters of a query, available with the
+/// `alloc` feature.
+///
+/// This is created by `QueryPairs::decoded`.
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct DecodedPairs<'a>(QueryPairs<'a>);
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for DecodedPairs<'a> {
+    type Item = Result<(Cow<'a, str>, Cow<'a, str>), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let QueryPairs { query, plus_as_space, .. } = self.0;
+
+        self.0.next().map(|(key, value)| {
+            Ok((decode(query, key, plus_as_space)?, decode(query, value, plus_as_space)?))
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> FusedIterator for DecodedPairs<'a> {}
+
+/// Map from decoded query keys to decoded values, available with the `alloc` feature.
+///
+/// Keys and values are borrowed from the query when they have no escapes.
+#[cfg(feature = "alloc")]
+pub type QueryMap<'a> = BTreeMap<Cow<'a, str>, Cow<'a, str>>;
+
+/// Collect the parameters of the given query into a map of decoded keys and values,
+/// available with the `alloc` feature.
+///
+/// The query is split like `query_pairs`, then each key and value is decoded like
+/// `percent_decode_str`, with invalid escapes and decoded bytes that aren't UTF-8
+/// rejected at their offset in the query. When a key appears more than once, its last
+/// value is kept.
+///
+/// ```rust
+/// use uhttp_request_target::query_map;
+///
+/// let map = query_map("q=caf%C3%A9&page=2&page=3").unwrap();
+///
+/// assert_eq!(map.len(), 2);
+/// assert_eq!(map["q"], "caf\u{e9}");
+/// assert_eq!(map["page"], "3");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn query_map(query: &str) -> Result<QueryMap<'_>, ParseError> {
+    let mut map = QueryMap::new();
+
+    for pair in query_pairs(query).decoded() {
+        let (key, value) = pair?;
+        map.insert(key, value);
+    }
+
+    Ok(map)
+}
+
+/// Map from decoded query keys to every decoded value kept for them, available with the
+/// `alloc` feature.
+#[cfg(feature = "alloc")]
+pub type QueryMultiMap<'a> = BTreeMap<Cow<'a, str>, Vec<Cow<'a, str>>>;
+
+/// Policy for a key that appears more than once in a query.
+///
+/// Frameworks disagree here: PHP and Rails keep the last value, while Go, Flask, and
+/// Servlets return the first by default and every value on request.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DuplicateKeyPolicy {
+    /// Keep the first value for the key.
+    First,
+    /// Keep the last value for the key.
+    Last,
+    /// Keep every value for the key, in order.
+    All,
+}
+
+/// Options controlling how queries are split into parameters and collected.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct QueryOptions {
+    duplicates: DuplicateKeyPolicy,
+    plus_as_space: bool,
+    semicolons: bool,
+}
+
+impl QueryOptions {
+    /// Create options that collect queries like `query_map`.
+    pub const fn new() -> Self {
+        QueryOptions {
+            duplicates: DuplicateKeyPolicy::Last,
+            plus_as_space: false,
+            semicolons: false,
+        }
+    }
+
+    /// Set the policy for a key that appears more than once, which is
+    /// `DuplicateKeyPolicy::Last` by default.
+    pub const fn duplicates(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Retrieve the policy for a key that appears more than once.
+    #[cfg(feature = "serde")]
+    pub(crate) const fn duplicate_policy(&self) -> DuplicateKeyPolicy { self.duplicates }
+
+    /// Set whether to decode `+` in keys and values as a space.
+    ///
+    /// This is the `application/x-www-form-urlencoded` convention used by HTML forms
+    /// [RFC1866§8.2.1], but `+` is an ordinary character in a URI query [RFC3986§3.4], so
+    /// it's decoded literally by default. An encoded `%2B` is always decoded as `+`.
+    pub const fn plus_as_space(mut self, plus: bool) -> Self {
+        self.plus_as_space = plus;
+        self
+    }
+
+    /// Set whether to also separate parameters with `;`.
+    ///
+    /// Some legacy clients and the HTML 4 recommendation use `;` in place of `&`, but
+    /// current guidance is to split on `&` only, since servers that disagree about `;`
+    /// can be tricked into seeing different parameters, as in cache poisoning attacks. A
+    /// `;` is part of the key or value by default.
+    pub const fn semicolons(mut self, semicolons: bool) -> Self {
+        self.semicolons = semicolons;
+        self
+    }
+
+    /// Split the given query into its raw `(key, value)` parameters like `query_pairs`,
+    /// with the separators enabled in these options.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::QueryOptions;
+    ///
+    /// let mut pairs = QueryOptions::new().semicolons(true).pairs("a=1;b=2&c");
+    ///
+    /// assert_eq!(pairs.next(), Some(("a", "1")));
+    /// assert_eq!(pairs.next(), Some(("b", "2")));
+    /// assert_eq!(pairs.next(), Some(("c", "")));
+    /// assert_eq!(pairs.next(), None);
+    /// ```
+    pub fn pairs<'a>(&self, query: &'a str) -> QueryPairs<'a> {
+        QueryPairs::new(query, self)
+    }
+
+    /// Look up the raw value of the given key in the given query, scanning its parameters
+    /// like `pairs` without allocating.
+    ///
+    /// The key is compared against each raw key after decoding, so `%70age` matches
+    /// `page`, and `+` is decoded as a space if enabled in these options. The value is
+    /// returned undecoded and can be decoded with `percent_decode_str`. When the key
+    /// appears more than once, the first value is returned under
+    /// `DuplicateKeyPolicy::First`, and the last otherwise.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{DuplicateKeyPolicy, QueryOptions};
+    ///
+    /// let first = QueryOptions::new().duplicates(DuplicateKeyPolicy::First);
+    ///
+    /// assert_eq!(first.get("page=2&q=a%20b&page=3", "page"), Some("2"));
+    /// assert_eq!(first.get("page=2&q=a%20b&page=3", "q"), Some("a%20b"));
+    /// assert_eq!(first.get("page=2&q=a%20b&page=3", "sort"), None);
+    /// ```
+    pub fn get<'a>(&self, query: &'a str, key: &str) -> Option<&'a str> {
+        let mut matches = self.pairs(query)
+            .filter(|&(k, _)| decodes_to(k, key, self.plus_as_space))
+            .map(|(_, v)| v);
+
+        match self.duplicates {
+            DuplicateKeyPolicy::First => matches.next(),
+            _ => matches.last(),
+        }
+    }
+
+    /// Collect the parameters of the given query into a map of decoded keys and values
+    /// like `query_map`, keeping the values for duplicate keys according to these
+    /// options, available with the `alloc` feature.
+    ///
+    /// Every key maps to at least one value, and exactly one unless the policy is
+    /// `DuplicateKeyPolicy::All`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::{DuplicateKeyPolicy, QueryOptions};
+    ///
+    /// let first = QueryOptions::new().duplicates(DuplicateKeyPolicy::First);
+    /// assert_eq!(first.map("a=1&a=2").unwrap()["a"], ["1"]);
+    ///
+    /// let all = QueryOptions::new().duplicates(DuplicateKeyPolicy::All);
+    /// assert_eq!(all.map("a=1&a=2").unwrap()["a"], ["1", "2"]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn map<'a>(&self, query: &'a str) -> Result<QueryMultiMap<'a>, ParseError> {
+        let mut map = QueryMultiMap::new();
+
+        for pair in self.pairs(query).decoded() {
+            let (key, value) = pair?;
+            let values = map.entry(key).or_default();
+
+            match self.duplicates {
+                DuplicateKeyPolicy::First if !values.is_empty() => {},
+                DuplicateKeyPolicy::Last => {
+                    values.clear();
+                    values.push(value);
+                },
+                _ => values.push(value),
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self { QueryOptions::new() }
+}
+
+/// Percent-decode the given part of the given query, optionally with `+` as a space,
+/// reporting errors at query offsets.
+#[cfg(feature = "alloc")]
+fn decode<'a>(query: &str, part: &'a str, plus_as_space: bool)
+    -> Result<Cow<'a, str>, ParseError>
+{
+    let shift = |e: ParseError| e.shift(offset(query, part));
+
+    if plus_as_space && part.contains('+') {
+        // Each `+` is replaced by a single byte, so offsets into the result still line up
+        // with the query.
+        let spaced = part.replace('+', " ");
+
+        return percent_decode_str(&spaced, InvalidEscapePolicy::Error)
+            .map(|s| Cow::Owned(s.into_owned()))
+            .map_err(shift);
+    }
+
+    percent_decode_str(part, InvalidEscapePolicy::Error).map_err(shift)
+}
+
+impl<'a> OriginForm<'a> {
+    /// Iterate over the raw `(key, value)` parameters of the query like `query_pairs`.
+    ///
+    /// A target without a query has no parameters.
+    pub fn query_pairs(&self) -> QueryPairs<'a> {
+        query_pairs(self.query().unwrap_or(""))
+    }
+
+    /// Look up the raw value of the given key in the query like `QueryOptions::get` with
+    /// the default options, returning its last value.
+    ///
+    /// This scans the query on each call, so it suits handlers that only need a few
+    /// parameters, while `query_map` is better for many.
+    pub fn query_get(&self, key: &str) -> Option<&'a str> {
+        QueryOptions::new().get(self.query().unwrap_or(""), key)
+    }
+
+    /// Collect the parameters of the query into a map like `query_map`, with errors
+    /// reported at their offset in the target, available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn query_map(&self) -> Result<QueryMap<'a>, ParseError> {
+        target_query(self.as_str(), self.query(), query_map)
+    }
+
+    /// Collect the parameters of the query into a map like `QueryOptions::map`, with
+    /// errors reported at their offset in the target, available with the `alloc`
+    /// feature.
+    #[cfg(feature = "alloc")]
+    pub fn query_map_with(&self, opts: &QueryOptions)
+        -> Result<QueryMultiMap<'a>, ParseError>
+    {
+        target_query(self.as_str(), self.query(), |q| opts.map(q))
+    }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Iterate over the raw `(key, value)` parameters of the query like `query_pairs`.
+    ///
+    /// A target without a query has no parameters.
+    pub fn query_pairs(&self) -> QueryPairs<'a> {
+        query_pairs(self.query().unwrap_or(""))
+    }
+
+    /// Look up the raw value of the given key in the query like `QueryOptions::get` with
+    /// the default options, returning its last value.
+    ///
+    /// This scans the query on each call, so it suits handlers that only need a few
+    /// parameters, while `query_map` is better for many.
+    pub fn query_get(&self, key: &str) -> Option<&'a str> {
+        QueryOptions::new().get(self.query().unwrap_or(""), key)
+    }
+
+    /// Collect the parameters of the query into a map like `query_map`, with errors
+    /// reported at their offset in the target, available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn query_map(&self) -> Result<QueryMap<'a>, ParseError> {
+        target_query(self.as_str(), self.query(), query_map)
+    }
+
+    /// Collect the parameters of the query into a map like `QueryOptions::map`, with
+    /// errors reported at their offset in the target, available with the `alloc`
+    /// feature.
+    #[cfg(feature = "alloc")]
+    pub fn query_map_with(&self, opts: &QueryOptions)
+        -> Result<QueryMultiMap<'a>, ParseError>
+    {
+        target_query(self.as_str(), self.query(), |q| opts.map(q))
+    }
+}
+
+/// Collect the given optional query of the given target with the given function,
+/// reporting errors at target offsets.
+#[cfg(feature = "alloc")]
+fn target_query<'a, T, F>(target: &'a str, query: Option<&'a str>, collect: F)
+    -> Result<T, ParseError>
+    where T: Default, F: FnOnce(&'a str) -> Result<T, ParseError>
+{
+    match query {
+        Some(q) => collect(q).map_err(|e| e.shift(offset(target, q))),
+        None => Ok(T::default()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pairs(s: &str) -> [Option<(&str, &str)>; 4] {
+        let mut it = query_pairs(s);
+        [it.next(), it.next(), it.next(), it.next()]
+    }
+
+    #[test]
+    fn test_query_pairs() {
+        assert_eq!(pairs(""), [None; 4]);
+        assert_eq!(pairs("&&&"), [None; 4]);
+        assert_eq!(pairs("a"), [Some(("a", "")), None, None, None]);
+        assert_eq!(pairs("a="), [Some(("a", "")), None, None, None]);
+        assert_eq!(pairs("=b"), [Some(("", "b")), None, None, None]);
+        assert_eq!(pairs("a=b=c&d"), [Some(("a", "b=c")), Some(("d", "")), None, None]);
+        assert_eq!(pairs("&a=%20+&b&"), [Some(("a", "%20+")), Some(("b", "")), None, None]);
+        assert_eq!(pairs("a;b=c"), [Some(("a;b", "c")), None, None, None]);
+        assert_eq!(pairs("a=1#b=2"), [Some(("a", "1")), None, None, None]);
+        assert_eq!(pairs("#a"), [None; 4]);
+        assert_eq!(pairs("a=?/&a=2"), [Some(("a", "?/")), Some(("a", "2")), None, None]);
+
+        let mut it = query_pairs("a");
+        assert_eq!(it.next(), Some(("a", "")));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_semicolons() {
+        let opts = QueryOptions::new().semicolons(true);
+        let pairs = |s| {
+            let mut it = opts.pairs(s);
+            [it.next(), it.next(), it.next(), it.next()]
+        };
+
+        assert_eq!(pairs("a;b=c"), [Some(("a", "")), Some(("b", "c")), None, None]);
+        assert_eq!(pairs(";;a&;b;"), [Some(("a", "")), Some(("b", "")), None, None]);
+        assert_eq!(pairs("a=%3B;b#;c"), [Some(("a", "%3B")), Some(("b", "")), None, None]);
+        assert_eq!(QueryOptions::new().pairs("a;b&c").collect::<Vec<_>>(),
+                   [("a;b", ""), ("c", "")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_decoded() {
+        use ErrorKind;
+
+        let mut it = query_pairs("a=1&%62=%7E&c=%zz&d+=+#e").decoded();
+        assert!(matches!(it.next(), Some(Ok((Cow::Borrowed("a"), Cow::Borrowed("1"))))));
+        assert!(matches!(it.next(), Some(Ok((Cow::Owned(ref b), Cow::Owned(ref v))))
+                                    if b == "b" && v == "~"));
+        let e = it.next().unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 14);
+        assert!(matches!(it.next(), Some(Ok((Cow::Borrowed("d+"), Cow::Borrowed("+"))))));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+
+        let opts = QueryOptions::new().plus_as_space(true).semicolons(true);
+        let pairs = opts.pairs("a+b=c+d;e=%FF").decoded().collect::<Vec<_>>();
+        assert_eq!(pairs[0], Ok(("a b".into(), "c d".into())));
+        let e = pairs[1].as_ref().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 10);
+        assert_eq!(pairs.len(), 2);
+    }
+
+    #[test]
+    fn test_get() {
+        let q = "a=1&b&%61=2&c+d=3&a=4;e=5#a=6";
+        let opts = QueryOptions::new();
+
+        assert_eq!(opts.get(q, "a"), Some("4;e=5"));
+        assert_eq!(opts.get(q, "b"), Some(""));
+        assert_eq!(opts.get(q, "c+d"), Some("3"));
+        assert_eq!(opts.get(q, "c d"), None);
+        assert_eq!(opts.get(q, "e"), None);
+        assert_eq!(opts.get(q, ""), None);
+        assert_eq!(opts.get("", "a"), None);
+        assert_eq!(opts.get("=1", ""), Some("1"));
+        assert_eq!(opts.get("%zz=1&%=2", "%zz"), Some("1"));
+        assert_eq!(opts.get("%zz=1&%=2", "%"), Some("2"));
+        assert_eq!(opts.get("%2=1", "%2"), Some("1"));
+        assert_eq!(opts.get("%41=1", "a"), None);
+        assert_eq!(opts.get("ab=1", "a"), None);
+        assert_eq!(opts.get("a=1", "ab"), None);
+
+        let opts = QueryOptions::new()
+            .duplicates(DuplicateKeyPolicy::First)
+            .plus_as_space(true)
+            .semicolons(true);
+        assert_eq!(opts.get(q, "a"), Some("1"));
+        assert_eq!(opts.get(q, "c d"), Some("3"));
+        assert_eq!(opts.get(q, "e"), Some("5"));
+
+        let opts = QueryOptions::new().duplicates(DuplicateKeyPolicy::All);
+        assert_eq!(opts.get(q, "a"), Some("4;e=5"));
+    }
+
+    #[test]
+    fn test_form_query_pairs() {
+        let t = OriginForm::parse("/a?b=c&d").unwrap();
+        assert_eq!(t.query_pairs().collect::<Vec<_>>(), [("b", "c"), ("d", "")]);
+        let t = OriginForm::parse("/a").unwrap();
+        assert_eq!(t.query_pairs().next(), None);
+        let t = OriginForm::parse("/a?").unwrap();
+        assert_eq!(t.query_pairs().next(), None);
+
+        let t = AbsoluteForm::parse("http://a/?b=c#d=e").unwrap();
+        assert_eq!(t.query_pairs().collect::<Vec<_>>(), [("b", "c")]);
+        let t = AbsoluteForm::parse("http://a").unwrap();
+        assert_eq!(t.query_pairs().next(), None);
+
+        let t = OriginForm::parse("/a?page=2&q=x&page=%33").unwrap();
+        assert_eq!(t.query_get("page"), Some("%33"));
+        assert_eq!(t.query_get("q"), Some("x"));
+        assert_eq!(OriginForm::parse("/a").unwrap().query_get("q"), None);
+        let t = AbsoluteForm::parse("http://a/?page=2#page=3").unwrap();
+        assert_eq!(t.query_get("page"), Some("2"));
+        assert_eq!(AbsoluteForm::parse("http://a").unwrap().query_get("q"), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_query_map() {
+        use ErrorKind;
+
+        assert!(query_map("").unwrap().is_empty());
+
+        let map = query_map("a=1&b&%62=2&c=%2F%20+&a=3#a=4").unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["a"], "3");
+        assert_eq!(map["b"], "2");
+        assert_eq!(map["c"], "/ +");
+        assert!(matches!(map.get("a"), Some(Cow::Borrowed(_))));
+        assert!(matches!(map.get("c"), Some(Cow::Owned(_))));
+
+        let e = query_map("a=1&b=%zz").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 6);
+        let e = query_map("a=1&%FF=b").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 4);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_form_query_map() {
+        let t = OriginForm::parse("/a?b=%41").unwrap();
+        assert_eq!(t.query_map().unwrap()["b"], "A");
+        assert!(OriginForm::parse("/a").unwrap().query_map().unwrap().is_empty());
+
+        let e = OriginForm::parse("/a?b=%4").unwrap().query_map().unwrap_err();
+        assert_eq!(e.offset(), 5);
+        assert_eq!(e.byte(), Some(b'%'));
+        let e = AbsoluteForm::parse("http://a/?%").unwrap().query_map().unwrap_err();
+        assert_eq!(e.offset(), 10);
+
+        let t = AbsoluteForm::parse("http://a?b=c").unwrap();
+        assert_eq!(t.query_map().unwrap()["b"], "c");
+
+        let opts = QueryOptions::new().duplicates(DuplicateKeyPolicy::All);
+        let t = OriginForm::parse("/a?b=1&b=%32").unwrap();
+        assert_eq!(t.query_map_with(&opts).unwrap()["b"], ["1", "2"]);
+        let e = AbsoluteForm::parse("http://a/?b&%").unwrap().query_map_with(&opts);
+        assert_eq!(e.unwrap_err().offset(), 12);
+        let t = AbsoluteForm::parse("http://a/").unwrap();
+        assert!(t.query_map_with(&opts).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_duplicate_key_policy() {
+        use self::DuplicateKeyPolicy::*;
+
+        assert_eq!(QueryOptions::default(), QueryOptions::new());
+
+        let q = "a=1&b=x&a=2&%61=3&c";
+        let first = QueryOptions::new().duplicates(First).map(q).unwrap();
+        assert_eq!(first["a"], ["1"]);
+        assert_eq!(first["b"], ["x"]);
+        assert_eq!(first["c"], [""]);
+        let last = QueryOptions::new().duplicates(Last).map(q).unwrap();
+        assert_eq!(last["a"], ["3"]);
+        assert_eq!(last, QueryOptions::new().map(q).unwrap());
+        let all = QueryOptions::new().duplicates(All).map(q).unwrap();
+        assert_eq!(all["a"], ["1", "2", "3"]);
+        assert_eq!(all["b"], ["x"]);
+        assert_eq!(all.len(), 3);
+
+        let e = QueryOptions::new().duplicates(First).map("a=1&a=%").unwrap_err();
+        assert_eq!(e.offset(), 6);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_plus_as_space() {
+        use ErrorKind;
+
+        let opts = QueryOptions::new().plus_as_space(true);
+
+        let map = opts.map("q=a+b%2Bc&a+b=+&c=%20").unwrap();
+        assert_eq!(map["q"], ["a b+c"]);
+        assert_eq!(map["a b"], [" "]);
+        assert_eq!(map["c"], [" "]);
+        assert!(matches!(opts.map("c=d").unwrap()["c"][0], Cow::Borrowed("d")));
+
+        let map = QueryOptions::new().map("q=a+b%2Bc").unwrap();
+        assert_eq!(map["q"], ["a+b+c"]);
+        assert_eq!(query_map("q=a+b").unwrap()["q"], "a+b");
+
+        let opts = opts.semicolons(true);
+        let map = opts.map("a=1;a=2&b=%3B").unwrap();
+        assert_eq!(map["a"], ["2"]);
+        assert_eq!(map["b"], [";"]);
+        let e = opts.map("a=1;b=%").unwrap_err();
+        assert_eq!(e.offset(), 6);
+
+        let e = opts.map("a=+++%+").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 5);
+        assert_eq!(e.byte(), Some(b'%'));
+    }
+}
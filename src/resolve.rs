@@ -0,0 +1,329 @@
+//! Resolution of references against a base target [RFC3986§5], available with the
+//! `alloc` feature.
+
+use alloc::string::String;
+
+use {ErrorKind, ParseError, ParsedTarget, RequestTargetBuf};
+use charset::{check, is_path_byte, is_query_byte};
+use form::offset;
+use normalize::remove_dot_segments;
+
+impl<'a> ParsedTarget<'a> {
+    /// Resolve the given URI reference against the target, producing the target it
+    /// refers to [RFC3986§5.2].
+    ///
+    /// This is how a relative reference like `../other?q` in a `Location` header is made
+    /// into a target that can be requested. The reference may be an absolute-path,
+    /// relative-path, query-only, or fragment-only reference against an origin-form or
+    /// absolute-form base, or a network-path reference like `//example.com/a` against an
+    /// absolute-form base. An absolute URI is taken as is, other than having its dot
+    /// segments removed. Any fragment on the base is ignored, and the fragment of the
+    /// reference is kept.
+    ///
+    /// A reference with illegal characters or malformed percent-encoding gives an error
+    /// at the offset into the reference. A base in authority or asterisk form, which has
+    /// no path to resolve against, and a reference that doesn't resolve to an
+    /// origin-form or absolute-form target, like `mailto:a@example.com`, give
+    /// `ErrorKind::UnresolvableReference`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::ParsedTarget;
+    ///
+    /// let base = ParsedTarget::parse("http://example.com/a/b/c?q").unwrap();
+    ///
+    /// assert_eq!(base.resolve("d").unwrap().as_str(), "http://example.com/a/b/d");
+    /// assert_eq!(base.resolve("../d?r").unwrap().as_str(), "http://example.com/a/d?r");
+    /// assert_eq!(base.resolve("?r").unwrap().as_str(), "http://example.com/a/b/c?r");
+    /// assert_eq!(base.resolve("//other.com").unwrap().as_str(), "http://other.com");
+    ///
+    /// let base = ParsedTarget::parse("/a/b/c").unwrap();
+    /// assert_eq!(base.resolve("./d/").unwrap().as_str(), "/a/b/d/");
+    /// ```
+    pub fn resolve(&self, reference: &str) -> Result<RequestTargetBuf, ParseError> {
+        let base = match *self {
+            ParsedTarget::AbsPath(ref form) => Base {
+                scheme: None,
+                authority: None,
+                path: form.path(),
+                query: form.query(),
+            },
+            ParsedTarget::AbsUri(ref form) => Base {
+                scheme: Some(form.scheme()),
+                authority: Some(form.authority()),
+                path: form.path(),
+                query: form.query(),
+            },
+            ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => {
+                return Err(ParseError::at(ErrorKind::UnresolvableReference, b"", 0));
+            },
+        };
+
+        let r = Reference::split(reference)?;
+        let target = base.resolve(&r)?;
+
+        RequestTargetBuf::new(target)
+    }
+}
+
+/// Components of a base target, which all end before any fragment.
+struct Base<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> Base<'a> {
+    /// Resolve the given reference against the base, returning the string of the
+    /// resolved target.
+    fn resolve(&self, r: &Reference) -> Result<String, ParseError> {
+        let mut target = String::new();
+
+        let (scheme, authority) = match (r.scheme, r.authority) {
+            (Some(_), _) => (r.scheme, r.authority),
+            (None, Some(_)) => (self.scheme, r.authority),
+            (None, None) => (self.scheme, self.authority),
+        };
+
+        match (scheme, authority) {
+            (Some(scheme), Some(authority)) => {
+                target.push_str(scheme);
+                target.push_str("://");
+                target.push_str(authority);
+            },
+            (None, None) => {},
+            // A URI without an authority, or an authority without a scheme, has no
+            // request-target form.
+            _ => return Err(ParseError::at(ErrorKind::UnresolvableReference, b"", 0)),
+        }
+
+        let query = if r.scheme.is_some() || r.authority.is_some() {
+            target.push_str(&remove_dot_segments(r.path));
+            r.query
+        } else if r.path.is_empty() {
+            target.push_str(self.path);
+            r.query.or(self.query)
+        } else if r.path.starts_with('/') {
+            target.push_str(&remove_dot_segments(r.path));
+            r.query
+        } else {
+            target.push_str(&remove_dot_segments(&self.merge(r.path)));
+            r.query
+        };
+
+        if let Some(query) = query {
+            target.push('?');
+            target.push_str(query);
+        }
+
+        if let Some(fragment) = r.fragment {
+            target.push('#');
+            target.push_str(fragment);
+        }
+
+        Ok(target)
+    }
+
+    /// Merge the given relative path with the path of the base [RFC3986§5.2.3].
+    fn merge(&self, path: &str) -> String {
+        let dir = if self.authority.is_some() && self.path.is_empty() {
+            "/"
+        } else {
+            &self.path[..self.path.rfind('/').map_or(0, |idx| idx + 1)]
+        };
+
+        let mut merged = String::with_capacity(dir.len() + path.len());
+        merged.push_str(dir);
+        merged.push_str(path);
+        merged
+    }
+}
+
+/// Components of a URI reference [RFC3986§4.1].
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    /// Split the given reference into components, checking each for illegal characters
+    /// [RFC3986§B].
+    fn split(s: &'a str) -> Result<Self, ParseError> {
+        let bytes = s.as_bytes();
+        let mut rest = s;
+
+        let scheme = match s.find([':', '/', '?', '#']) {
+            Some(idx) if bytes[idx] == b':' && is_scheme(&s[..idx]) => {
+                rest = &s[idx + 1..];
+                Some(&s[..idx])
+            },
+            _ => None,
+        };
+
+        let authority = match rest.strip_prefix("//") {
+            Some(after) => {
+                let end = after.find(['/', '?', '#']).unwrap_or(after.len());
+                rest = &after[end..];
+                Some(&after[..end])
+            },
+            None => None,
+        };
+
+        let (rest, fragment) = match rest.find('#') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+
+        let (path, query) = match rest.find('?') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+
+        let range = |part: &str| offset(s, part)..offset(s, part) + part.len();
+
+        if let Some(authority) = authority {
+            check(bytes, range(authority), is_authority_byte, ErrorKind::InvalidHost)?;
+        }
+
+        check(bytes, range(path), is_path_byte, ErrorKind::InvalidPath)?;
+
+        for part in query.iter().chain(fragment.iter()) {
+            check(bytes, range(part), is_query_byte, ErrorKind::InvalidQuery)?;
+        }
+
+        Ok(Reference { scheme, authority, path, query, fragment })
+    }
+}
+
+/// Check if the given string is a valid scheme name [RFC3986§3.1].
+fn is_scheme(s: &str) -> bool {
+    let mut bytes = s.bytes();
+
+    bytes.next().is_some_and(|b| b.is_ascii_alphabetic()) &&
+    bytes.all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'-' || b == b'.')
+}
+
+/// Check if the given byte may appear in an authority [RFC3986§3.2].
+fn is_authority_byte(b: u8) -> bool {
+    is_path_byte(b) && b != b'/' || b == b'[' || b == b']'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resolve(base: &str, r: &str) -> Result<String, ParseError> {
+        ParsedTarget::parse(base).unwrap().resolve(r).map(RequestTargetBuf::into_string)
+    }
+
+    #[test]
+    fn test_resolve_rfc() {
+        // The normal and abnormal examples of RFC 3986 §5.4.
+        let base = "http://a/b/c/d;p?q";
+
+        for &(r, t) in &[
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            (".g", "http://a/b/c/.g"),
+            ("g..", "http://a/b/c/g.."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("g?y/../x", "http://a/b/c/g?y/../x"),
+            ("g#s/./x", "http://a/b/c/g#s/./x"),
+            ("g#s/../x", "http://a/b/c/g#s/../x"),
+        ] {
+            assert_eq!(resolve(base, r).unwrap(), t, "{}", r);
+        }
+
+        // Under strict parsing, this is an absolute URI without an authority.
+        assert_eq!(resolve(base, "http:g").unwrap_err().kind(),
+                   ErrorKind::UnresolvableReference);
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("/a/b?q", "c").unwrap(), "/a/c");
+        assert_eq!(resolve("/a/b?q", "?r").unwrap(), "/a/b?r");
+        assert_eq!(resolve("/a/b?q", "").unwrap(), "/a/b?q");
+        assert_eq!(resolve("/a/b?q", "#f").unwrap(), "/a/b?q#f");
+        assert_eq!(resolve("/a/b?q#f", "").unwrap(), "/a/b?q");
+        assert_eq!(resolve("/a/b#f", "?r").unwrap(), "/a/b?r");
+        assert_eq!(resolve("/a/b#f", "").unwrap(), "/a/b");
+        assert_eq!(resolve("http://a#f", "b").unwrap(), "http://a/b");
+        assert_eq!(resolve("http://a:80#f", "?q").unwrap(), "http://a:80?q");
+        assert_eq!(resolve("http://a?q#f", "").unwrap(), "http://a?q");
+        assert_eq!(resolve("http://a#f", "#g").unwrap(), "http://a#g");
+        assert_eq!(resolve("/a/b", "../../../c").unwrap(), "/c");
+        assert_eq!(resolve("/a/b", "/c/./d").unwrap(), "/c/d");
+        assert_eq!(resolve("/", "a:b/c").unwrap_err().kind(),
+                   ErrorKind::UnresolvableReference);
+        assert_eq!(resolve("/a/b", "./a:b").unwrap(), "/a/a:b");
+        assert_eq!(resolve("/a/b", "https://c/./d?e").unwrap(), "https://c/d?e");
+        assert_eq!(resolve("/a/b", "//c/d").unwrap_err().kind(),
+                   ErrorKind::UnresolvableReference);
+
+        assert_eq!(resolve("http://a", "b").unwrap(), "http://a/b");
+        assert_eq!(resolve("http://a", "?b").unwrap(), "http://a?b");
+        assert_eq!(resolve("http://a?q", "").unwrap(), "http://a?q");
+        assert_eq!(resolve("https://u@a:8/b/c", "d").unwrap(), "https://u@a:8/b/d");
+        assert_eq!(resolve("http://a/b", "//[::1]:8/c").unwrap(), "http://[::1]:8/c");
+        assert_eq!(resolve("http://a/b", "HTTPS://c").unwrap(), "HTTPS://c");
+
+        let err = resolve("http://a/b", "c d").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidPath);
+        assert_eq!(err.offset(), 1);
+        let err = resolve("http://a/b", "c?d%zz").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(err.offset(), 3);
+        let err = resolve("http://a/b", "c#d#e").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidQuery);
+        assert_eq!(err.offset(), 3);
+        let err = resolve("http://a/b", "//c\\d").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidHost);
+        assert_eq!(err.offset(), 3);
+        let err = resolve("http://a/b", "//c:x/").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidPort);
+
+        for base in &["a:443", "*"] {
+            assert_eq!(resolve(base, "/b").unwrap_err().kind(),
+                       ErrorKind::UnresolvableReference);
+        }
+
+        assert_eq!(resolve("/a", "mailto:a@b").unwrap_err().kind(),
+                   ErrorKind::UnresolvableReference);
+    }
+}
@@ -0,0 +1,206 @@
+//! Rewriting of target paths under a prefix, available with the `alloc` feature.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use {AbsoluteForm, OriginForm};
+use decode::decodes_to;
+use form::offset;
+use path::{is_dot_segment, path_segments};
+
+impl<'a> OriginForm<'a> {
+    /// Strip the given path prefix from the target, producing the origin-form target the
+    /// service mounted at the prefix sees.
+    ///
+    /// The prefix matches whole segments, so `/api` matches `/api`, `/api/`, and
+    /// `/api/users`, but not `/apiary`, and a trailing slash on the prefix is ignored.
+    /// Segments are compared case-sensitively after percent-decoding, like
+    /// `Segments::starts_with` and `PrefixTable`, so `/%61pi/users` matches `/api` too.
+    /// The query is kept, and a path left empty becomes `/`, so `/api?q` becomes `/?q`.
+    /// The result is borrowed from the target unless that requires adding the `/`.
+    ///
+    /// A target the prefix doesn't match gives `None`, and so does one whose rest has a
+    /// `.` or `..` segment, even encoded, since the service would resolve
+    /// `/api/../admin` to `/admin` outside its mount. Dot segments should be removed
+    /// before stripping, such as with `remove_dot_segments`, if such targets are to be
+    /// served.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::OriginForm;
+    ///
+    /// let t = OriginForm::parse("/api/users?page=2").unwrap();
+    /// assert_eq!(t.strip_prefix("/api").unwrap(), "/users?page=2");
+    ///
+    /// let t = OriginForm::parse("/api?page=2").unwrap();
+    /// assert_eq!(t.strip_prefix("/api/").unwrap(), "/?page=2");
+    ///
+    /// let t = OriginForm::parse("/apiary").unwrap();
+    /// assert_eq!(t.strip_prefix("/api"), None);
+    /// ```
+    pub fn strip_prefix(&self, prefix: &str) -> Option<Cow<'a, str>> {
+        strip_prefix(self.as_str(), self.path(), prefix).map(rooted)
+    }
+
+    /// Replace the given path prefix of the target with another, producing the
+    /// origin-form target for a service mounted elsewhere.
+    ///
+    /// The prefix is matched like `strip_prefix`, and the replacement is joined to the
+    /// rest of the path with a single `/`, so `/old` can be replaced with `/new` or
+    /// `/new/` alike. A path left empty becomes `/`.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::OriginForm;
+    ///
+    /// let t = OriginForm::parse("/api/users?page=2").unwrap();
+    /// assert_eq!(t.replace_prefix("/api", "/v2/").unwrap(), "/v2/users?page=2");
+    /// assert_eq!(t.replace_prefix("/api/users", "").unwrap(), "/?page=2");
+    /// ```
+    pub fn replace_prefix(&self, prefix: &str, with: &str) -> Option<String> {
+        strip_prefix(self.as_str(), self.path(), prefix).map(|rest| replace(rest, with))
+    }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Strip the given path prefix from the path and query of the target like
+    /// `OriginForm::strip_prefix`, producing an origin-form target.
+    ///
+    /// The scheme and authority are dropped, and an empty path is treated like `/`.
+    pub fn strip_prefix(&self, prefix: &str) -> Option<Cow<'a, str>> {
+        strip_prefix(self.as_str(), self.path(), prefix).map(rooted)
+    }
+
+    /// Replace the given path prefix of the target with another like
+    /// `OriginForm::replace_prefix`, producing an origin-form target.
+    pub fn replace_prefix(&self, prefix: &str, with: &str) -> Option<String> {
+        strip_prefix(self.as_str(), self.path(), prefix).map(|rest| replace(rest, with))
+    }
+}
+
+/// Strip the given prefix from the given path of the given target, returning the rest of
+/// the target after it, if the prefix matches whole decoded segments and the rest of the
+/// path has no dot segments.
+fn strip_prefix<'a>(target: &'a str, path: &'a str, prefix: &str) -> Option<&'a str> {
+    let prefix = prefix.trim_end_matches('/');
+
+    if !prefix.is_empty() && !prefix.starts_with('/') {
+        return None;
+    }
+
+    let path = &path[..path.find('#').unwrap_or(path.len())];
+    let mut segs = path_segments(path);
+    let mut end = 0;
+
+    for expected in path_segments(prefix) {
+        let seg = segs.next()?;
+
+        if !decodes_to(seg, expected, false) {
+            return None;
+        }
+
+        end = offset(path, seg) + seg.len();
+    }
+
+    if segs.any(is_dot_segment) {
+        return None;
+    }
+
+    Some(&target[offset(target, path) + end..])
+}
+
+/// Make the given rest of a target into an origin-form target, adding a `/` before an
+/// empty path.
+fn rooted(rest: &str) -> Cow<'_, str> {
+    if rest.starts_with('/') {
+        Cow::Borrowed(rest)
+    } else if rest.is_empty() {
+        Cow::Borrowed("/")
+    } else {
+        let mut target = String::with_capacity(rest.len() + 1);
+        target.push('/');
+        target.push_str(rest);
+        Cow::Owned(target)
+    }
+}
+
+/// Join the given replacement prefix to the given rest of a target.
+fn replace(rest: &str, with: &str) -> String {
+    let with = with.trim_end_matches('/');
+    let rest = rooted(rest);
+
+    let mut target = String::with_capacity(with.len() + rest.len());
+
+    if !with.is_empty() && !with.starts_with('/') {
+        target.push('/');
+    }
+
+    target.push_str(with);
+    target.push_str(&rest);
+    target
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strip<'a>(s: &'a str, prefix: &str) -> Option<Cow<'a, str>> {
+        OriginForm::parse(s).unwrap().strip_prefix(prefix)
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        assert!(matches!(strip("/api/a?b", "/api"), Some(Cow::Borrowed("/a?b"))));
+        assert!(matches!(strip("/api", "/api"), Some(Cow::Borrowed("/"))));
+        assert!(matches!(strip("/api/", "/api"), Some(Cow::Borrowed("/"))));
+        assert!(matches!(strip("/api?q", "/api"), Some(Cow::Owned(ref s)) if s == "/?q"));
+        assert!(matches!(strip("/api#f", "/api"), Some(Cow::Owned(ref s)) if s == "/#f"));
+        assert_eq!(strip("/api/a/b/", "/api/").unwrap(), "/a/b/");
+        assert_eq!(strip("/api//a", "/api").unwrap(), "//a");
+        assert_eq!(strip("/api/v1/a", "/api/v1").unwrap(), "/a");
+        assert_eq!(strip("/a?b", "/").unwrap(), "/a?b");
+        assert_eq!(strip("/a?b", "").unwrap(), "/a?b");
+        assert_eq!(strip("/api?/x", "/api").unwrap(), "/?/x");
+
+        assert_eq!(strip("/apiary", "/api"), None);
+        assert_eq!(strip("/ap", "/api"), None);
+        assert_eq!(strip("/API/a", "/api"), None);
+        assert_eq!(strip("/%61pi/a", "/api").unwrap(), "/a");
+        assert_eq!(strip("/caf%C3%A9/a", "/caf\u{e9}").unwrap(), "/a");
+        assert_eq!(strip("/api", "api"), None);
+        assert_eq!(strip("/api/../admin", "/api"), None);
+        assert_eq!(strip("/api/a/./b", "/api"), None);
+        assert_eq!(strip("/api/%2E%2e/admin", "/api"), None);
+        assert_eq!(strip("/api/..a/b", "/api").unwrap(), "/..a/b");
+        assert_eq!(strip("/api/a?/../b", "/api").unwrap(), "/a?/../b");
+        assert_eq!(strip("/a?/api", "/api"), None);
+        assert_eq!(strip("/a#/api", "/api"), None);
+        assert_eq!(strip("/", "/api"), None);
+
+        let t = AbsoluteForm::parse("http://a/api/b?c").unwrap();
+        assert_eq!(t.strip_prefix("/api").unwrap(), "/b?c");
+        let t = AbsoluteForm::parse("http://a?c").unwrap();
+        assert_eq!(t.strip_prefix("/").unwrap(), "/?c");
+        assert_eq!(t.strip_prefix("/api"), None);
+    }
+
+    #[test]
+    fn test_replace_prefix() {
+        let replace = |s, prefix, with| {
+            OriginForm::parse(s).unwrap().replace_prefix(prefix, with)
+        };
+
+        assert_eq!(replace("/api/a?b", "/api", "/v2").unwrap(), "/v2/a?b");
+        assert_eq!(replace("/api/a?b", "/api/", "/v2/").unwrap(), "/v2/a?b");
+        assert_eq!(replace("/api/a?b", "/api", "v2").unwrap(), "/v2/a?b");
+        assert_eq!(replace("/api/a?b", "/api", "").unwrap(), "/a?b");
+        assert_eq!(replace("/api/a?b", "/api", "/").unwrap(), "/a?b");
+        assert_eq!(replace("/api?b", "/api", "/v2").unwrap(), "/v2/?b");
+        assert_eq!(replace("/api", "/api", "").unwrap(), "/");
+        assert_eq!(replace("/a", "/", "/b").unwrap(), "/b/a");
+        assert_eq!(replace("/apiary", "/api", "/v2"), None);
+        assert_eq!(replace("/api/../admin", "/api", "/v2"), None);
+        assert_eq!(replace("/%61pi/a", "/api", "/v2").unwrap(), "/v2/a");
+
+        let t = AbsoluteForm::parse("http://a/api/b").unwrap();
+        assert_eq!(t.replace_prefix("/api", "/v2").unwrap(), "/v2/b");
+    }
+}
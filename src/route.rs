@@ -0,0 +1,417 @@
+//! Matching of paths against route patterns with named captures.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+use {AbsoluteForm, ErrorKind, OriginForm, ParseError};
+#[cfg(feature = "alloc")]
+use decode::{percent_decode_str, InvalidEscapePolicy};
+use decode::decodes_to;
+use form::offset;
+use path::{path_segments, Segments};
+
+/// Route pattern, such as `/users/{id}/posts/{post}`, that matches paths segment by
+/// segment.
+///
+/// Each segment of the pattern is either literal text, which must equal the decoded path
+/// segment, or a `{name}` capture, which matches any non-empty segment. Captures span
+/// whole segments, so `/files/{name}` doesn't match `/files/a/b`, and the path must have
+/// exactly as many segments as the pattern, so a trailing slash is significant. Literal
+/// text is compared to the decoded segment, so `/caf%C3%A9` matches the pattern
+/// `/café`, and a `%` in the pattern is just a literal `%`. As with `path_segments`, the
+/// path ends at any `#` fragment.
+///
+/// Matching does no allocation and the pattern is borrowed, so patterns can be parsed
+/// once from string constants and kept for the life of the program.
+///
+/// ```rust
+/// use uhttp_request_target::{OriginForm, RoutePattern};
+///
+/// let route = RoutePattern::parse("/users/{id}/posts/{post}").unwrap();
+///
+/// assert!(OriginForm::parse("/users/42/posts/7?page=2").unwrap().matches_route(&route));
+/// assert!(!OriginForm::parse("/users/42/posts").unwrap().matches_route(&route));
+/// assert!(!OriginForm::parse("/users/42/posts/7/").unwrap().matches_route(&route));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RoutePattern<'p> {
+    pattern: &'p str,
+    captures: usize,
+}
+
+impl<'p> RoutePattern<'p> {
+    /// Parse the given route pattern.
+    ///
+    /// The pattern must begin with `/` and may not contain a `?` or `#`. A segment
+    /// starting with `{` must be a whole capture with a non-empty name that's distinct
+    /// from every other capture in the pattern, and braces are otherwise not allowed.
+    /// Errors are `ErrorKind::InvalidPattern` at the offending offset in the pattern.
+    pub fn parse(pattern: &'p str) -> Result<Self, ParseError> {
+        let invalid = |idx| {
+            ParseError::at(ErrorKind::InvalidPattern, pattern.as_bytes(), idx)
+        };
+
+        if !pattern.starts_with('/') {
+            return Err(invalid(0));
+        }
+
+        if let Some(idx) = pattern.find(['?', '#']) {
+            return Err(invalid(idx));
+        }
+
+        let mut captures = 0;
+
+        for seg in path_segments(pattern) {
+            let start = offset(pattern, seg);
+
+            let name = match seg.strip_prefix('{') {
+                Some(rest) => match rest.strip_suffix('}') {
+                    Some(name) if !name.is_empty() => name,
+                    _ => return Err(invalid(start + seg.len())),
+                },
+                None => match seg.find(['{', '}']) {
+                    Some(idx) => return Err(invalid(start + idx)),
+                    None => continue,
+                },
+            };
+
+            if let Some(idx) = name.find(['{', '}']) {
+                return Err(invalid(start + 1 + idx));
+            }
+
+            if capture_names(pattern).take(captures).any(|prev| prev == name) {
+                return Err(invalid(start));
+            }
+
+            captures += 1;
+        }
+
+        Ok(RoutePattern { pattern, captures })
+    }
+
+    /// Retrieve the pattern string.
+    pub fn as_str(&self) -> &'p str { self.pattern }
+
+    /// Retrieve the number of captures in the pattern.
+    pub fn capture_count(&self) -> usize { self.captures }
+
+    /// Iterate over the names of the captures in the pattern, in order.
+    pub fn capture_names(&self) -> CaptureNames<'p> { capture_names(self.pattern) }
+
+    /// Check if the given path matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.walk(path, |_| {})
+    }
+
+    /// Match the given path against the pattern, writing the raw value of each capture
+    /// into the given buffer in order, without decoding or allocating.
+    ///
+    /// This returns `false` if the path doesn't match or the buffer is shorter than
+    /// `capture_count`, in which case the buffer contents are unspecified.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RoutePattern;
+    ///
+    /// let route = RoutePattern::parse("/users/{id}/posts/{post}").unwrap();
+    /// let mut caps = [""; 2];
+    ///
+    /// assert!(route.captures_into("/users/42/posts/a%20b", &mut caps));
+    /// assert_eq!(caps, ["42", "a%20b"]);
+    /// ```
+    pub fn captures_into<'a>(&self, path: &'a str, dst: &mut [&'a str]) -> bool {
+        if dst.len() < self.captures {
+            return false;
+        }
+
+        let mut idx = 0;
+
+        self.walk(path, |seg| {
+            dst[idx] = seg;
+            idx += 1;
+        })
+    }
+
+    /// Match the given path against the pattern, percent-decoding the value of each
+    /// capture, available with the `alloc` feature.
+    ///
+    /// This returns `None` if the path doesn't match. A captured segment that doesn't
+    /// decode to UTF-8 gives an error at its offset in the path, so the request can be
+    /// rejected rather than routed elsewhere.
+    ///
+    /// ```rust
+    /// use uhttp_request_target::RoutePattern;
+    ///
+    /// let route = RoutePattern::parse("/users/{id}/posts/{post}").unwrap();
+    /// let caps = route.captures("/users/42/posts/hello%20world").unwrap().unwrap();
+    ///
+    /// assert_eq!(caps.get("id"), Some("42"));
+    /// assert_eq!(caps.get("post"), Some("hello world"));
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn captures<'a>(&self, path: &'a str)
+        -> Option<Result<Captures<'p, 'a>, ParseError>>
+    {
+        let mut raw = Vec::with_capacity(self.captures);
+
+        if !self.walk(path, |seg| raw.push(seg)) {
+            return None;
+        }
+
+        Some(decode_captures(path, self.capture_names().zip(raw)))
+    }
+
+    /// Match the given path against the pattern, passing each captured segment to the
+    /// given function in order.
+    fn walk<'a, F: FnMut(&'a str)>(&self, path: &'a str, mut capture: F) -> bool {
+        let mut segs = path_segments(path);
+
+        for pat in path_segments(self.pattern) {
+            let seg = match segs.next() {
+                Some(seg) => seg,
+                None => return false,
+            };
+
+            if pat.starts_with('{') {
+                if seg.is_empty() {
+                    return false;
+                }
+
+                capture(seg);
+            } else if !decodes_to(seg, pat, false) {
+                return false;
+            }
+        }
+
+        segs.next().is_none()
+    }
+}
+
+/// Iterate over the names of the captures in the given pattern.
+fn capture_names(pattern: &str) -> CaptureNames<'_> {
+    CaptureNames(path_segments(pattern))
+}
+
+/// Iterator over the names of the captures in a route pattern.
+///
+/// This is created by `RoutePattern::capture_names`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CaptureNames<'p>(Segments<'p>);
+
+impl<'p> Iterator for CaptureNames<'p> {
+    type Item = &'p str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.by_ref()
+            .find_map(|seg| seg.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+    }
+}
+
+impl<'p> FusedIterator for CaptureNames<'p> {}
+
+/// Decoded captures of a path matched against a route pattern or path template,
+/// available with the `alloc` feature.
+///
+/// This is created by `RoutePattern::captures` and `PathTemplate::captures`, and is part
+/// of each match of a `Router` or `PathTemplates`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Captures<'p, 'a>(pub(crate) Vec<(&'p str, Cow<'a, str>)>);
+
+/// Percent-decode the given named raw captures of the given path, reporting errors at
+/// path offsets.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode_captures<'p, 'a, I>(path: &'a str, raw: I)
+    -> Result<Captures<'p, 'a>, ParseError>
+    where I: IntoIterator<Item = (&'p str, &'a str)>
+{
+    raw.into_iter()
+        .map(|(name, seg)| {
+            percent_decode_str(seg, InvalidEscapePolicy::Error)
+                .map(|value| (name, value))
+                .map_err(|e| e.shift(offset(path, seg)))
+        })
+        .collect::<Result<_, _>>()
+        .map(Captures)
+}
+
+#[cfg(feature = "alloc")]
+impl<'p, 'a> Captures<'p, 'a> {
+    /// Retrieve the decoded value of the capture with the given name, if the pattern has
+    /// one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|&&(n, _)| n == name).map(|(_, value)| &**value)
+    }
+
+    /// Retrieve the number of captures.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Check if there are no captures.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Iterate over the name and decoded value of each capture, in pattern order.
+    pub fn iter(&self) -> core::slice::Iter<'_, (&'p str, Cow<'a, str>)> { self.0.iter() }
+
+    /// Consume the captures, returning the name and decoded value of each.
+    pub fn into_vec(self) -> Vec<(&'p str, Cow<'a, str>)> { self.0 }
+}
+
+impl<'a> OriginForm<'a> {
+    /// Check if the path matches the given route pattern.
+    pub fn matches_route(&self, route: &RoutePattern) -> bool {
+        route.is_match(self.path())
+    }
+
+    /// Match the path against the given route pattern like `RoutePattern::captures`,
+    /// available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn route<'p>(&self, route: &RoutePattern<'p>)
+        -> Option<Result<Captures<'p, 'a>, ParseError>>
+    {
+        route.captures(self.path())
+    }
+}
+
+impl<'a> AbsoluteForm<'a> {
+    /// Check if the path, or `/` if it's empty, matches the given route pattern.
+    pub fn matches_route(&self, route: &RoutePattern) -> bool {
+        route.is_match(self.path_or_root())
+    }
+
+    /// Match the path, or `/` if it's empty, against the given route pattern like
+    /// `RoutePattern::captures`, available with the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn route<'p>(&self, route: &RoutePattern<'p>)
+        -> Option<Result<Captures<'p, 'a>, ParseError>>
+    {
+        route.captures(self.path_or_root())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn err(pattern: &str) -> usize {
+        let e = RoutePattern::parse(pattern).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPattern);
+        e.offset()
+    }
+
+    #[test]
+    fn test_parse() {
+        let route = RoutePattern::parse("/users/{id}/posts/{post}").unwrap();
+        assert_eq!(route.as_str(), "/users/{id}/posts/{post}");
+        assert_eq!(route.capture_count(), 2);
+        assert_eq!(route.capture_names().collect::<Vec<_>>(), ["id", "post"]);
+
+        for &pattern in &["/", "/a", "/a/", "//", "/{a}/{b}/", "/a%zz", "/{\u{e9}}"] {
+            assert!(RoutePattern::parse(pattern).is_ok(), "{}", pattern);
+        }
+
+        assert_eq!(err(""), 0);
+        assert_eq!(err("a"), 0);
+        assert_eq!(err("{a}"), 0);
+        assert_eq!(err("/a?b"), 2);
+        assert_eq!(err("/a#b"), 2);
+        assert_eq!(err("/{}"), 3);
+        assert_eq!(err("/{a"), 3);
+        assert_eq!(err("/{a}b"), 5);
+        assert_eq!(err("/a{b}"), 2);
+        assert_eq!(err("/a}"), 2);
+        assert_eq!(err("/{a{b}"), 3);
+        assert_eq!(err("/{a}/{b}/{a}"), 9);
+    }
+
+    #[test]
+    fn test_is_match() {
+        let route = RoutePattern::parse("/users/{id}/posts/{post}").unwrap();
+
+        assert!(route.is_match("/users/1/posts/2"));
+        assert!(route.is_match("/users/1/posts/2#a/b"));
+        assert!(route.is_match("/%75sers/1/posts/2"));
+        assert!(!route.is_match("/users/1/posts/2/"));
+        assert!(!route.is_match("/users/1/posts"));
+        assert!(!route.is_match("/users//posts/2"));
+        assert!(!route.is_match("/users/1/post/2"));
+        assert!(!route.is_match("/Users/1/posts/2"));
+        assert!(!route.is_match("/users/1/2/posts/3"));
+
+        let route = RoutePattern::parse("/").unwrap();
+        assert!(route.is_match("/"));
+        assert!(!route.is_match("//"));
+        assert!(!route.is_match(""));
+
+        let route = RoutePattern::parse("/caf\u{e9}/").unwrap();
+        assert!(route.is_match("/caf%C3%A9/"));
+        assert!(!route.is_match("/caf%C3%A9"));
+    }
+
+    #[test]
+    fn test_captures_into() {
+        let route = RoutePattern::parse("/a/{x}/{y}").unwrap();
+        let mut buf = [""; 3];
+
+        assert!(route.captures_into("/a/b%20c/d", &mut buf));
+        assert_eq!(buf, ["b%20c", "d", ""]);
+        assert!(!route.captures_into("/a/b", &mut buf));
+        assert!(!route.captures_into("/a/b/c", &mut buf[..1]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_captures() {
+        let route = RoutePattern::parse("/a/{x}/{y}").unwrap();
+
+        let caps = route.captures("/a/b%2Fc/caf%C3%A9").unwrap().unwrap();
+        assert_eq!(caps.len(), 2);
+        assert!(!caps.is_empty());
+        assert_eq!(caps.get("x"), Some("b/c"));
+        assert_eq!(caps.get("y"), Some("caf\u{e9}"));
+        assert_eq!(caps.get("z"), None);
+        assert_eq!(caps.iter().map(|&(n, ref v)| (n, &**v)).collect::<Vec<_>>(),
+                   [("x", "b/c"), ("y", "caf\u{e9}")]);
+        assert!(matches!(caps.into_vec()[0].1, Cow::Owned(_)));
+
+        let caps = route.captures("/a/b/c").unwrap().unwrap();
+        assert!(matches!(caps.into_vec()[0].1, Cow::Borrowed("b")));
+
+        assert!(route.captures("/a/b").is_none());
+
+        let e = route.captures("/a/b/%FF").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 5);
+        let e = route.captures("/a/%zz/c").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPercentEncoding);
+        assert_eq!(e.offset(), 3);
+
+        let caps = RoutePattern::parse("/a").unwrap().captures("/a").unwrap().unwrap();
+        assert!(caps.is_empty());
+    }
+
+    #[test]
+    fn test_matches_route() {
+        let route = RoutePattern::parse("/{x}").unwrap();
+
+        assert!(OriginForm::parse("/a%20b?c").unwrap().matches_route(&route));
+        assert!(AbsoluteForm::parse("http://a/b").unwrap().matches_route(&route));
+
+        let t = AbsoluteForm::parse("http://a").unwrap();
+        assert!(!t.matches_route(&route));
+        assert!(t.matches_route(&RoutePattern::parse("/").unwrap()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_route() {
+        let route = RoutePattern::parse("/{x}").unwrap();
+
+        let t = OriginForm::parse("/a%20b?c").unwrap();
+        assert_eq!(t.route(&route).unwrap().unwrap().get("x"), Some("a b"));
+        let t = AbsoluteForm::parse("http://a/b").unwrap();
+        assert_eq!(t.route(&route).unwrap().unwrap().get("x"), Some("b"));
+        assert!(OriginForm::parse("/").unwrap().route(&route).is_none());
+    }
+}
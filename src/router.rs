@@ -0,0 +1,377 @@
+//! Routing of origin-form paths through a tree of route patterns, available with the
+//! `alloc` feature.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use {ParsedTarget, ParseError};
+use decode::{percent_decode, InvalidEscapePolicy};
+use path::path_segments;
+use route::{decode_captures, Captures, RoutePattern};
+
+/// Router mapping route patterns to values, such as handlers.
+///
+/// Patterns use the syntax of `RoutePattern`, and are stored in a tree keyed on path
+/// segments, so a lookup does work proportional to the length of the path rather than to
+/// the number of routes. At each segment, a literal is preferred over a capture, and a
+/// capture is only tried if the rest of the path fails to match under the literal. So
+/// with `/users/me` and `/users/{id}` both inserted, `/users/me` routes to the first and
+/// `/users/42` to the second. Lookups never recurse, so they're safe to run on paths
+/// with any number of segments.
+///
+/// ```rust
+/// use uhttp_request_target::{ParsedTarget, Router};
+///
+/// let mut router = Router::new();
+/// router.insert("/users/{id}", "user").unwrap();
+/// router.insert("/users/me", "me").unwrap();
+/// router.insert("/users/{id}/posts/{post}", "post").unwrap();
+///
+/// let m = router.lookup("/users/42/posts/7").unwrap().unwrap();
+/// assert_eq!(*m.value(), "post");
+/// assert_eq!(m.captures().get("id"), Some("42"));
+/// assert_eq!(m.captures().get("post"), Some("7"));
+///
+/// let t = ParsedTarget::parse("http://example.com/users/me?full=1").unwrap();
+/// assert_eq!(*router.route(&t).unwrap().unwrap().value(), "me");
+///
+/// assert!(router.lookup("/users").is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Router<T> {
+    root: Node,
+    routes: Vec<Route<T>>,
+}
+
+/// Node in the tree of path segments.
+#[derive(Clone, Debug, Default)]
+struct Node {
+    /// Children under literal segments, sorted by segment for binary search.
+    literals: Vec<(String, Node)>,
+    /// Child under a capture segment.
+    capture: Option<Box<Node>>,
+    /// Index of the route ending at this node.
+    route: Option<usize>,
+}
+
+/// Route ending at a node.
+#[derive(Clone, Debug)]
+struct Route<T> {
+    pattern: String,
+    names: Vec<String>,
+    value: T,
+}
+
+impl<T> Router<T> {
+    /// Create an empty router.
+    pub fn new() -> Self {
+        Router {
+            root: Node::default(),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Retrieve the number of routes.
+    pub fn len(&self) -> usize { self.routes.len() }
+
+    /// Check if there are no routes.
+    pub fn is_empty(&self) -> bool { self.routes.is_empty() }
+
+    /// Insert a route with the given pattern and value.
+    ///
+    /// The pattern is parsed like `RoutePattern::parse`, and errors are returned in the
+    /// same way. A pattern that matches exactly the same paths as an existing route,
+    /// even with different capture names, replaces that route and returns its value.
+    pub fn insert(&mut self, pattern: &str, value: T) -> Result<Option<T>, ParseError> {
+        let parsed = RoutePattern::parse(pattern)?;
+        let mut node = &mut self.root;
+
+        for seg in path_segments(pattern) {
+            node = if seg.starts_with('{') {
+                node.capture.get_or_insert_with(Box::default)
+            } else {
+                let idx = match node.literals.binary_search_by(|(lit, _)| (**lit).cmp(seg)) {
+                    Ok(idx) => idx,
+                    Err(idx) => {
+                        node.literals.insert(idx, (seg.to_string(), Node::default()));
+                        idx
+                    },
+                };
+
+                &mut node.literals[idx].1
+            };
+        }
+
+        let route = Route {
+            pattern: pattern.to_string(),
+            names: parsed.capture_names().map(ToString::to_string).collect(),
+            value,
+        };
+
+        match node.route {
+            Some(idx) => Ok(Some(core::mem::replace(&mut self.routes[idx], route).value)),
+            None => {
+                node.route = Some(self.routes.len());
+                self.routes.push(route);
+                Ok(None)
+            },
+        }
+    }
+
+    /// Route the given path, returning the matched route with its decoded captures.
+    ///
+    /// This returns `None` if no route matches. As with `RoutePattern::captures`, a
+    /// captured segment that doesn't decode to UTF-8 gives an error at its offset in the
+    /// path. As with `path_segments`, the path ends at any `#` fragment, and `route`
+    /// should be used for a whole target with a query.
+    pub fn lookup<'r, 'a>(&'r self, path: &'a str)
+        -> Option<Result<RouteMatch<'r, 'a, T>, ParseError>>
+    {
+        let segs: Vec<&'a str> = path_segments(path).collect();
+        let decoded: Vec<Cow<[u8]>> = segs.iter()
+            .map(|seg| percent_decode(seg, InvalidEscapePolicy::PassThrough)
+                .unwrap_or(Cow::Borrowed(seg.as_bytes())))
+            .collect();
+
+        let mut stack = Vec::with_capacity(segs.len() + 1);
+        let mut captures = Vec::new();
+
+        stack.push(Frame { node: &self.root, next: Step::Literal, captured: false });
+
+        loop {
+            let depth = stack.len().checked_sub(1)?;
+            let frame = &mut stack[depth];
+            let node = frame.node;
+
+            let child = match frame.next {
+                Step::Literal if depth == segs.len() => {
+                    if let Some(idx) = node.route {
+                        return Some(self.matched(idx, path, captures));
+                    }
+
+                    frame.next = Step::Done;
+                    None
+                },
+                Step::Literal => {
+                    frame.next = Step::Capture;
+
+                    node.literals
+                        .binary_search_by(|(lit, _)| lit.as_bytes().cmp(&decoded[depth]))
+                        .ok()
+                        .map(|idx| (&node.literals[idx].1, false))
+                },
+                Step::Capture => {
+                    frame.next = Step::Done;
+
+                    match node.capture {
+                        Some(ref child) if !segs[depth].is_empty() => {
+                            captures.push(segs[depth]);
+                            Some((&**child, true))
+                        },
+                        _ => None,
+                    }
+                },
+                Step::Done => {
+                    if frame.captured {
+                        captures.pop();
+                    }
+
+                    stack.pop();
+                    None
+                },
+            };
+
+            if let Some((node, captured)) = child {
+                stack.push(Frame { node, next: Step::Literal, captured });
+            }
+        }
+    }
+
+    /// Route the path of the given target like `lookup`.
+    ///
+    /// An empty absolute-form path is treated like `/`, and authority-form and
+    /// asterisk-form targets, which have no path, never match.
+    pub fn route<'r, 'a>(&'r self, target: &ParsedTarget<'a>)
+        -> Option<Result<RouteMatch<'r, 'a, T>, ParseError>>
+    {
+        match *target {
+            ParsedTarget::AbsPath(ref form) => self.lookup(form.path()),
+            ParsedTarget::AbsUri(ref form) => self.lookup(form.path_or_root()),
+            ParsedTarget::Authority(_) | ParsedTarget::ServerOptions => None,
+        }
+    }
+
+    /// Build the match of the route with the given index from the given raw captures.
+    fn matched<'r, 'a>(&'r self, idx: usize, path: &'a str, captures: Vec<&'a str>)
+        -> Result<RouteMatch<'r, 'a, T>, ParseError>
+    {
+        let route = &self.routes[idx];
+        let names = route.names.iter().map(|name| &**name);
+
+        Ok(RouteMatch {
+            pattern: &route.pattern,
+            value: &route.value,
+            captures: decode_captures(path, names.zip(captures))?,
+        })
+    }
+}
+
+impl<T> Default for Router<T> {
+    fn default() -> Self { Router::new() }
+}
+
+/// Frame of the backtracking search through the tree.
+struct Frame<'r> {
+    node: &'r Node,
+    next: Step,
+    /// Whether the node was entered through a capture.
+    captured: bool,
+}
+
+/// Next child of a node to try.
+#[derive(Copy, Clone)]
+enum Step {
+    Literal,
+    Capture,
+    Done,
+}
+
+/// Route matched by `Router::lookup`.
+#[derive(Clone, Debug)]
+pub struct RouteMatch<'r, 'a, T> {
+    pattern: &'r str,
+    value: &'r T,
+    captures: Captures<'r, 'a>,
+}
+
+impl<'r, 'a, T> RouteMatch<'r, 'a, T> {
+    /// Retrieve the pattern of the matched route, as it was inserted.
+    pub fn pattern(&self) -> &'r str { self.pattern }
+
+    /// Retrieve the value of the matched route.
+    pub fn value(&self) -> &'r T { self.value }
+
+    /// Retrieve the decoded captures of the path, named by the matched route.
+    pub fn captures(&self) -> &Captures<'r, 'a> { &self.captures }
+
+    /// Consume the match, returning the decoded captures.
+    pub fn into_captures(self) -> Captures<'r, 'a> { self.captures }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ErrorKind;
+
+    fn lookup(router: &Router<u32>, path: &str) -> Option<(u32, Vec<String>)> {
+        router.lookup(path).map(|m| {
+            let m = m.unwrap();
+            let caps = m.captures().iter().map(|(_, v)| v.to_string()).collect();
+            (*m.value(), caps)
+        })
+    }
+
+    #[test]
+    fn test_router() {
+        let mut router = Router::new();
+        assert!(router.is_empty());
+
+        for (i, &pattern) in [
+            "/",
+            "/users",
+            "/users/",
+            "/users/me",
+            "/users/{id}",
+            "/users/{id}/posts",
+            "/users/{id}/posts/{post}",
+            "/users/me/posts/{post}",
+            "/files/{a}/x",
+            "/files/{b}/{c}",
+            "/caf\u{e9}",
+        ].iter().enumerate() {
+            assert_eq!(router.insert(pattern, i as u32), Ok(None));
+        }
+
+        assert_eq!(router.len(), 11);
+        assert!(!router.is_empty());
+
+        let s = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(lookup(&router, "/"), Some((0, s(&[]))));
+        assert_eq!(lookup(&router, "/users"), Some((1, s(&[]))));
+        assert_eq!(lookup(&router, "/users/"), Some((2, s(&[]))));
+        assert_eq!(lookup(&router, "/users/me"), Some((3, s(&[]))));
+        assert_eq!(lookup(&router, "/users/%6De"), Some((3, s(&[]))));
+        assert_eq!(lookup(&router, "/users/42"), Some((4, s(&["42"]))));
+        assert_eq!(lookup(&router, "/users/a%20b#c/d"), Some((4, s(&["a b"]))));
+        assert_eq!(lookup(&router, "/users/me/posts"), Some((5, s(&["me"]))));
+        assert_eq!(lookup(&router, "/users/1/posts/2"), Some((6, s(&["1", "2"]))));
+        assert_eq!(lookup(&router, "/users/me/posts/2"), Some((7, s(&["2"]))));
+        assert_eq!(lookup(&router, "/files/f/x"), Some((8, s(&["f"]))));
+        assert_eq!(lookup(&router, "/files/f/y"), Some((9, s(&["f", "y"]))));
+        assert_eq!(lookup(&router, "/caf%C3%A9"), Some((10, s(&[]))));
+
+        assert_eq!(lookup(&router, ""), None);
+        assert_eq!(lookup(&router, "//"), None);
+        assert_eq!(lookup(&router, "/user"), None);
+        assert_eq!(lookup(&router, "/users/1/posts/"), None);
+        assert_eq!(lookup(&router, "/users/1/posts/2/3"), None);
+        assert_eq!(lookup(&router, "/files//x"), None);
+        assert_eq!(lookup(&router, "/caf%E9"), None);
+
+        let m = router.lookup("/files/f/y").unwrap().unwrap();
+        assert_eq!(m.pattern(), "/files/{b}/{c}");
+        assert_eq!(m.captures().get("b"), Some("f"));
+        assert_eq!(m.into_captures().get("c"), Some("y"));
+
+        let e = router.lookup("/users/%FF/posts/1").unwrap().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 7);
+
+        let route = |s| {
+            router.route(&ParsedTarget::parse(s).unwrap()).map(|m| *m.unwrap().value())
+        };
+        assert_eq!(route("/users/me?a"), Some(3));
+        assert_eq!(route("http://x/users/me?a"), Some(3));
+        assert_eq!(route("x:443"), None);
+        assert_eq!(route("*"), None);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut router = Router::default();
+
+        assert_eq!(router.insert("/a/{x}", 1), Ok(None));
+        assert_eq!(router.insert("/a/{y}", 2), Ok(Some(1)));
+        assert_eq!(router.len(), 1);
+
+        let m = router.lookup("/a/b").unwrap().unwrap();
+        assert_eq!(*m.value(), 2);
+        assert_eq!(m.pattern(), "/a/{y}");
+        assert_eq!(m.captures().get("y"), Some("b"));
+        assert_eq!(m.captures().get("x"), None);
+
+        let e = router.insert("/a/{x", 3).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidPattern);
+        assert_eq!(router.len(), 1);
+    }
+
+    #[test]
+    fn test_backtrack() {
+        let mut router = Router::new();
+        router.insert("/a/b/c", 1).unwrap();
+        router.insert("/a/{x}/d", 2).unwrap();
+        router.insert("/{x}/{y}/{z}/e", 3).unwrap();
+
+        assert_eq!(lookup(&router, "/a/b/c"), Some((1, vec![])));
+        assert_eq!(lookup(&router, "/a/b/d").map(|m| m.0), Some(2));
+        assert_eq!(lookup(&router, "/a/b/c/e").map(|m| m.0), Some(3));
+        assert_eq!(lookup(&router, "/a/b/d/e").unwrap().1, ["a", "b", "d"]);
+        assert_eq!(lookup(&router, "/a/b/e"), None);
+
+        router.insert("/{x}", 4).unwrap();
+        assert_eq!(lookup(&router, &"/a".repeat(100_000)), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Scanning of target bytes for forbidden octets and suspicious patterns.
+
+/// Whitespace pattern that may indicate a request smuggling probe.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SuspiciousWhitespace {
+    /// An HTAB, which some parsers accept as a separator in place of SP.
+    Tab,
+    /// Two or more consecutive SP, where the request line has exactly one
+    /// [RFC7230§3.1.1].
+    RepeatedSpace,
+    /// A CR not followed by LF, which some parsers treat as a line break
+    /// [RFC7230§3.5].
+    BareCr,
+}
+
+/// Find the offset of the first space, control, or `#` octet in the given target.
+///
+/// None of these may appear literally in a well-formed request target [RFC3986§2,
+/// RFC7230§5.3], so this can be used to validate long targets up front. With the `swar`
+/// feature, the bytes are checked a machine word at a time, falling back to bytewise
+/// comparison only for the word containing a violation.
+pub fn find_forbidden_octet(s: &[u8]) -> Option<usize> {
+    find_forbidden_impl(s)
+}
+
+/// Check if the given octet is a space, control, or `#`.
+fn is_forbidden(b: u8) -> bool {
+    b <= b' ' || b == 0x7F || b == b'#'
+}
+
+/// Find the offset of the first CR or LF in the given target, whether raw or
+/// percent-encoded as `%0D` or `%0A` in either case.
+///
+/// For an encoded one, the offset is that of its `%`. These can be used to split a
+/// response or forge log lines, so servers that decode and echo targets may want to
+/// reject them up front.
+pub fn find_line_break(s: &[u8]) -> Option<usize> {
+    s.iter().enumerate().position(|(idx, &b)| match b {
+        b'\r' | b'\n' => true,
+        b'%' => matches!(s[idx + 1..], [b'0', b'd' | b'D' | b'a' | b'A', ..]),
+        _ => false,
+    })
+}
+
+/// Find the offset of the first `%` in the given target that isn't followed by two hex
+/// digits [RFC3986§2.1].
+///
+/// This catches both incomplete escapes, such as a trailing `%2`, and invalid ones, such
+/// as `%zz`, which other parsers may decode inconsistently.
+///
+/// ```rust
+/// use uhttp_request_target::find_invalid_escape;
+///
+/// assert_eq!(find_invalid_escape(b"/a%20b"), None);
+/// assert_eq!(find_invalid_escape(b"/a%2"), Some(2));
+/// assert_eq!(find_invalid_escape(b"/a%20%g0"), Some(5));
+/// ```
+pub fn find_invalid_escape(s: &[u8]) -> Option<usize> {
+    s.iter().enumerate().position(|(idx, &b)| {
+        b == b'%' && !matches!(s[idx + 1..], [hi, lo, ..]
+            if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit())
+    })
+}
+
+/// Find the offset of the first doubly percent-encoded octet in the given path or query,
+/// such as `%252e`, which decodes once to `%2e` and again to `.`.
+///
+/// A `%25` followed by two hex digits is rarely sent by legitimate clients, but is a
+/// common way to slip a character past a filter that decodes only once before passing
+/// the target to a backend that decodes again. The offset is that of the first `%`, and
+/// the hex digits may be in either case.
+///
+/// ```rust
+/// use uhttp_request_target::find_double_encoding;
+///
+/// assert_eq!(find_double_encoding(b"/a%25b"), None);
+/// assert_eq!(find_double_encoding(b"/%252e%252e/etc"), Some(1));
+/// ```
+pub fn find_double_encoding(s: &[u8]) -> Option<usize> {
+    s.windows(5).position(|w| match *w {
+        [b'%', b'2', b'5', hi, lo] => hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit(),
+        _ => false,
+    })
+}
+
+/// Find the index of the first `..` segment in the given path, whether literal or with
+/// any of its bytes percent-encoded, such as `%2e%2E` or `.%2e`.
+///
+/// Segments are separated by `/` or its encoding `%2F` in either case, since backends
+/// that decode before splitting would see a separator there. Segments are indexed from
+/// zero after any leading separator, and scanning stops at any `?` or `#`. Only `.` and
+/// `/` are decoded, so this is cheap enough to run on every request before forwarding
+/// to a backend that may resolve the traversal [RFC3986§5.2.4].
+///
+/// ```rust
+/// use uhttp_request_target::find_traversal;
+///
+/// assert_eq!(find_traversal(b"/a/b/../c"), Some(2));
+/// assert_eq!(find_traversal(b"/a/%2e%2E%2fetc"), Some(1));
+/// assert_eq!(find_traversal(b"/a/..b/c"), None);
+/// ```
+pub fn find_traversal(s: &[u8]) -> Option<usize> {
+    let mut idx = 0;
+    let mut seg = 0;
+    let mut dots = 0;
+    let mut other = false;
+
+    loop {
+        let (b, len) = match s.get(idx) {
+            None | Some(&b'?') | Some(&b'#') => (b'/', 0),
+            Some(&b'%') => match s[idx + 1..] {
+                [b'2', b'e' | b'E', ..] => (b'.', 3),
+                [b'2', b'f' | b'F', ..] => (b'/', 3),
+                _ => (b'%', 1),
+            },
+            Some(&b) => (b, 1),
+        };
+
+        match b {
+            b'/' => {
+                if dots == 2 && !other {
+                    return Some(seg);
+                }
+
+                if len == 0 {
+                    return None;
+                }
+
+                // The separator before the first segment doesn't start a new one.
+                if idx > 0 {
+                    seg += 1;
+                }
+
+                dots = 0;
+                other = false;
+            },
+            b'.' => dots += 1,
+            _ => other = true,
+        }
+
+        idx += len;
+    }
+}
+
+/// Find the offset and kind of the first suspicious whitespace pattern in the given
+/// bytes.
+///
+/// This is meant to be run on the request line around the target, or on a target with
+/// its adjacent bytes, so frontends can flag probes before forwarding to a backend that
+/// may split the line differently. A final CRLF isn't flagged.
+///
+/// ```rust
+/// use uhttp_request_target::{find_suspicious_whitespace, SuspiciousWhitespace};
+///
+/// assert_eq!(find_suspicious_whitespace(b"GET /a HTTP/1.1\r\n"), None);
+/// assert_eq!(find_suspicious_whitespace(b"GET  /a HTTP/1.1\r\n"),
+///            Some((3, SuspiciousWhitespace::RepeatedSpace)));
+/// ```
+pub fn find_suspicious_whitespace(s: &[u8]) -> Option<(usize, SuspiciousWhitespace)> {
+    s.iter().enumerate().find_map(|(idx, &b)| match (b, s.get(idx + 1)) {
+        (b'\t', _) => Some((idx, SuspiciousWhitespace::Tab)),
+        (b' ', Some(&b' ')) => Some((idx, SuspiciousWhitespace::RepeatedSpace)),
+        (b'\r', Some(&b'\n')) => None,
+        (b'\r', _) => Some((idx, SuspiciousWhitespace::BareCr)),
+        _ => None,
+    })
+}
+
+#[cfg(not(feature = "swar"))]
+fn find_forbidden_impl(s: &[u8]) -> Option<usize> {
+    s.iter().position(|&b| is_forbidden(b))
+}
+
+#[cfg(feature = "swar")]
+fn find_forbidden_impl(s: &[u8]) -> Option<usize> {
+    use core::mem::size_of;
+
+    const WORD: usize = size_of::<usize>();
+    const LO: usize = usize::MAX / 255;
+    const HI: usize = LO * 0x80;
+
+    /// Check if any byte in the given word is zero.
+    fn has_zero(x: usize) -> bool {
+        x.wrapping_sub(LO) & !x & HI != 0
+    }
+
+    /// Check if any byte in the given word is less than the given value, up to 128.
+    fn has_less(x: usize, n: u8) -> bool {
+        x.wrapping_sub(LO * n as usize) & !x & HI != 0
+    }
+
+    let mut chunks = s.chunks_exact(WORD);
+    let mut base = 0;
+
+    for chunk in &mut chunks {
+        let mut bytes = [0; WORD];
+        bytes.copy_from_slice(chunk);
+        let x = usize::from_ne_bytes(bytes);
+
+        let forbidden = has_less(x, b' ' + 1) ||
+            has_zero(x ^ (LO * 0x7F)) ||
+            has_zero(x ^ (LO * b'#' as usize));
+
+        if forbidden {
+            return chunk.iter().position(|&b| is_forbidden(b)).map(|idx| base + idx);
+        }
+
+        base += WORD;
+    }
+
+    chunks.remainder().iter().position(|&b| is_forbidden(b)).map(|idx| base + idx)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_forbidden_octet() {
+        assert_eq!(find_forbidden_octet(b""), None);
+        assert_eq!(find_forbidden_octet(b"/a/b?c=d"), None);
+        assert_eq!(find_forbidden_octet(b"/a b"), Some(2));
+        assert_eq!(find_forbidden_octet(b"/a#b"), Some(2));
+        assert_eq!(find_forbidden_octet(b"\0"), Some(0));
+        assert_eq!(find_forbidden_octet(b"/\x7f"), Some(1));
+        assert_eq!(find_forbidden_octet(b"/\x80\xff\xa0!~"), None);
+
+        let long = [b'a'; 100];
+
+        for idx in 0..long.len() {
+            for &bad in &[b' ', b'\t', b'\r', b'\n', 0x00, 0x1F, 0x7F, b'#'] {
+                let mut s = long;
+                s[idx] = bad;
+                assert_eq!(find_forbidden_octet(&s), Some(idx));
+                assert_eq!(find_forbidden_octet(&s[..idx]), None);
+            }
+        }
+
+        for b in 0..=255u8 {
+            let s = [b'a', b'a', b'a', b'a', b'a', b'a', b'a', b'a', b, b'a'];
+            assert_eq!(find_forbidden_octet(&s).is_some(), is_forbidden(b));
+            assert_eq!(find_forbidden_octet(&s[1..]).is_some(), is_forbidden(b));
+        }
+    }
+
+    #[test]
+    fn test_find_line_break() {
+        assert_eq!(find_line_break(b""), None);
+        assert_eq!(find_line_break(b"/a%0b%00%0%25%0Z?%0"), None);
+        assert_eq!(find_line_break(b"/a\rb"), Some(2));
+        assert_eq!(find_line_break(b"/a?\n"), Some(3));
+        assert_eq!(find_line_break(b"/a%0D%0A"), Some(2));
+        assert_eq!(find_line_break(b"/%0a"), Some(1));
+        assert_eq!(find_line_break(b"/%2%0d"), Some(3));
+        assert_eq!(find_line_break(b"%0A"), Some(0));
+    }
+
+    #[test]
+    fn test_find_suspicious_whitespace() {
+        use self::SuspiciousWhitespace::*;
+
+        assert_eq!(find_suspicious_whitespace(b""), None);
+        assert_eq!(find_suspicious_whitespace(b"/a/b"), None);
+        assert_eq!(find_suspicious_whitespace(b" /a "), None);
+        assert_eq!(find_suspicious_whitespace(b"GET /a HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(find_suspicious_whitespace(b"/a\tb"), Some((2, Tab)));
+        assert_eq!(find_suspicious_whitespace(b"GET /a\t HTTP/1.1"), Some((6, Tab)));
+        assert_eq!(find_suspicious_whitespace(b"/a   b"), Some((2, RepeatedSpace)));
+        assert_eq!(find_suspicious_whitespace(b"/a \t"), Some((3, Tab)));
+        assert_eq!(find_suspicious_whitespace(b"/a\rb"), Some((2, BareCr)));
+        assert_eq!(find_suspicious_whitespace(b"/a\r"), Some((2, BareCr)));
+        assert_eq!(find_suspicious_whitespace(b"/a\r\r\n"), Some((2, BareCr)));
+        assert_eq!(find_suspicious_whitespace(b"/a\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_find_invalid_escape() {
+        assert_eq!(find_invalid_escape(b""), None);
+        assert_eq!(find_invalid_escape(b"/a/b?c=d"), None);
+        assert_eq!(find_invalid_escape(b"%41%ff%Fa"), None);
+        assert_eq!(find_invalid_escape(b"%"), Some(0));
+        assert_eq!(find_invalid_escape(b"/a%"), Some(2));
+        assert_eq!(find_invalid_escape(b"/a%4"), Some(2));
+        assert_eq!(find_invalid_escape(b"/a%4g"), Some(2));
+        assert_eq!(find_invalid_escape(b"/a%g4"), Some(2));
+        assert_eq!(find_invalid_escape(b"%%41"), Some(0));
+        assert_eq!(find_invalid_escape(b"%41%"), Some(3));
+    }
+
+    #[test]
+    fn test_find_double_encoding() {
+        assert_eq!(find_double_encoding(b""), None);
+        assert_eq!(find_double_encoding(b"/a%2e%2E"), None);
+        assert_eq!(find_double_encoding(b"/a%25"), None);
+        assert_eq!(find_double_encoding(b"/a%25z1"), None);
+        assert_eq!(find_double_encoding(b"/a%252"), None);
+        assert_eq!(find_double_encoding(b"%252e"), Some(0));
+        assert_eq!(find_double_encoding(b"/a?b=%252F"), Some(5));
+        assert_eq!(find_double_encoding(b"/a%2525"), Some(2));
+        assert_eq!(find_double_encoding(b"/%25zz%25fF"), Some(6));
+    }
+
+    #[test]
+    fn test_find_traversal() {
+        assert_eq!(find_traversal(b""), None);
+        assert_eq!(find_traversal(b"/"), None);
+        assert_eq!(find_traversal(b"/a/b/c"), None);
+        assert_eq!(find_traversal(b"/a/./b"), None);
+        assert_eq!(find_traversal(b"/a/.../b"), None);
+        assert_eq!(find_traversal(b"/a/b..c/.d/e."), None);
+        assert_eq!(find_traversal(b"/a/%2e%2e%41"), None);
+        assert_eq!(find_traversal(b"/a/%2"), None);
+        assert_eq!(find_traversal(b"/a?b=/../"), None);
+        assert_eq!(find_traversal(b"/a#/../"), None);
+        assert_eq!(find_traversal(b".."), Some(0));
+        assert_eq!(find_traversal(b"/.."), Some(0));
+        assert_eq!(find_traversal(b"/a/.."), Some(1));
+        assert_eq!(find_traversal(b"/a/..?b"), Some(1));
+        assert_eq!(find_traversal(b"/a/../b"), Some(1));
+        assert_eq!(find_traversal(b"a/b/../c"), Some(2));
+        assert_eq!(find_traversal(b"/a/%2e%2e/b"), Some(1));
+        assert_eq!(find_traversal(b"/a/%2E./b"), Some(1));
+        assert_eq!(find_traversal(b"/a/.%2e/b"), Some(1));
+        assert_eq!(find_traversal(b"/a/..%2fb"), Some(1));
+        assert_eq!(find_traversal(b"/a%2F..%2Fb"), Some(1));
+        assert_eq!(find_traversal(b"//../b"), Some(1));
+    }
+}
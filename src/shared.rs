@@ -0,0 +1,107 @@
+//! Request targets backed by reference-counted `Bytes` buffers.
+
+use core::ops::Range;
+use core::str;
+
+use bytes::Bytes;
+
+use {ErrorKind, ParseError, ParsedTarget, RequestTarget, TargetSpans};
+
+/// Parsed request target that shares ownership of its underlying buffer.
+///
+/// Cloning this only bumps the reference count of the buffer, and each component can
+/// be split off as its own `Bytes` handle without copying.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct BytesTarget {
+    buf: Bytes,
+    spans: TargetSpans,
+}
+
+impl BytesTarget {
+    /// Parse the target held in the given buffer.
+    ///
+    /// Return `ErrorKind::InvalidUtf8` if the buffer doesn't hold a UTF-8 string.
+    pub fn new(buf: Bytes) -> Result<Self, ParseError> {
+        let spans = match str::from_utf8(&buf) {
+            Ok(s) => TargetSpans::parse(s)?,
+            Err(e) => {
+                return Err(ParseError::at(ErrorKind::InvalidUtf8, &buf, e.valid_up_to()))
+            },
+        };
+
+        Ok(BytesTarget { buf, spans })
+    }
+
+    /// Retrieve the entire target string.
+    pub fn as_str(&self) -> &str {
+        // The buffer was checked to be UTF-8 on construction and is immutable.
+        unsafe { str::from_utf8_unchecked(&self.buf) }
+    }
+
+    /// Retrieve the form of the target.
+    pub fn kind(&self) -> RequestTarget { self.spans.kind }
+
+    /// Retrieve the byte ranges of each component.
+    pub fn spans(&self) -> &TargetSpans { &self.spans }
+
+    /// Split the target into borrowed components.
+    pub fn parsed(&self) -> ParsedTarget<'_> { ParsedTarget::split(self.as_str(), self.kind()) }
+
+    /// Retrieve a handle to the scheme of an absolute-form target.
+    pub fn scheme(&self) -> Option<Bytes> { self.slice(&self.spans.scheme) }
+
+    /// Retrieve a handle to the authority of an absolute-form or authority-form target.
+    pub fn authority(&self) -> Option<Bytes> { self.slice(&self.spans.authority) }
+
+    /// Retrieve a handle to the path of an origin-form or absolute-form target.
+    pub fn path(&self) -> Option<Bytes> { self.slice(&self.spans.path) }
+
+    /// Retrieve a handle to the query, if any.
+    pub fn query(&self) -> Option<Bytes> { self.slice(&self.spans.query) }
+
+    /// Retrieve a handle to the entire target.
+    pub fn as_bytes(&self) -> &Bytes { &self.buf }
+
+    /// Consume the target, returning the underlying buffer.
+    pub fn into_bytes(self) -> Bytes { self.buf }
+
+    fn slice(&self, range: &Option<Range<usize>>) -> Option<Bytes> {
+        range.as_ref().map(|r| self.buf.slice(r.clone()))
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for BytesTarget {
+    fn format(&self, fmt: defmt::Formatter) { defmt::write!(fmt, "{=str}", self.as_str()) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytes_target() {
+        let t = BytesTarget::new(Bytes::from_static(b"http://a.com/b?c")).unwrap();
+        assert_eq!(t.kind(), RequestTarget::AbsUri);
+        assert_eq!(t.as_str(), "http://a.com/b?c");
+        assert_eq!(t.scheme(), Some(Bytes::from_static(b"http")));
+        assert_eq!(t.authority(), Some(Bytes::from_static(b"a.com")));
+        assert_eq!(t.path(), Some(Bytes::from_static(b"/b")));
+        assert_eq!(t.query(), Some(Bytes::from_static(b"c")));
+        assert_eq!(t.parsed(), ParsedTarget::parse("http://a.com/b?c").unwrap());
+
+        let t2 = t.clone();
+        assert_eq!(t2.as_bytes().as_ptr(), t.as_bytes().as_ptr());
+
+        let t = BytesTarget::new(Bytes::from("/a".to_string())).unwrap();
+        assert_eq!(t.path(), Some(Bytes::from_static(b"/a")));
+        assert_eq!(t.query(), None);
+        assert_eq!(t.scheme(), None);
+        assert_eq!(t.into_bytes(), Bytes::from_static(b"/a"));
+
+        assert_eq!(BytesTarget::new(Bytes::from_static(b"a/b")).map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+        assert_eq!(BytesTarget::new(Bytes::from_static(b"/\xff")).map_err(|e| e.kind()),
+            Err(ErrorKind::InvalidUtf8));
+    }
+}
@@ -0,0 +1,114 @@
+//! Offset-based decomposition of request targets.
+
+use core::ops::Range;
+
+use {ParseError, ParsedTarget, RequestTarget};
+use form::offset;
+
+/// Byte ranges of the components of a request target within its buffer.
+///
+/// This carries the same information as `ParsedTarget` but doesn't borrow the buffer,
+/// so it can be stored alongside a fixed receive buffer and used to index into it later.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TargetSpans {
+    /// Form of the target.
+    pub kind: RequestTarget,
+    /// Scheme of an absolute-form target.
+    pub scheme: Option<Range<usize>>,
+    /// Authority of an absolute-form or authority-form target.
+    pub authority: Option<Range<usize>>,
+    /// Path of an origin-form or absolute-form target.
+    pub path: Option<Range<usize>>,
+    /// Query of an origin-form or absolute-form target, if any.
+    pub query: Option<Range<usize>>,
+}
+
+impl TargetSpans {
+    /// Parse the given target into component ranges relative to its start.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        ParsedTarget::parse(s).map(|t| t.spans())
+    }
+}
+
+impl<'a> ParsedTarget<'a> {
+    /// Compute the byte ranges of each component relative to the start of the target.
+    pub fn spans(&self) -> TargetSpans {
+        let base = self.as_str();
+        let span = |part: &str| Some(offset(base, part)..offset(base, part) + part.len());
+
+        let mut spans = TargetSpans {
+            kind: self.kind(),
+            scheme: None,
+            authority: None,
+            path: None,
+            query: None,
+        };
+
+        match *self {
+            ParsedTarget::AbsPath(ref form) => {
+                spans.path = span(form.path());
+                spans.query = form.query().and_then(span);
+            },
+            ParsedTarget::AbsUri(ref form) => {
+                spans.scheme = span(form.scheme());
+                spans.authority = span(form.authority());
+                spans.path = span(form.path());
+                spans.query = form.query().and_then(span);
+            },
+            ParsedTarget::Authority(ref form) => {
+                spans.authority = span(form.as_str());
+            },
+            ParsedTarget::ServerOptions => {},
+        }
+
+        spans
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ErrorKind;
+
+    #[test]
+    fn test_spans() {
+        let buf = "/where?q=now";
+        let s = TargetSpans::parse(buf).unwrap();
+        assert_eq!(s.kind, RequestTarget::AbsPath);
+        assert_eq!(s.scheme, None);
+        assert_eq!(s.authority, None);
+        assert_eq!(s.path, Some(0..6));
+        assert_eq!(s.query, Some(7..12));
+        assert_eq!(&buf[s.query.unwrap()], "q=now");
+
+        let s = TargetSpans::parse("/a?").unwrap();
+        assert_eq!(s.path, Some(0..2));
+        assert_eq!(s.query, Some(3..3));
+
+        let buf = "https://example.com:443/a/b?c";
+        let s = TargetSpans::parse(buf).unwrap();
+        assert_eq!(s.kind, RequestTarget::AbsUri);
+        assert_eq!(&buf[s.scheme.unwrap()], "https");
+        assert_eq!(&buf[s.authority.unwrap()], "example.com:443");
+        assert_eq!(&buf[s.path.unwrap()], "/a/b");
+        assert_eq!(&buf[s.query.unwrap()], "c");
+
+        let s = TargetSpans::parse("http://a").unwrap();
+        assert_eq!(s.path, Some(8..8));
+        assert_eq!(s.query, None);
+
+        let s = TargetSpans::parse("example.com:80").unwrap();
+        assert_eq!(s.kind, RequestTarget::Authority);
+        assert_eq!(s.authority, Some(0..14));
+        assert_eq!(s.path, None);
+
+        let s = TargetSpans::parse("*").unwrap();
+        assert_eq!(s.kind, RequestTarget::ServerOptions);
+        assert_eq!(s.authority, None);
+        assert_eq!(s.path, None);
+
+        assert_eq!(TargetSpans::parse("a/b").map_err(|e| e.kind()),
+            Err(ErrorKind::SlashInAuthority));
+    }
+}
@@ -0,0 +1,260 @@
+//! Incremental classification of targets that arrive in chunks.
+
+use {ErrorKind, ParseError, RequestTarget, eq_ignore_case, is_http, is_scheme_byte,
+     starts_with_ignore_case, starts_with_whitespace, trailing_whitespace_len};
+
+/// Longest prefix needed to recognize a target form, `https://`.
+const HEAD_LEN: usize = 8;
+
+/// Longest UTF-8 encoding of a character.
+const TAIL_LEN: usize = 4;
+
+/// Progress of a `StreamClassifier` after being fed a chunk.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Progress {
+    /// The target hasn't been terminated yet, and the whole chunk was consumed.
+    NeedsMoreData,
+    /// The target was terminated within the chunk, with the given classification and
+    /// number of bytes consumed from the chunk, not including the delimiter.
+    Complete(RequestTarget, usize),
+}
+
+/// Push-based classifier for a target that's received across multiple reads.
+///
+/// The target ends at the first SP, CR, or LF, as with `RequestTarget::parse_prefix`,
+/// and is classified with the same rules, but only a few bytes of state are kept
+/// between chunks.
+///
+/// ```rust
+/// use uhttp_request_target::{StreamClassifier, Progress, RequestTarget};
+///
+/// let mut c = StreamClassifier::new();
+///
+/// assert_eq!(c.feed(b"/r/ru"), Ok(Progress::NeedsMoreData));
+/// assert_eq!(c.feed(b"st HTTP/1.1"), Ok(Progress::Complete(RequestTarget::AbsPath, 2)));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct StreamClassifier {
+    /// First bytes of the target.
+    head: [u8; HEAD_LEN],
+    /// Last bytes of the target, in order.
+    tail: [u8; TAIL_LEN],
+    /// Number of target bytes consumed so far.
+    len: usize,
+    /// Offset of the first slash in what may still be the authority form.
+    slash: Option<usize>,
+    /// Whether every byte so far could belong to a scheme.
+    scheme: bool,
+    /// Whether the last byte is a colon ending a scheme other than the HTTP schemes.
+    colon: bool,
+    /// Provisional form, if any bytes have been consumed.
+    kind: Option<RequestTarget>,
+    /// Final result, once the target has been terminated or rejected.
+    result: Option<Result<RequestTarget, ParseError>>,
+}
+
+impl Default for StreamClassifier {
+    fn default() -> Self { StreamClassifier::new() }
+}
+
+impl StreamClassifier {
+    /// Create a new classifier that hasn't consumed any bytes.
+    pub const fn new() -> Self {
+        StreamClassifier {
+            head: [0; HEAD_LEN],
+            tail: [0; TAIL_LEN],
+            len: 0,
+            slash: None,
+            scheme: true,
+            colon: false,
+            kind: None,
+            result: None,
+        }
+    }
+
+    /// Feed the next chunk of bytes, returning whether the target has been terminated.
+    ///
+    /// An error is returned as soon as the target is known to be invalid. Once the
+    /// target has been completed or rejected, further chunks are ignored and the same
+    /// outcome is returned, with nothing consumed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Progress, ParseError> {
+        if let Some(result) = self.result {
+            return result.map(|kind| Progress::Complete(kind, 0));
+        }
+
+        for (idx, &b) in chunk.iter().enumerate() {
+            if b == b' ' || b == b'\r' || b == b'\n' {
+                return self.finish().map(|kind| Progress::Complete(kind, idx));
+            }
+
+            if let Err(e) = self.push(b) {
+                self.result = Some(Err(e));
+                return Err(e);
+            }
+        }
+
+        Ok(Progress::NeedsMoreData)
+    }
+
+    /// Signal that no more bytes will arrive, classifying the target consumed so far.
+    pub fn finish(&mut self) -> Result<RequestTarget, ParseError> {
+        if let Some(result) = self.result {
+            return result;
+        }
+
+        let head = &self.head[..self.len.min(HEAD_LEN)];
+        let tail = &self.tail[TAIL_LEN - self.len.min(TAIL_LEN)..];
+
+        let space = trailing_whitespace_len(tail);
+
+        let result = match (self.kind, self.slash) {
+            (None, _) => Err(ParseError::new(ErrorKind::Empty, 0, None)),
+            (Some(RequestTarget::Authority), Some(idx)) => {
+                Err(ParseError::new(ErrorKind::SlashInAuthority, idx, Some(b'/')))
+            },
+            (Some(_), _) if starts_with_whitespace(head) => {
+                Err(ParseError::at(ErrorKind::SurroundingWhitespace, head, 0))
+            },
+            (Some(_), _) if space > 0 => {
+                Err(ParseError::new(ErrorKind::SurroundingWhitespace, self.len - space,
+                    Some(tail[tail.len() - space])))
+            },
+            (Some(_), _) if head == b"*" => Ok(RequestTarget::ServerOptions),
+            (Some(kind), _) => Ok(kind),
+        };
+
+        self.result = Some(result);
+        result
+    }
+
+    /// Consume the given target byte.
+    fn push(&mut self, b: u8) -> Result<(), ParseError> {
+        let offset = self.len;
+
+        if b == b'\0' {
+            return Err(ParseError::new(ErrorKind::NulByte, offset, Some(b)));
+        }
+
+        let after_scheme = self.colon;
+        let prev = &self.head[..self.len.min(HEAD_LEN)];
+
+        self.colon = self.scheme && self.len > 0 && b == b':' && !is_http(prev);
+        self.scheme &= is_scheme_byte(b, self.len == 0);
+
+        if self.len < HEAD_LEN {
+            self.head[self.len] = b;
+        }
+
+        self.tail.rotate_left(1);
+        self.tail[TAIL_LEN - 1] = b;
+        self.len = self.len.saturating_add(1);
+
+        let head = &self.head[..self.len.min(HEAD_LEN)];
+
+        // Leading whitespace can be rejected as soon as the first character is complete.
+        if self.len <= TAIL_LEN && starts_with_whitespace(head) {
+            return Err(ParseError::at(ErrorKind::SurroundingWhitespace, head, 0));
+        }
+
+        match self.kind {
+            None if b == b'/' => self.kind = Some(RequestTarget::AbsPath),
+            None => self.kind = Some(RequestTarget::Authority),
+            _ => {},
+        }
+
+        if self.kind != Some(RequestTarget::Authority) {
+            return Ok(());
+        }
+
+        if eq_ignore_case(head, b"http://") || eq_ignore_case(head, b"https://") {
+            self.kind = Some(RequestTarget::AbsUri);
+            return Ok(());
+        }
+
+        if b == b'/' && self.slash.is_none() {
+            self.slash = Some(offset);
+        }
+
+        let slash = match self.slash {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+
+        // A slash is only allowed if this may still become one of the HTTP schemes.
+        if !starts_with_ignore_case(b"http://", head) &&
+           !starts_with_ignore_case(b"https://", head)
+        {
+            let kind = if b == b'/' && after_scheme {
+                ErrorKind::UnknownScheme
+            } else {
+                ErrorKind::SlashInAuthority
+            };
+
+            return Err(ParseError::new(kind, slash, Some(b'/')));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stream_classifier() {
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.feed(b""), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b"http"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b":/"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b"/a/b"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b"\r\n"), Ok(Progress::Complete(RequestTarget::AbsUri, 0)));
+        assert_eq!(c.feed(b"/a"), Ok(Progress::Complete(RequestTarget::AbsUri, 0)));
+
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.feed(b"http:/"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b"a").map_err(|e| e.kind()), Err(ErrorKind::SlashInAuthority));
+        assert_eq!(c.feed(b" ").map_err(|e| e.kind()), Err(ErrorKind::SlashInAuthority));
+
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.feed(b"a.com:443"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.finish(), Ok(RequestTarget::Authority));
+
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.feed(b" ").map_err(|e| e.kind()), Err(ErrorKind::Empty));
+
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.finish().map_err(|e| e.kind()), Err(ErrorKind::Empty));
+
+        let mut c = StreamClassifier::new();
+        assert_eq!(c.feed(b"ftp:"), Ok(Progress::NeedsMoreData));
+        assert_eq!(c.feed(b"//a").map_err(|e| e.kind()), Err(ErrorKind::UnknownScheme));
+
+        // Check every split of each target against the one-shot classifier.
+        let targets: &[&[u8]] = &[
+            b"*", b"**", b"/", b"/a?b", b"http://a/b", b"https://a", b"http:/", b"http:",
+            b"https:/a", b"htt//", b"a:80", b"a/b", b"\xe3\x80\x80/a", b"/a\xe3\x80\x80",
+            b"/a\xc2\xa0", b"\xc2/", b"/\xc2", b"\t/", b"/a\0", b"/a\xa0", b"ftp://a",
+            b"ftp:/", b"http/", b"https:/", b"ht:/a", b"a/\t", b"*\0", b"a:\0/", b":/",
+            b"a:b/", b"h+.-:/", b"1a:/", b"httpsx://a", b"HTTP://a/b", b"HtTpS://a",
+            b"HTTP:/", b"Http/", b"HTTPS:/a", b"FTP://a",
+        ];
+
+        for &t in targets {
+            let expected = RequestTarget::classify(t);
+
+            for split in 0..=t.len() {
+                let (a, b) = t.split_at(split);
+
+                let mut c = StreamClassifier::new();
+                let result = c.feed(a).and_then(|_| c.feed(b)).and_then(|_| c.finish());
+                assert_eq!(result, expected);
+
+                let mut c = StreamClassifier::new();
+                let result = c.feed(a).and_then(|_| c.feed(b)).and_then(|_| c.feed(b" /"));
+                assert_eq!(result, expected.map(|k| Progress::Complete(k, 0)));
+            }
+        }
+    }
+}
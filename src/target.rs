@@ -0,0 +1,73 @@
+//! Borrowed target strings that are known to be classifiable.
+
+use core::fmt;
+
+use {ParseError, ParsedTarget, RequestTarget};
+
+/// Borrowed request target string that has already been successfully parsed.
+///
+/// This is to `RequestTargetBuf` as `str` is to `String`, so either can be used to look
+/// up the other in maps and sets.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct RequestTargetStr(str);
+
+impl RequestTargetStr {
+    /// Parse the given target, borrowing it if it's valid.
+    pub fn new(s: &str) -> Result<&Self, ParseError> {
+        ParsedTarget::parse(s).map(|t| t.as_target_str())
+    }
+
+    /// Wrap the given target, which must already have been parsed successfully.
+    pub(crate) fn from_str_unchecked(s: &str) -> &Self {
+        // This cast is sound because the type is a transparent wrapper around `str`.
+        unsafe { &*(s as *const str as *const RequestTargetStr) }
+    }
+
+    /// Retrieve the underlying target string.
+    pub fn as_str(&self) -> &str { &self.0 }
+
+    /// Retrieve the form of the target.
+    pub fn kind(&self) -> RequestTarget { RequestTarget::discriminate(self.0.as_bytes()) }
+
+    /// Split the target into its components.
+    pub fn parsed(&self) -> ParsedTarget<'_> { ParsedTarget::split(&self.0, self.kind()) }
+}
+
+impl AsRef<str> for RequestTargetStr {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl fmt::Debug for RequestTargetStr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt::Debug::fmt(&self.0, fmt) }
+}
+
+impl fmt::Display for RequestTargetStr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result { fmt::Display::fmt(&self.0, fmt) }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RequestTargetStr {
+    fn format(&self, fmt: defmt::Formatter) { defmt::write!(fmt, "{=str}", &self.0) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_target_str() {
+        let t = RequestTargetStr::new("/a/b?c").unwrap();
+        assert_eq!(t.as_str(), "/a/b?c");
+        assert_eq!(t.kind(), RequestTarget::AbsPath);
+        assert_eq!(t.parsed(), ParsedTarget::parse("/a/b?c").unwrap());
+
+        assert_eq!(RequestTargetStr::new("http://a/b").unwrap().kind(), RequestTarget::AbsUri);
+        assert_eq!(RequestTargetStr::new("a:80").unwrap().kind(), RequestTarget::Authority);
+        assert_eq!(RequestTargetStr::new("*").unwrap().kind(), RequestTarget::ServerOptions);
+
+        assert!(RequestTargetStr::new("").is_err());
+        assert!(RequestTargetStr::new("a/b").is_err());
+        assert!(RequestTargetStr::new("a:b").is_err());
+    }
+}
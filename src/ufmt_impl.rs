@@ -0,0 +1,281 @@
+//! `ufmt` formatting impls, available with the `ufmt` feature.
+
+use ufmt::{uDebug, uDisplay, uWrite, Formatter};
+
+use {AbsoluteForm, AuthorityForm, ErrorKind, HostKind, OriginForm, ParseError,
+     ParsedTarget, RequestTarget, RequestTargetStr, SuspiciousWhitespace};
+
+/// String that's debug-formatted in quotes, since `ufmt` has no `uDebug` impl for `str`.
+///
+/// The contents aren't escaped, to keep formatting code small.
+struct Quoted<'a>(&'a str);
+
+impl<'a> uDebug for Quoted<'a> {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str("\"")?;
+        f.write_str(self.0)?;
+        f.write_str("\"")
+    }
+}
+
+impl uDebug for RequestTarget {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(match *self {
+            RequestTarget::AbsPath => "AbsPath",
+            RequestTarget::AbsUri => "AbsUri",
+            RequestTarget::Authority => "Authority",
+            RequestTarget::ServerOptions => "ServerOptions",
+        })
+    }
+}
+
+impl uDebug for HostKind {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(match *self {
+            HostKind::RegName => "RegName",
+            HostKind::Ipv4 => "Ipv4",
+            HostKind::Ipv6 => "Ipv6",
+            HostKind::IpvFuture => "IpvFuture",
+        })
+    }
+}
+
+impl uDebug for SuspiciousWhitespace {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(match *self {
+            SuspiciousWhitespace::Tab => "Tab",
+            SuspiciousWhitespace::RepeatedSpace => "RepeatedSpace",
+            SuspiciousWhitespace::BareCr => "BareCr",
+        })
+    }
+}
+
+impl uDebug for ErrorKind {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(match *self {
+            ErrorKind::Empty => "Empty",
+            ErrorKind::SurroundingWhitespace => "SurroundingWhitespace",
+            ErrorKind::IllegalCharacter => "IllegalCharacter",
+            ErrorKind::UnknownScheme => "UnknownScheme",
+            ErrorKind::SlashInAuthority => "SlashInAuthority",
+            ErrorKind::InvalidHost => "InvalidHost",
+            ErrorKind::InvalidPort => "InvalidPort",
+            ErrorKind::InvalidPercentEncoding => "InvalidPercentEncoding",
+            ErrorKind::InvalidUtf8 => "InvalidUtf8",
+            ErrorKind::UnexpectedForm => "UnexpectedForm",
+            ErrorKind::TooLong => "TooLong",
+            ErrorKind::InvalidPath => "InvalidPath",
+            ErrorKind::InvalidQuery => "InvalidQuery",
+            ErrorKind::ControlCharacter => "ControlCharacter",
+            ErrorKind::Fragment => "Fragment",
+            ErrorKind::Userinfo => "Userinfo",
+            ErrorKind::InvalidUserinfo => "InvalidUserinfo",
+            ErrorKind::NonAscii => "NonAscii",
+            ErrorKind::EncodedLineBreak => "EncodedLineBreak",
+            ErrorKind::NulByte => "NulByte",
+            ErrorKind::PathTooLong => "PathTooLong",
+            ErrorKind::QueryTooLong => "QueryTooLong",
+            ErrorKind::HostTooLong => "HostTooLong",
+            ErrorKind::TooManySegments => "TooManySegments",
+            ErrorKind::TooManyParams => "TooManyParams",
+            ErrorKind::Backslash => "Backslash",
+            ErrorKind::InvalidIdn => "InvalidIdn",
+            ErrorKind::UnsafePathSegment => "UnsafePathSegment",
+            ErrorKind::UnresolvableReference => "UnresolvableReference",
+            ErrorKind::InvalidPattern => "InvalidPattern",
+        })
+    }
+}
+
+impl uDisplay for ErrorKind {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(self.message())
+    }
+}
+
+impl uDebug for ParseError {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.debug_struct("ParseError")?
+            .field("kind", &self.kind())?
+            .field("offset", &self.offset())?
+            .field("byte", &self.byte())?
+            .finish()
+    }
+}
+
+impl uDisplay for ParseError {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        match self.kind() {
+            ErrorKind::Empty => uDisplay::fmt(&ErrorKind::Empty, f),
+            kind => ufmt::uwrite!(f, "{} at offset {}", kind, self.offset()),
+        }
+    }
+}
+
+impl uDebug for RequestTargetStr {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        uDebug::fmt(&Quoted(self.as_str()), f)
+    }
+}
+
+impl uDisplay for RequestTargetStr {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> uDebug for ParsedTarget<'a> {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        match *self {
+            ParsedTarget::AbsPath(ref form) => f.debug_tuple("AbsPath")?.field(form)?.finish(),
+            ParsedTarget::AbsUri(ref form) => f.debug_tuple("AbsUri")?.field(form)?.finish(),
+            ParsedTarget::Authority(ref form) => {
+                f.debug_tuple("Authority")?.field(form)?.finish()
+            },
+            ParsedTarget::ServerOptions => f.write_str("ServerOptions"),
+        }
+    }
+}
+
+impl<'a> uDebug for OriginForm<'a> {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.debug_struct("OriginForm")?
+            .field("path", &Quoted(self.path()))?
+            .field("query", &self.query().map(Quoted))?
+            .finish()
+    }
+}
+
+impl<'a> uDebug for AbsoluteForm<'a> {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.debug_struct("AbsoluteForm")?
+            .field("scheme", &Quoted(self.scheme()))?
+            .field("authority", &Quoted(self.authority()))?
+            .field("path", &Quoted(self.path()))?
+            .field("query", &self.query().map(Quoted))?
+            .finish()
+    }
+}
+
+impl<'a> uDebug for AuthorityForm<'a> {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+        f.debug_struct("AuthorityForm")?
+            .field("userinfo", &self.userinfo().map(Quoted))?
+            .field("host", &Quoted(self.host()))?
+            .field("port", &self.port_str().map(Quoted))?
+            .finish()
+    }
+}
+
+/// Display each parsed type as the target string it was parsed from.
+macro_rules! display_target {
+    ($($ty:ident),*) => {$(
+        impl<'a> uDisplay for $ty<'a> {
+            fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>)
+                -> Result<(), W::Error>
+            {
+                f.write_str(self.as_str())
+            }
+        }
+    )*}
+}
+
+display_target!(ParsedTarget, OriginForm, AbsoluteForm, AuthorityForm);
+
+#[cfg(feature = "alloc")]
+mod owned {
+    use ufmt::{uDebug, uDisplay, uWrite, Formatter};
+
+    use super::Quoted;
+    use RequestTargetBuf;
+
+    impl uDebug for RequestTargetBuf {
+        fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+            uDebug::fmt(&Quoted(self.as_str()), f)
+        }
+    }
+
+    impl uDisplay for RequestTargetBuf {
+        fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+            f.write_str(self.as_str())
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+mod fixed {
+    use ufmt::{uDebug, uDisplay, uWrite, Formatter};
+
+    use super::Quoted;
+    use HeaplessTarget;
+
+    impl<const N: usize> uDebug for HeaplessTarget<N> {
+        fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+            uDebug::fmt(&Quoted(self.as_str()), f)
+        }
+    }
+
+    impl<const N: usize> uDisplay for HeaplessTarget<N> {
+        fn fmt<W: uWrite + ?Sized>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> {
+            f.write_str(self.as_str())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Buf(String);
+
+    impl uWrite for Buf {
+        type Error = ();
+
+        fn write_str(&mut self, s: &str) -> Result<(), ()> {
+            self.0.push_str(s);
+            Ok(())
+        }
+    }
+
+    fn debug<T: uDebug + ?Sized>(t: &T) -> String {
+        let mut buf = Buf(String::new());
+        ufmt::uwrite!(&mut buf, "{:?}", t).unwrap();
+        buf.0
+    }
+
+    fn display<T: uDisplay + ?Sized>(t: &T) -> String {
+        let mut buf = Buf(String::new());
+        ufmt::uwrite!(&mut buf, "{}", t).unwrap();
+        buf.0
+    }
+
+    #[test]
+    fn test_ufmt() {
+        assert_eq!(debug(&RequestTarget::AbsUri), "AbsUri");
+        assert_eq!(debug(&ErrorKind::TooLong), "TooLong");
+        assert_eq!(debug(&HostKind::IpvFuture), "IpvFuture");
+        assert_eq!(display(&ErrorKind::TooLong), ErrorKind::TooLong.message());
+
+        let e = RequestTarget::classify(b"a/b").unwrap_err();
+        assert_eq!(debug(&e),
+            "ParseError { kind: SlashInAuthority, offset: 1, byte: Some(47) }");
+        assert_eq!(display(&e), "authority-form target contains a slash at offset 1");
+        let e = RequestTarget::classify(b"").unwrap_err();
+        assert_eq!(display(&e), "target is empty");
+
+        let t = ParsedTarget::parse("/a?b").unwrap();
+        assert_eq!(display(&t), "/a?b");
+        assert_eq!(debug(&t),
+            r#"AbsPath(OriginForm { path: "/a", query: Some("b") })"#);
+        assert_eq!(debug(&ParsedTarget::ServerOptions), "ServerOptions");
+
+        let t = AuthorityForm::parse("a:80").unwrap();
+        assert_eq!(display(&t), "a:80");
+        assert_eq!(debug(&t),
+            r#"AuthorityForm { userinfo: None, host: "a", port: Some("80") }"#);
+
+        let t = RequestTargetStr::new("http://a").unwrap();
+        assert_eq!(display(t), "http://a");
+        assert_eq!(debug(t), r#""http://a""#);
+    }
+}
@@ -0,0 +1,85 @@
+//! Unicode normalization of decoded target components.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use ParseError;
+use decode::{percent_decode_str, InvalidEscapePolicy};
+
+/// Percent-decode the given path or query component like `percent_decode_str`, then
+/// convert the result to Unicode Normalization Form C, available with the `unicode`
+/// feature.
+///
+/// The same text can be encoded with different sequences of code points, such as `é` as
+/// either U+00E9 or `e` followed by the combining U+0301, and clients don't agree on which
+/// to send, so internationalized paths should be compared and routed on their NFC form.
+/// Since decoding can produce `/` from `%2F`, this should be applied to each segment of a
+/// path rather than the whole path when segment boundaries matter. The component itself
+/// is borrowed if it has no escapes and is already normalized.
+///
+/// ```rust
+/// use uhttp_request_target::{percent_decode_nfc, InvalidEscapePolicy};
+///
+/// let a = percent_decode_nfc("caf%C3%A9", InvalidEscapePolicy::Error).unwrap();
+/// let b = percent_decode_nfc("cafe%CC%81", InvalidEscapePolicy::Error).unwrap();
+/// assert_eq!(a, "caf\u{e9}");
+/// assert_eq!(a, b);
+/// ```
+pub fn percent_decode_nfc(s: &str, policy: InvalidEscapePolicy)
+    -> Result<Cow<'_, str>, ParseError>
+{
+    let decoded = percent_decode_str(s, policy)?;
+
+    if is_nfc(&decoded) {
+        return Ok(decoded);
+    }
+
+    Ok(Cow::Owned(decoded.nfc().collect::<String>()))
+}
+
+/// Check if the given decoded string is in Unicode Normalization Form C, available with
+/// the `unicode` feature.
+pub fn is_nfc(s: &str) -> bool {
+    match is_nfc_quick(s.chars()) {
+        IsNormalized::Yes => true,
+        IsNormalized::No => false,
+        IsNormalized::Maybe => s.chars().eq(s.nfc()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ErrorKind;
+
+    #[test]
+    fn test_percent_decode_nfc() {
+        use self::InvalidEscapePolicy::*;
+
+        assert!(matches!(percent_decode_nfc("/a/b", Error), Ok(Cow::Borrowed("/a/b"))));
+        assert!(matches!(percent_decode_nfc("caf\u{e9}", Error), Ok(Cow::Borrowed(_))));
+        assert!(matches!(percent_decode_nfc("cafe\u{301}", Error),
+                         Ok(Cow::Owned(ref s)) if s == "caf\u{e9}"));
+        assert_eq!(percent_decode_nfc("caf%C3%A9", Error).unwrap(), "caf\u{e9}");
+        assert_eq!(percent_decode_nfc("cafe%CC%81", Error).unwrap(), "caf\u{e9}");
+        assert_eq!(percent_decode_nfc("%E1%84%80%E1%85%A1", Error).unwrap(), "\u{ac00}");
+        assert_eq!(percent_decode_nfc("a%2Fe%CC%81", Error).unwrap(), "a/\u{e9}");
+        assert_eq!(percent_decode_nfc("e%CC", Replace).unwrap(), "e\u{fffd}");
+
+        let e = percent_decode_nfc("/e%CC", Error).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(e.offset(), 2);
+    }
+
+    #[test]
+    fn test_is_nfc() {
+        assert!(is_nfc(""));
+        assert!(is_nfc("/caf\u{e9}"));
+        assert!(is_nfc("\u{ac00}"));
+        assert!(!is_nfc("cafe\u{301}"));
+        assert!(!is_nfc("\u{1100}\u{1161}"));
+        assert!(!is_nfc("\u{212b}"));
+    }
+}